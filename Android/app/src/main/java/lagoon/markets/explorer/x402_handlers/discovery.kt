@@ -48,8 +48,18 @@ fun PerformDiscovery(navController: NavController, x402Path: String) {
     LaunchedEffect(Unit) {
 
         try {
-            outcome.value = rustffiDiscoverResources(x402Path)
+            val firstPage = rustffiDiscoverResources(x402Path)
+            outcome.value = firstPage.resources
             appLog("Fetched resources: ${outcome.value} ")
+
+            // The pager stops after the most pages a discovery URL can have
+            while (firstPage.pager.hasMore()) {
+                val nextPage = firstPage.pager.nextPage()
+                if (nextPage.isEmpty()) break
+
+                outcome.value = outcome.value.orEmpty() + nextPage
+                appLog("Fetched ${nextPage.size} more resources")
+            }
         } catch (error: Exception) {
             appLog("Error discovering resources: ${error.toString()}")
         }
@@ -0,0 +1,196 @@
+use base64ct::{Base64UrlUnpadded, Encoding};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::{Deserialize, Serialize};
+
+/// Grants access to a paid resource until `expires_at` without paying again.
+///
+/// Issued by the resource server after it settles a payment. The token is
+/// `<base64url JSON claims>.<base64url signature>` signed by the server key.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402AccessToken {
+    pub resource: String,
+    /// The address that paid for the resource
+    pub payer: String,
+    /// The signature of the payment transaction
+    pub transaction: String,
+    /// Unix timestamp in seconds
+    pub issued_at: u64,
+    /// Unix timestamp in seconds
+    pub expires_at: u64,
+}
+
+impl X402AccessToken {
+    /// The query parameter for clients that cannot set headers, like `EventSource`
+    pub const QUERY_PARAM: &str = "access_token";
+
+    pub fn new(
+        resource: &str,
+        payer: &str,
+        transaction: &str,
+        issued_at: u64,
+        duration_secs: u64,
+    ) -> Self {
+        Self {
+            resource: resource.to_string(),
+            payer: payer.to_string(),
+            transaction: transaction.to_string(),
+            issued_at,
+            expires_at: issued_at.saturating_add(duration_secs),
+        }
+    }
+
+    /// Reads the claims without checking the signature, for clients that store the token
+    pub fn decode(token: &str) -> Result<Self, X402AccessTokenError> {
+        let (claims, _) = token
+            .trim()
+            .split_once('.')
+            .ok_or(X402AccessTokenError::InvalidToken)?;
+
+        let claims =
+            Base64UrlUnpadded::decode_vec(claims).or(Err(X402AccessTokenError::InvalidToken))?;
+
+        serde_json::from_slice(&claims).or(Err(X402AccessTokenError::InvalidToken))
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// `url` with the token as the [Self::QUERY_PARAM] query parameter
+    pub fn append_to_url(url: &str, token: &str) -> String {
+        let separator = if url.contains('?') { "&" } else { "?" };
+
+        String::from(url) + separator + Self::QUERY_PARAM + "=" + token
+    }
+}
+
+/// Issues and verifies the [X402AccessToken]s of a resource server
+pub struct X402AccessTokenSigner {
+    signing_key: SigningKey,
+}
+
+impl X402AccessTokenSigner {
+    pub fn new(secret_key: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret_key),
+        }
+    }
+
+    pub fn sign(&self, token: &X402AccessToken) -> String {
+        // Only strings and integers are serialized which cannot fail
+        let claims = Base64UrlUnpadded::encode_string(
+            serde_json::to_string(token).unwrap_or_default().as_bytes(),
+        );
+        let signature = self.signing_key.sign(claims.as_bytes());
+
+        claims + "." + Base64UrlUnpadded::encode_string(&signature.to_bytes()).as_str()
+    }
+
+    /// Checks that the server signed the token for `resource` and that it has not expired at `now`
+    pub fn verify(
+        &self,
+        token: &str,
+        resource: &str,
+        now: u64,
+    ) -> Result<X402AccessToken, X402AccessTokenError> {
+        let token = token.trim();
+        let (claims, signature) = token
+            .split_once('.')
+            .ok_or(X402AccessTokenError::InvalidToken)?;
+
+        let mut signature_bytes = [0u8; 64];
+        Base64UrlUnpadded::decode(signature, &mut signature_bytes)
+            .ok()
+            .filter(|decoded| decoded.len() == 64)
+            .ok_or(X402AccessTokenError::InvalidSignature)?;
+
+        self.signing_key
+            .verifying_key()
+            .verify(claims.as_bytes(), &Signature::from_bytes(&signature_bytes))
+            .or(Err(X402AccessTokenError::InvalidSignature))?;
+
+        let access_token = X402AccessToken::decode(token)?;
+
+        if access_token.resource != resource {
+            return Err(X402AccessTokenError::WrongResource);
+        }
+
+        if access_token.is_expired(now) {
+            return Err(X402AccessTokenError::Expired);
+        }
+
+        Ok(access_token)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, thiserror::Error)]
+pub enum X402AccessTokenError {
+    /// The token is not `<base64url JSON claims>.<base64url signature>`
+    #[error("The access token is not a base64url encoded token")]
+    InvalidToken,
+    /// The token was not signed by the resource server
+    #[error("The access token was not issued by this server")]
+    InvalidSignature,
+    /// The token grants access to another resource
+    #[error("The access token is for another resource")]
+    WrongResource,
+    /// The access granted by the token is over
+    #[error("The access token has expired")]
+    Expired,
+}
+
+#[cfg(test)]
+mod x402_access_token_sanity {
+    use super::*;
+
+    const NOW: u64 = 1_760_000_000;
+    const RESOURCE: &str = "https://lagoon.markets/x402/voting";
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = X402AccessTokenSigner::new(&[7u8; 32]);
+        let access_token = X402AccessToken::new(RESOURCE, "payer", "signature", NOW, 3600);
+        let token = signer.sign(&access_token);
+
+        assert_eq!(X402AccessToken::decode(&token), Ok(access_token.clone()));
+        assert_eq!(signer.verify(&token, RESOURCE, NOW + 10), Ok(access_token));
+        assert_eq!(
+            signer.verify(&token, RESOURCE, NOW + 3600),
+            Err(X402AccessTokenError::Expired)
+        );
+        assert_eq!(
+            signer.verify(&token, "https://lagoon.markets/latest_newsletter", NOW),
+            Err(X402AccessTokenError::WrongResource)
+        );
+        assert_eq!(
+            X402AccessTokenSigner::new(&[8u8; 32]).verify(&token, RESOURCE, NOW),
+            Err(X402AccessTokenError::InvalidSignature)
+        );
+
+        // The claims cannot be changed without the server key
+        let (_, signature) = token.split_once('.').unwrap();
+        let extended = X402AccessToken::new(RESOURCE, "payer", "signature", NOW, 360_000);
+        let forged = signer
+            .sign(&extended)
+            .split_once('.')
+            .unwrap()
+            .0
+            .to_string()
+            + "."
+            + signature;
+        assert_eq!(
+            signer.verify(&forged, RESOURCE, NOW),
+            Err(X402AccessTokenError::InvalidSignature)
+        );
+
+        assert_eq!(
+            X402AccessToken::append_to_url(RESOURCE, &token),
+            String::from(RESOURCE) + "?access_token=" + &token
+        );
+        assert_eq!(
+            X402AccessToken::decode("not a token"),
+            Err(X402AccessTokenError::InvalidToken)
+        );
+    }
+}
@@ -1,4 +1,8 @@
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+use core::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
 pub enum SolanaChain {
     MainnetBeta,
     Testnet,
@@ -8,8 +12,245 @@ pub enum SolanaChain {
 }
 
 impl SolanaChain {
-    pub const MAINNET_X402_ID: &str = "solana-mainnet";
+    /// The x402 specification names mainnet `solana` without a cluster suffix
+    pub const MAINNET_X402_ID: &str = "solana";
+    /// Also accepted for mainnet when parsing, for symmetry with the other clusters
+    pub const MAINNET_ALIAS_X402_ID: &str = "solana-mainnet";
     pub const TESTNET_X402_ID: &str = "solana-testnet";
     pub const DEVNET_X402_ID: &str = "solana-devnet";
     pub const LOCALNET_X402_ID: &str = "solana-localnet";
+
+    pub const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+    pub const TESTNET_GENESIS_HASH: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+    pub const DEVNET_GENESIS_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+
+    /// The CAIP-2 namespace of Solana chains
+    pub const CAIP2_NAMESPACE: &str = "solana";
+    /// CAIP-2 references are the first 32 characters of the genesis hash
+    pub const CAIP2_REFERENCE_LENGTH: usize = 32;
+
+    pub const ALL: [Self; 4] = [
+        Self::MainnetBeta,
+        Self::Testnet,
+        Self::Devnet,
+        Self::Localnet,
+    ];
+
+    /// The network identifier used in x402 payment requirements like `solana-devnet`
+    pub fn x402_id(&self) -> &'static str {
+        match self {
+            Self::MainnetBeta => Self::MAINNET_X402_ID,
+            Self::Testnet => Self::TESTNET_X402_ID,
+            Self::Devnet => Self::DEVNET_X402_ID,
+            Self::Localnet => Self::LOCALNET_X402_ID,
+        }
+    }
+
+    /// The genesis hash of the cluster. A local validator creates a new one on every reset
+    pub fn genesis_hash(&self) -> Option<&'static str> {
+        match self {
+            Self::MainnetBeta => Some(Self::MAINNET_GENESIS_HASH),
+            Self::Testnet => Some(Self::TESTNET_GENESIS_HASH),
+            Self::Devnet => Some(Self::DEVNET_GENESIS_HASH),
+            Self::Localnet => None,
+        }
+    }
+
+    /// The CAIP-2 chain id like `solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1`
+    pub fn caip2_id(&self) -> Option<String> {
+        self.genesis_hash().map(|genesis_hash| {
+            String::from(Self::CAIP2_NAMESPACE)
+                + ":"
+                + &genesis_hash[..Self::CAIP2_REFERENCE_LENGTH]
+        })
+    }
+
+    /// The cluster name used by the Solana CLI and explorers
+    pub fn cluster(&self) -> &'static str {
+        match self {
+            Self::MainnetBeta => "mainnet-beta",
+            Self::Testnet => "testnet",
+            Self::Devnet => "devnet",
+            Self::Localnet => "localnet",
+        }
+    }
+
+    /// The public RPC endpoint of the cluster
+    pub fn default_rpc_url(&self) -> &'static str {
+        match self {
+            Self::MainnetBeta => "https://api.mainnet-beta.solana.com",
+            Self::Testnet => "https://api.testnet.solana.com",
+            Self::Devnet => "https://api.devnet.solana.com",
+            Self::Localnet => "http://127.0.0.1:8899",
+        }
+    }
+
+    /// The public websocket endpoint of the cluster
+    pub fn default_websocket_url(&self) -> &'static str {
+        match self {
+            Self::MainnetBeta => "wss://api.mainnet-beta.solana.com",
+            Self::Testnet => "wss://api.testnet.solana.com",
+            Self::Devnet => "wss://api.devnet.solana.com",
+            Self::Localnet => "ws://127.0.0.1:8900",
+        }
+    }
+
+    pub fn is_mainnet(&self) -> bool {
+        *self == Self::MainnetBeta
+    }
+
+    /// Parses a CAIP-2 id, accepting the full genesis hash as the reference
+    fn from_caip2(reference: &str) -> Option<Self> {
+        if reference.len() < Self::CAIP2_REFERENCE_LENGTH {
+            return None;
+        }
+
+        Self::ALL.into_iter().find(|chain| {
+            chain
+                .genesis_hash()
+                .is_some_and(|genesis_hash| genesis_hash.starts_with(reference))
+        })
+    }
+}
+
+impl FromStr for SolanaChain {
+    type Err = SolanaChainError;
+
+    /// Parses x402 ids like `solana` or `solana-devnet`, CAIP-2 ids like `solana:<genesis hash>`
+    /// and cluster names like `mainnet-beta` or `devnet`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+
+        if let Some((namespace, reference)) = value.split_once(':') {
+            return namespace
+                .eq_ignore_ascii_case(Self::CAIP2_NAMESPACE)
+                .then(|| Self::from_caip2(reference))
+                .flatten()
+                .ok_or_else(|| SolanaChainError::UnknownCaip2Id(value.to_string()));
+        }
+
+        let chain = Self::ALL.into_iter().find(|chain| {
+            value.eq_ignore_ascii_case(chain.x402_id())
+                || value.eq_ignore_ascii_case(chain.cluster())
+        });
+
+        match chain {
+            Some(chain) => Ok(chain),
+            None if value.eq_ignore_ascii_case("mainnet")
+                || value.eq_ignore_ascii_case(Self::MAINNET_ALIAS_X402_ID) =>
+            {
+                Ok(Self::MainnetBeta)
+            }
+            None => Err(SolanaChainError::UnknownChain(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for SolanaChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.x402_id())
+    }
+}
+
+impl Serialize for SolanaChain {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.x402_id())
+    }
+}
+
+impl<'de> Deserialize<'de> for SolanaChain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, thiserror::Error)]
+pub enum SolanaChainError {
+    /// The network id is not a Solana x402 id or cluster name
+    #[error("Unknown Solana chain `{0}`")]
+    UnknownChain(String),
+    /// The CAIP-2 id is not in the `solana` namespace or the genesis hash is unknown
+    #[error("Unknown Solana CAIP-2 chain id `{0}`")]
+    UnknownCaip2Id(String),
+}
+
+#[cfg(test)]
+mod solana_chain_sanity {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        for chain in SolanaChain::ALL {
+            assert_eq!(chain.x402_id().parse::<SolanaChain>(), Ok(chain));
+            assert_eq!(chain.to_string().parse::<SolanaChain>(), Ok(chain));
+            assert_eq!(chain.cluster().parse::<SolanaChain>(), Ok(chain));
+
+            if let Some(caip2_id) = chain.caip2_id() {
+                assert_eq!(caip2_id.parse::<SolanaChain>(), Ok(chain));
+            }
+        }
+
+        assert_eq!(
+            "SOLANA-DEVNET".parse::<SolanaChain>(),
+            Ok(SolanaChain::Devnet)
+        );
+        assert_eq!(
+            "mainnet".parse::<SolanaChain>(),
+            Ok(SolanaChain::MainnetBeta)
+        );
+        assert_eq!(
+            SolanaChain::MainnetBeta.caip2_id().as_deref(),
+            Some("solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp")
+        );
+        assert_eq!(
+            (String::from("solana:") + SolanaChain::DEVNET_GENESIS_HASH).parse::<SolanaChain>(),
+            Ok(SolanaChain::Devnet)
+        );
+        assert_eq!(SolanaChain::Localnet.caip2_id(), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            "ethereum".parse::<SolanaChain>(),
+            Err(SolanaChainError::UnknownChain("ethereum".to_string()))
+        );
+        assert_eq!(
+            "solana:EtWTRABZ".parse::<SolanaChain>(),
+            Err(SolanaChainError::UnknownCaip2Id(
+                "solana:EtWTRABZ".to_string()
+            ))
+        );
+        assert_eq!(
+            "eip155:1".parse::<SolanaChain>(),
+            Err(SolanaChainError::UnknownCaip2Id("eip155:1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        assert_eq!(
+            serde_json::to_string(&SolanaChain::Devnet).unwrap(),
+            "\"solana-devnet\""
+        );
+        assert_eq!(
+            serde_json::from_str::<SolanaChain>("\"solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1\"")
+                .unwrap(),
+            SolanaChain::Devnet
+        );
+        assert!(serde_json::from_str::<SolanaChain>("\"solana-unknown\"").is_err());
+
+        // Mainnet is `solana` in the x402 specification
+        assert_eq!(
+            serde_json::to_string(&SolanaChain::MainnetBeta).unwrap(),
+            "\"solana\""
+        );
+        for mainnet in ["\"solana\"", "\"solana-mainnet\""] {
+            let parsed = serde_json::from_str::<SolanaChain>(mainnet).unwrap();
+            assert_eq!(parsed, SolanaChain::MainnetBeta);
+            assert_eq!(serde_json::to_string(&parsed).unwrap(), "\"solana\"");
+        }
+    }
 }
@@ -0,0 +1,748 @@
+use base64ct::{Base64UrlUnpadded, Encoding};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+/// Proves that a request comes from the owner of the `X402-Client-Address`.
+///
+/// The request is signed either by the address itself or by a session key.
+/// A session key is delegated by listing `x402-session:<session key>` in the
+/// resources of the Sign In With Solana message signed by the address. The message
+/// must be for the domain of the server and have an `Expiration Time`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402ClientProof {
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+    pub nonce: String,
+    /// The base58 session public key, `None` if the address signed the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_key: Option<String>,
+    /// The base64 Sign In With Solana message that delegates the session key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub siws_message: Option<String>,
+    /// The base58 signature of the address over `siws_message`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub siws_signature: Option<String>,
+    /// The base58 signature over [X402ClientProof::message]
+    pub signature: String,
+}
+
+impl X402ClientProof {
+    pub const VERSION: &str = "x402-client-proof-v1";
+    /// The prefix of the SIWS resource that delegates a session key
+    pub const SESSION_RESOURCE_PREFIX: &str = "x402-session:";
+    /// How far the timestamp may be from the server clock
+    pub const MAX_CLOCK_SKEW_SECS: u64 = 300;
+    pub const MIN_NONCE_LENGTH: usize = 16;
+    pub const MAX_NONCE_LENGTH: usize = 64;
+    /// The query parameters of the address and the proof for clients that cannot set
+    /// headers, like `EventSource`
+    pub const ADDRESS_QUERY_PARAM: &str = "client_address";
+    pub const QUERY_PARAM: &str = "client_proof";
+
+    /// The bytes that are signed, one field per line
+    pub fn message(address: &str, method: &str, path: &str, timestamp: u64, nonce: &str) -> String {
+        [
+            Self::VERSION,
+            address,
+            &method.to_ascii_uppercase(),
+            path,
+            &timestamp.to_string(),
+            nonce,
+        ]
+        .join("\n")
+    }
+
+    pub fn to_header(&self) -> String {
+        // Only strings and integers are serialized which cannot fail
+        Base64UrlUnpadded::encode_string(serde_json::to_string(self).unwrap_or_default().as_bytes())
+    }
+
+    pub fn from_header(value: &str) -> Result<Self, X402ClientProofError> {
+        let json = Base64UrlUnpadded::decode_vec(value.trim())
+            .or(Err(X402ClientProofError::InvalidHeader))?;
+
+        serde_json::from_slice(&json).or(Err(X402ClientProofError::InvalidHeader))
+    }
+
+    /// `url` with the address and the proof as the [Self::ADDRESS_QUERY_PARAM] and
+    /// [Self::QUERY_PARAM] query parameters. The proof signs `url` without them.
+    pub fn append_to_url(&self, url: &str, address: &str) -> String {
+        let separator = if url.contains('?') { "&" } else { "?" };
+
+        String::from(url)
+            + separator
+            + Self::ADDRESS_QUERY_PARAM
+            + "="
+            + address
+            + "&"
+            + Self::QUERY_PARAM
+            + "="
+            + self.to_header().as_str()
+    }
+
+    /// The path of a request with the proof in its query, without the query parameters
+    /// added by [Self::append_to_url], which is the path the proof signed
+    pub fn signed_path(path: &str) -> String {
+        let Some((path, query)) = path.split_once('?') else {
+            return path.to_string();
+        };
+
+        let query = query
+            .split('&')
+            .filter(|param| {
+                let name = param.split_once('=').map_or(*param, |(name, _)| name);
+
+                name != Self::ADDRESS_QUERY_PARAM && name != Self::QUERY_PARAM
+            })
+            .collect::<Vec<&str>>()
+            .join("&");
+
+        if query.is_empty() {
+            path.to_string()
+        } else {
+            String::from(path) + "?" + query.as_str()
+        }
+    }
+
+    /// Verifies the proof for a request to `method` and `path` (with the query) at `now` in unix seconds.
+    /// A SIWS delegation must be for `domain`.
+    pub fn verify(
+        &self,
+        address: &str,
+        method: &str,
+        path: &str,
+        domain: &str,
+        now: u64,
+    ) -> Result<(), X402ClientProofError> {
+        if now.abs_diff(self.timestamp) > Self::MAX_CLOCK_SKEW_SECS {
+            return Err(X402ClientProofError::Expired);
+        }
+
+        if !(Self::MIN_NONCE_LENGTH..=Self::MAX_NONCE_LENGTH).contains(&self.nonce.len())
+            || !self.nonce.chars().all(|char| char.is_ascii_alphanumeric())
+        {
+            return Err(X402ClientProofError::InvalidNonce);
+        }
+
+        let address_key =
+            Self::verifying_key(address).ok_or(X402ClientProofError::InvalidAddress)?;
+
+        let signer = match self.session_key.as_deref() {
+            Some(session_key) => {
+                self.verify_delegation(address, &address_key, session_key, domain, now)?;
+
+                Self::verifying_key(session_key).ok_or(X402ClientProofError::InvalidSessionKey)?
+            }
+            None => address_key,
+        };
+
+        let message = Self::message(address, method, path, self.timestamp, &self.nonce);
+
+        signer
+            .verify_strict(message.as_bytes(), &Self::signature(&self.signature)?)
+            .or(Err(X402ClientProofError::InvalidSignature))
+    }
+
+    fn verify_delegation(
+        &self,
+        address: &str,
+        address_key: &VerifyingKey,
+        session_key: &str,
+        domain: &str,
+        now: u64,
+    ) -> Result<(), X402ClientProofError> {
+        let (Some(siws_message), Some(siws_signature)) =
+            (self.siws_message.as_deref(), self.siws_signature.as_deref())
+        else {
+            return Err(X402ClientProofError::MissingSiwsDelegation);
+        };
+
+        let siws_message = Base64UrlUnpadded::decode_vec(siws_message)
+            .or(Err(X402ClientProofError::InvalidSiwsSignature))?;
+
+        address_key
+            .verify_strict(&siws_message, &Self::signature(siws_signature)?)
+            .or(Err(X402ClientProofError::InvalidSiwsSignature))?;
+
+        let siws_message = String::from_utf8(siws_message)
+            .ok()
+            .and_then(|message| SiwsMessage::parse(&message))
+            .ok_or(X402ClientProofError::InvalidSiwsMessage)?;
+
+        // Wallets given the identity URI of the app may keep its scheme
+        if siws_message.domain.trim_start_matches("https://") != domain {
+            return Err(X402ClientProofError::WrongSiwsDomain);
+        }
+
+        let resource = String::from(Self::SESSION_RESOURCE_PREFIX) + session_key;
+
+        if siws_message.address != address || !siws_message.resources.contains(&resource) {
+            return Err(X402ClientProofError::SessionKeyNotDelegated);
+        }
+
+        let expiration_time = siws_message
+            .expiration_time
+            .ok_or(X402ClientProofError::MissingSiwsExpiration)?;
+
+        if now >= expiration_time
+            || siws_message
+                .not_before
+                .is_some_and(|not_before| now < not_before)
+        {
+            return Err(X402ClientProofError::SiwsExpired);
+        }
+
+        Ok(())
+    }
+
+    fn verifying_key(public_key: &str) -> Option<VerifyingKey> {
+        let mut bytes = [0u8; 32];
+        bs58::decode(public_key)
+            .onto(&mut bytes[..])
+            .ok()
+            .filter(|len| *len == 32)?;
+
+        VerifyingKey::from_bytes(&bytes).ok()
+    }
+
+    fn signature(signature: &str) -> Result<Signature, X402ClientProofError> {
+        let mut bytes = [0u8; 64];
+        bs58::decode(signature)
+            .onto(&mut bytes[..])
+            .ok()
+            .filter(|len| *len == 64)
+            .ok_or(X402ClientProofError::InvalidSignature)?;
+
+        Ok(Signature::from_bytes(&bytes))
+    }
+}
+
+/// The fields of a Sign In With Solana message used to delegate a session key
+#[derive(Debug, PartialEq, Eq, Default)]
+struct SiwsMessage {
+    domain: String,
+    address: String,
+    /// Unix timestamp in seconds
+    expiration_time: Option<u64>,
+    /// Unix timestamp in seconds
+    not_before: Option<u64>,
+    resources: Vec<String>,
+}
+
+impl SiwsMessage {
+    const HEADER_SUFFIX: &str = " wants you to sign in with your Solana account:";
+    const FIELDS: [&str; 8] = [
+        "URI",
+        "Version",
+        "Chain ID",
+        "Nonce",
+        "Issued At",
+        "Expiration Time",
+        "Not Before",
+        "Request ID",
+    ];
+
+    /// Parses the message, `None` if a line is not part of the SIWS format or a field repeats
+    fn parse(message: &str) -> Option<Self> {
+        let mut lines = message.lines();
+
+        let domain = lines
+            .next()?
+            .strip_suffix(Self::HEADER_SUFFIX)
+            .filter(|domain| !domain.is_empty() && !domain.contains(char::is_whitespace))?;
+        let address = lines
+            .next()
+            .filter(|address| !address.is_empty() && !address.contains(char::is_whitespace))?;
+
+        let mut parsed = Self {
+            domain: domain.to_string(),
+            address: address.to_string(),
+            ..Self::default()
+        };
+        let mut fields = Vec::<&str>::new();
+        let mut has_statement = false;
+        let mut in_resources = false;
+
+        for line in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            if in_resources {
+                if let Some(resource) = line.strip_prefix("- ") {
+                    parsed.resources.push(resource.to_string());
+                    continue;
+                }
+            }
+
+            if line == "Resources:" && !fields.contains(&"Resources") {
+                fields.push("Resources");
+                in_resources = true;
+                continue;
+            }
+
+            let field = line
+                .split_once(": ")
+                .filter(|(name, _)| Self::FIELDS.contains(name));
+
+            match field {
+                Some((name, value)) if !fields.contains(&name) => {
+                    fields.push(name);
+                    in_resources = false;
+
+                    match name {
+                        "Expiration Time" => {
+                            parsed.expiration_time.replace(Self::unix_timestamp(value)?);
+                        }
+                        "Not Before" => {
+                            parsed.not_before.replace(Self::unix_timestamp(value)?);
+                        }
+                        _ => {}
+                    }
+                }
+                // The statement is the only free text and comes before the fields
+                None if !has_statement && fields.is_empty() => has_statement = true,
+                _ => return None,
+            }
+        }
+
+        Some(parsed)
+    }
+
+    /// The unix timestamp in seconds of an RFC 3339 date time like `2025-10-09T08:53:20.5+02:00`
+    fn unix_timestamp(value: &str) -> Option<u64> {
+        fn number(digits: &str, len: usize) -> Option<i64> {
+            (digits.len() == len && digits.bytes().all(|byte| byte.is_ascii_digit()))
+                .then(|| digits.parse().ok())
+                .flatten()
+        }
+
+        let (date, time) = value.split_once(['T', 't'])?;
+
+        let mut date = date.split('-');
+        let (year, month, day) = (
+            number(date.next()?, 4)?,
+            number(date.next()?, 2)?,
+            number(date.next()?, 2)?,
+        );
+
+        let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+            Some(time) => (time, 0),
+            None => {
+                let (time, offset) = time.split_at(time.rfind(['+', '-'])?);
+                let (hours, minutes) = offset[1..].split_once(':')?;
+                let seconds = number(hours, 2)? * 3600 + number(minutes, 2)? * 60;
+
+                (
+                    time,
+                    if offset.starts_with('-') {
+                        -seconds
+                    } else {
+                        seconds
+                    },
+                )
+            }
+        };
+
+        let (time, fraction) = time.split_once('.').unwrap_or((time, "0"));
+        let mut time = time.split(':');
+        let (hour, minute, second) = (
+            number(time.next()?, 2)?,
+            number(time.next()?, 2)?,
+            number(time.next()?, 2)?,
+        );
+
+        let leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let days_in_month = match month {
+            2 if leap_year => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+
+        if date.next().is_some()
+            || time.next().is_some()
+            || fraction.is_empty()
+            || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+            || !(1..=12).contains(&month)
+            || !(1..=days_in_month).contains(&day)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return None;
+        }
+
+        // Days since the unix epoch of the proleptic Gregorian date, with years starting in March
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second - offset).ok()
+    }
+}
+
+/// Signs [X402ClientProof]s with the address key or a delegated session key
+#[derive(Debug, Clone)]
+pub struct X402ClientProofSigner {
+    signing_key: SigningKey,
+    /// The SIWS message and the signature of the address over it
+    siws_delegation: Option<(Vec<u8>, [u8; 64])>,
+}
+
+impl X402ClientProofSigner {
+    pub fn new(secret_key: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret_key),
+            siws_delegation: Option::None,
+        }
+    }
+
+    /// Signs as a session key delegated by the address in the SIWS message
+    pub fn set_siws_delegation(
+        &mut self,
+        siws_message: &[u8],
+        siws_signature: &[u8; 64],
+    ) -> &mut Self {
+        self.siws_delegation
+            .replace((siws_message.to_vec(), *siws_signature));
+
+        self
+    }
+
+    /// The base58 public key of the signer
+    pub fn public_key(&self) -> String {
+        bs58::encode(self.signing_key.verifying_key().as_bytes()).into_string()
+    }
+
+    /// The resource to add to the SIWS message to delegate this key
+    pub fn session_resource(&self) -> String {
+        String::from(X402ClientProof::SESSION_RESOURCE_PREFIX) + self.public_key().as_str()
+    }
+
+    pub fn sign(
+        &self,
+        address: &str,
+        method: &str,
+        path: &str,
+        timestamp: u64,
+        nonce: &str,
+    ) -> X402ClientProof {
+        let message = X402ClientProof::message(address, method, path, timestamp, nonce);
+        let signature = self.signing_key.sign(message.as_bytes());
+
+        let (session_key, siws_message, siws_signature) = match self.siws_delegation.as_ref() {
+            Some((siws_message, siws_signature)) => (
+                Some(self.public_key()),
+                Some(Base64UrlUnpadded::encode_string(siws_message)),
+                Some(bs58::encode(siws_signature).into_string()),
+            ),
+            None => (None, None, None),
+        };
+
+        X402ClientProof {
+            timestamp,
+            nonce: nonce.to_string(),
+            session_key,
+            siws_message,
+            siws_signature,
+            signature: bs58::encode(signature.to_bytes()).into_string(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, thiserror::Error)]
+pub enum X402ClientProofError {
+    /// The header is not base64url encoded JSON of a [X402ClientProof]
+    #[error("The client proof header is not valid base64url encoded JSON")]
+    InvalidHeader,
+    /// The client address is not a base58 Ed25519 public key
+    #[error("The client address is not a base58 Ed25519 public key")]
+    InvalidAddress,
+    /// The session key is not a base58 Ed25519 public key
+    #[error("The session key is not a base58 Ed25519 public key")]
+    InvalidSessionKey,
+    /// A session key signed the request without a SIWS message delegating it
+    #[error("The session key requires the SIWS message and signature that delegate it")]
+    MissingSiwsDelegation,
+    /// The SIWS message was not signed by the client address
+    #[error("The SIWS message was not signed by the client address")]
+    InvalidSiwsSignature,
+    /// The SIWS message is not in the Sign In With Solana format
+    #[error("The SIWS message is not a valid Sign In With Solana message")]
+    InvalidSiwsMessage,
+    /// The SIWS message was signed for another domain than the server
+    #[error("The SIWS message was signed for another domain")]
+    WrongSiwsDomain,
+    /// The SIWS message does not name the address and the session key
+    #[error("The SIWS message does not delegate the session key for the client address")]
+    SessionKeyNotDelegated,
+    /// The SIWS message delegates the session key without an `Expiration Time`
+    #[error("The SIWS message that delegates the session key requires an `Expiration Time`")]
+    MissingSiwsExpiration,
+    /// The SIWS message is past its `Expiration Time` or before its `Not Before`
+    #[error("The SIWS message that delegates the session key has expired or is not valid yet")]
+    SiwsExpired,
+    /// The signature does not match the request
+    #[error("The client proof signature does not match the request")]
+    InvalidSignature,
+    /// The timestamp is too far from the server clock
+    #[error("The client proof has expired or its timestamp is in the future")]
+    Expired,
+    /// The nonce is too short, too long or not alphanumeric
+    #[error("The client proof nonce must be 16 to 64 alphanumeric characters")]
+    InvalidNonce,
+    /// The nonce was already used within the allowed clock skew
+    #[error("The client proof nonce was already used")]
+    ReplayedNonce,
+}
+
+#[cfg(test)]
+mod x402_client_proof_sanity {
+    use super::*;
+
+    const NOW: u64 = 1_760_000_000;
+    const NONCE: &str = "4a7HcPJ3o9bXkqWm";
+    const PATH: &str = "/latest_newsletter?issue=1";
+    const DOMAIN: &str = "lagoon.markets";
+
+    fn wallet() -> (X402ClientProofSigner, String) {
+        let wallet = X402ClientProofSigner::new(&[7u8; 32]);
+        let address = wallet.public_key();
+
+        (wallet, address)
+    }
+
+    fn siws_message(address: &str, resource: &str) -> Vec<u8> {
+        format!(
+            "lagoon.markets wants you to sign in with your Solana account:\n{address}\n\nSign in to Lagoon Markets\n\nVersion: 1\nChain ID: mainnet\nExpiration Time: 2025-10-09T09:53:20Z\nResources:\n- {resource}"
+        )
+        .into_bytes()
+    }
+
+    fn delegated(message: &[u8], timestamp: u64) -> (X402ClientProof, String) {
+        let (wallet, address) = wallet();
+        let mut session = X402ClientProofSigner::new(&[11u8; 32]);
+
+        let siws_signature = wallet.signing_key.sign(message).to_bytes();
+        session.set_siws_delegation(message, &siws_signature);
+
+        (
+            session.sign(&address, "GET", PATH, timestamp, NONCE),
+            address,
+        )
+    }
+
+    #[test]
+    fn test_address_signed() {
+        let (wallet, address) = wallet();
+
+        let proof = wallet.sign(&address, "get", PATH, NOW, NONCE);
+        let parsed = X402ClientProof::from_header(&proof.to_header()).unwrap();
+        assert_eq!(parsed, proof);
+        assert_eq!(
+            parsed.verify(&address, "GET", PATH, DOMAIN, NOW + 10),
+            Ok(())
+        );
+
+        assert_eq!(
+            parsed.verify(&address, "POST", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::InvalidSignature)
+        );
+        assert_eq!(
+            parsed.verify(&address, "GET", "/latest_newsletter", DOMAIN, NOW),
+            Err(X402ClientProofError::InvalidSignature)
+        );
+        assert_eq!(
+            parsed.verify(&address, "GET", PATH, DOMAIN, NOW + 301),
+            Err(X402ClientProofError::Expired)
+        );
+
+        let impostor = X402ClientProofSigner::new(&[9u8; 32]).public_key();
+        assert_eq!(
+            parsed.verify(&impostor, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_session_signed() {
+        let (wallet, address) = wallet();
+        let mut session = X402ClientProofSigner::new(&[11u8; 32]);
+
+        let message = siws_message(&address, &session.session_resource());
+        let siws_signature = wallet.signing_key.sign(&message).to_bytes();
+        session.set_siws_delegation(&message, &siws_signature);
+
+        let proof = session.sign(&address, "GET", PATH, NOW, NONCE);
+        assert_eq!(proof.verify(&address, "GET", PATH, DOMAIN, NOW), Ok(()));
+
+        let mut undelegated = X402ClientProofSigner::new(&[13u8; 32]);
+        undelegated.set_siws_delegation(&message, &siws_signature);
+        assert_eq!(
+            undelegated
+                .sign(&address, "GET", PATH, NOW, NONCE)
+                .verify(&address, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::SessionKeyNotDelegated)
+        );
+
+        let mut forged = X402ClientProofSigner::new(&[11u8; 32]);
+        forged.set_siws_delegation(&message, &[0u8; 64]);
+        assert_eq!(
+            forged
+                .sign(&address, "GET", PATH, NOW, NONCE)
+                .verify(&address, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::InvalidSiwsSignature)
+        );
+
+        let mut missing = proof.clone();
+        missing.siws_signature = None;
+        assert_eq!(
+            missing.verify(&address, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::MissingSiwsDelegation)
+        );
+    }
+
+    #[test]
+    fn test_siws_delegation() {
+        let (_, address) = wallet();
+        let resource = X402ClientProofSigner::new(&[11u8; 32]).session_resource();
+        let message = String::from_utf8(siws_message(&address, &resource)).unwrap();
+
+        let (proof, _) = delegated(message.as_bytes(), NOW + 3599);
+        assert_eq!(
+            proof.verify(&address, "GET", PATH, DOMAIN, NOW + 3599),
+            Ok(())
+        );
+        let (expired, _) = delegated(message.as_bytes(), NOW + 3600);
+        assert_eq!(
+            expired.verify(&address, "GET", PATH, DOMAIN, NOW + 3600),
+            Err(X402ClientProofError::SiwsExpired)
+        );
+        assert_eq!(
+            proof.verify(&address, "GET", PATH, "evil.example", NOW + 3599),
+            Err(X402ClientProofError::WrongSiwsDomain)
+        );
+
+        let (phished, _) = delegated(
+            message.replace("lagoon.markets", "evil.example").as_bytes(),
+            NOW,
+        );
+        assert_eq!(
+            phished.verify(&address, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::WrongSiwsDomain)
+        );
+
+        let (no_expiration, _) = delegated(
+            message
+                .replace("Expiration Time: 2025-10-09T09:53:20Z\n", "")
+                .as_bytes(),
+            NOW,
+        );
+        assert_eq!(
+            no_expiration.verify(&address, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::MissingSiwsExpiration)
+        );
+
+        // The resource and the expiration cannot be smuggled into the statement or repeated
+        let smuggled = format!(
+            "lagoon.markets wants you to sign in with your Solana account:\n{address}\n\n- {resource}\n\nExpiration Time: 2025-10-09T09:53:20Z"
+        );
+        assert_eq!(
+            delegated(smuggled.as_bytes(), NOW)
+                .0
+                .verify(&address, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::SessionKeyNotDelegated)
+        );
+        let repeated = message.replace(
+            "Expiration Time: 2025-10-09T09:53:20Z",
+            "Expiration Time: 2025-10-09T09:53:20Z\nExpiration Time: 2099-01-01T00:00:00Z",
+        );
+        assert_eq!(
+            delegated(repeated.as_bytes(), NOW)
+                .0
+                .verify(&address, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::InvalidSiwsMessage)
+        );
+    }
+
+    #[test]
+    fn test_query() {
+        let (wallet, address) = wallet();
+        let proof = wallet.sign(&address, "GET", PATH, NOW, NONCE);
+
+        let url = proof.append_to_url(PATH, &address);
+        assert!(url.starts_with("/latest_newsletter?issue=1&client_address="));
+        assert_eq!(X402ClientProof::signed_path(&url), PATH);
+
+        let header = url
+            .rsplit_once(&(String::from(X402ClientProof::QUERY_PARAM) + "="))
+            .map(|(_, header)| header)
+            .unwrap();
+        assert_eq!(
+            X402ClientProof::from_header(header).unwrap().verify(
+                &address,
+                "GET",
+                &X402ClientProof::signed_path(&url),
+                DOMAIN,
+                NOW
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            X402ClientProof::signed_path(&proof.append_to_url("/voting", &address)),
+            "/voting"
+        );
+    }
+
+    #[test]
+    fn test_siws_timestamp() {
+        assert_eq!(
+            SiwsMessage::unix_timestamp("2025-10-09T08:53:20Z"),
+            Some(NOW)
+        );
+        assert_eq!(
+            SiwsMessage::unix_timestamp("2025-10-09T10:53:20.125+02:00"),
+            Some(NOW)
+        );
+        assert_eq!(
+            SiwsMessage::unix_timestamp("2025-10-09T07:53:20-01:00"),
+            Some(NOW)
+        );
+        assert_eq!(SiwsMessage::unix_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            SiwsMessage::unix_timestamp("2024-02-29T00:00:00Z"),
+            Some(1_709_164_800)
+        );
+        assert_eq!(SiwsMessage::unix_timestamp("2025-02-29T00:00:00Z"), None);
+        assert_eq!(SiwsMessage::unix_timestamp("2025-10-09 08:53:20Z"), None);
+        assert_eq!(SiwsMessage::unix_timestamp("2025-10-09T08:53:20"), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (wallet, address) = wallet();
+
+        assert_eq!(
+            X402ClientProof::from_header("not base64!"),
+            Err(X402ClientProofError::InvalidHeader)
+        );
+        assert_eq!(
+            wallet
+                .sign(&address, "GET", PATH, NOW, "short")
+                .verify(&address, "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::InvalidNonce)
+        );
+        assert_eq!(
+            wallet
+                .sign(&address, "GET", PATH, NOW, NONCE)
+                .verify("0OIl", "GET", PATH, DOMAIN, NOW),
+            Err(X402ClientProofError::InvalidAddress)
+        );
+    }
+}
@@ -0,0 +1,437 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::CommonUtils;
+
+/// The filters and the page of a `/x402/discover` request
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct X402DiscoveryQuery {
+    resource_type: Option<String>,
+    asset: Option<String>,
+    min_amount: Option<u64>,
+    max_amount: Option<u64>,
+    text: Option<String>,
+    limit: Option<u32>,
+    offset: u32,
+}
+
+impl X402DiscoveryQuery {
+    pub const DEFAULT_LIMIT: u32 = 20;
+    pub const MAX_LIMIT: u32 = 100;
+    pub const RESOURCE_TYPES: [&str; 3] = ["http", "a2a", "mcp"];
+
+    pub const TYPE_PARAM: &str = "type";
+    pub const ASSET_PARAM: &str = "asset";
+    pub const MIN_AMOUNT_PARAM: &str = "minAmount";
+    pub const MAX_AMOUNT_PARAM: &str = "maxAmount";
+    pub const TEXT_PARAM: &str = "query";
+    pub const LIMIT_PARAM: &str = "limit";
+    pub const OFFSET_PARAM: &str = "offset";
+    pub const PARAMS: [&str; 7] = [
+        Self::TYPE_PARAM,
+        Self::ASSET_PARAM,
+        Self::MIN_AMOUNT_PARAM,
+        Self::MAX_AMOUNT_PARAM,
+        Self::TEXT_PARAM,
+        Self::LIMIT_PARAM,
+        Self::OFFSET_PARAM,
+    ];
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses decoded query parameters. Other parameters, like the `pk` and `sig` of a signed
+    /// x402-URI, are ignored.
+    pub fn from_params<'a>(
+        params: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, X402DiscoveryQueryError> {
+        let mut query = Self::new();
+
+        for (name, value) in params {
+            match name {
+                Self::TYPE_PARAM => query.set_type(value),
+                Self::ASSET_PARAM => query.set_asset(value),
+                Self::MIN_AMOUNT_PARAM => query.set_min_amount(Self::number(name, value)?),
+                Self::MAX_AMOUNT_PARAM => query.set_max_amount(Self::number(name, value)?),
+                Self::TEXT_PARAM => query.set_text(value),
+                Self::LIMIT_PARAM => query.set_limit(Self::number(name, value)?),
+                Self::OFFSET_PARAM => query.set_offset(Self::number(name, value)?),
+                _ => &mut query,
+            };
+        }
+
+        query.validate()?;
+
+        Ok(query)
+    }
+
+    /// Splits a discovery URL into its parsed query and the URL without the discovery
+    /// parameters. Other parameters are kept as they are so the URL can be rebuilt with [Self::url].
+    pub fn parse_url(url: &str) -> Result<(String, Self), X402DiscoveryQueryError> {
+        let Some((base, query)) = url.split_once('?') else {
+            return Ok((url.to_string(), Self::new()));
+        };
+
+        let decode = |value: &str| {
+            CommonUtils::percent_decode(&value.replace('+', " "))
+                .map_err(X402DiscoveryQueryError::Encoding)
+        };
+
+        let mut params = Vec::<(String, String)>::new();
+        let mut other_params = Vec::<&str>::new();
+
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let name = decode(name)?;
+
+            if Self::PARAMS.contains(&name.as_str()) {
+                params.push((name, decode(value)?));
+            } else {
+                other_params.push(param);
+            }
+        }
+
+        let query = Self::from_params(
+            params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        )?;
+
+        let base = if other_params.is_empty() {
+            base.to_string()
+        } else {
+            String::from(base) + "?" + &other_params.join("&")
+        };
+
+        Ok((base, query))
+    }
+
+    pub fn validate(&self) -> Result<(), X402DiscoveryQueryError> {
+        if let Some(resource_type) = self.resource_type.as_deref() {
+            if !Self::RESOURCE_TYPES.contains(&resource_type) {
+                return Err(X402DiscoveryQueryError::UnsupportedType(
+                    resource_type.to_string(),
+                ));
+            }
+        }
+
+        if let (Some(min_amount), Some(max_amount)) = (self.min_amount, self.max_amount) {
+            if min_amount > max_amount {
+                return Err(X402DiscoveryQueryError::InvalidAmountRange);
+            }
+        }
+
+        if self.limit == Some(0) {
+            return Err(X402DiscoveryQueryError::ZeroLimit);
+        }
+
+        Ok(())
+    }
+
+    /// The query string without the leading `?`, empty when nothing is set
+    pub fn to_query_string(&self) -> String {
+        let mut params = Vec::<(&str, String)>::new();
+
+        if let Some(resource_type) = self.resource_type.as_ref() {
+            params.push((Self::TYPE_PARAM, resource_type.clone()));
+        }
+        if let Some(asset) = self.asset.as_ref() {
+            params.push((Self::ASSET_PARAM, asset.clone()));
+        }
+        if let Some(min_amount) = self.min_amount {
+            params.push((Self::MIN_AMOUNT_PARAM, min_amount.to_string()));
+        }
+        if let Some(max_amount) = self.max_amount {
+            params.push((Self::MAX_AMOUNT_PARAM, max_amount.to_string()));
+        }
+        if let Some(text) = self.text.as_ref() {
+            params.push((Self::TEXT_PARAM, text.clone()));
+        }
+        if let Some(limit) = self.limit {
+            params.push((Self::LIMIT_PARAM, limit.to_string()));
+        }
+        if self.offset > 0 {
+            params.push((Self::OFFSET_PARAM, self.offset.to_string()));
+        }
+
+        params
+            .iter()
+            .map(|(name, value)| String::from(*name) + "=" + &CommonUtils::percent_encode(value))
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+
+    /// `base` with this query appended after the query `base` may already have
+    pub fn url(&self, base: &str) -> String {
+        let query = self.to_query_string();
+
+        if query.is_empty() {
+            base.to_string()
+        } else if base.contains('?') {
+            String::from(base) + "&" + &query
+        } else {
+            String::from(base) + "?" + &query
+        }
+    }
+
+    pub fn set_type(&mut self, resource_type: &str) -> &mut Self {
+        self.resource_type = Some(resource_type.trim().to_lowercase());
+
+        self
+    }
+
+    pub fn set_asset(&mut self, asset: &str) -> &mut Self {
+        self.asset = Some(asset.trim().to_string());
+
+        self
+    }
+
+    /// The minimum price in the smallest unit of the asset
+    pub fn set_min_amount(&mut self, min_amount: u64) -> &mut Self {
+        self.min_amount.replace(min_amount);
+
+        self
+    }
+
+    /// The maximum price in the smallest unit of the asset
+    pub fn set_max_amount(&mut self, max_amount: u64) -> &mut Self {
+        self.max_amount.replace(max_amount);
+
+        self
+    }
+
+    /// Matches the title, description and URL of a resource
+    pub fn set_text(&mut self, text: &str) -> &mut Self {
+        let text = text.trim();
+        self.text = (!text.is_empty()).then(|| text.to_lowercase());
+
+        self
+    }
+
+    pub fn set_limit(&mut self, limit: u32) -> &mut Self {
+        self.limit.replace(limit);
+
+        self
+    }
+
+    pub fn set_offset(&mut self, offset: u32) -> &mut Self {
+        self.offset = offset;
+
+        self
+    }
+
+    pub fn resource_type(&self) -> Option<&str> {
+        self.resource_type.as_deref()
+    }
+
+    pub fn asset(&self) -> Option<&str> {
+        self.asset.as_deref()
+    }
+
+    /// The page size, at most [Self::MAX_LIMIT]
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn matches_type(&self, resource_type: &str) -> bool {
+        self.resource_type
+            .as_deref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(resource_type))
+    }
+
+    /// Whether a payment option in `asset` at `amount` passes the asset and price filters
+    pub fn matches_price(&self, asset: &str, amount: u64) -> bool {
+        self.asset
+            .as_deref()
+            .is_none_or(|expected| expected == asset)
+            && self
+                .min_amount
+                .is_none_or(|min_amount| amount >= min_amount)
+            && self
+                .max_amount
+                .is_none_or(|max_amount| amount <= max_amount)
+    }
+
+    /// Every word of the text query has to appear in one of the `fields`, ignoring case
+    pub fn matches_text(&self, fields: &[&str]) -> bool {
+        let Some(text) = self.text.as_deref() else {
+            return true;
+        };
+
+        let fields = fields
+            .iter()
+            .map(|field| field.to_lowercase())
+            .collect::<Vec<String>>();
+
+        text.split_whitespace()
+            .all(|word| fields.iter().any(|field| field.contains(word)))
+    }
+
+    fn number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, X402DiscoveryQueryError> {
+        value
+            .trim()
+            .parse::<T>()
+            .or(Err(X402DiscoveryQueryError::InvalidNumber {
+                parameter: name.to_string(),
+                value: value.to_string(),
+            }))
+    }
+}
+
+/// The `pagination` of a discovery payload
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct X402DiscoveryPagination {
+    pub limit: u32,
+    pub offset: u32,
+    /// The number of resources that match the filters across all pages
+    pub total: u32,
+}
+
+impl X402DiscoveryPagination {
+    /// The most pages a client fetches of a discovery URL, so a server cannot page it forever
+    pub const MAX_PAGES: u32 = 100;
+
+    pub fn new(query: &X402DiscoveryQuery, total: usize) -> Self {
+        Self {
+            limit: query.limit(),
+            offset: query.offset(),
+            total: u32::try_from(total).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// Reads the pagination of a discovery payload, `None` if it is missing or incomplete
+    pub fn from_payload(payload: &serde_json::Value) -> Option<Self> {
+        serde_json::from_value::<Self>(payload.get("pagination")?.clone()).ok()
+    }
+
+    /// The indexes of the matching resources in this page
+    pub fn range(&self, len: usize) -> Range<usize> {
+        let start = (self.offset as usize).min(len);
+        let end = start.saturating_add(self.limit as usize).min(len);
+
+        start..end
+    }
+
+    /// The offset of the page after the `page_len` resources fetched at `requested_offset`,
+    /// if there is one. The offset echoed by the server is not used so that it cannot send
+    /// the client back to a page it already fetched.
+    pub fn next_offset(&self, requested_offset: u32, page_len: usize) -> Option<u32> {
+        let page_len = u32::try_from(page_len).ok().filter(|page_len| *page_len > 0)?;
+        let next_offset = requested_offset.checked_add(page_len)?;
+
+        (next_offset < self.total).then_some(next_offset)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, thiserror::Error)]
+pub enum X402DiscoveryQueryError {
+    /// A numeric parameter is not an unsigned integer
+    #[error(
+        "The discovery query parameter `{parameter}` must be an unsigned integer, found `{value}`"
+    )]
+    InvalidNumber { parameter: String, value: String },
+    /// The resource type is not `http`, `a2a` or `mcp`
+    #[error("The resource type `{0}` is not one of `http`, `a2a` or `mcp`")]
+    UnsupportedType(String),
+    /// The minimum amount is greater than the maximum amount
+    #[error("The `minAmount` is greater than the `maxAmount`")]
+    InvalidAmountRange,
+    /// A page of zero resources was requested
+    #[error("The `limit` must be greater than zero")]
+    ZeroLimit,
+    /// The query string is not valid percent encoded UTF-8
+    #[error("Unable to decode the discovery query. Error: {0}")]
+    Encoding(String),
+}
+
+#[cfg(test)]
+mod x402_discovery_sanity {
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let (base, query) = X402DiscoveryQuery::parse_url(
+            "https://lagoon.markets/x402/discover?type=A2A&query=book+timeline&maxAmount=500000&limit=500&offset=20&pk=Pk&sig=S%20g",
+        )
+        .unwrap();
+
+        assert_eq!(base, "https://lagoon.markets/x402/discover?pk=Pk&sig=S%20g");
+        assert_eq!(query.resource_type(), Some("a2a"));
+        assert_eq!(query.limit(), X402DiscoveryQuery::MAX_LIMIT);
+        assert_eq!(query.offset(), 20);
+        assert!(query.matches_type("a2a"));
+        assert!(!query.matches_type("http"));
+        assert!(query.matches_price("11111111111111111111111111111111", 500_000));
+        assert!(!query.matches_price("11111111111111111111111111111111", 500_001));
+        assert!(query.matches_text(&["Live Updates", "Building the eBook TIMELINE"]));
+        assert!(!query.matches_text(&["Live Updates"]));
+
+        assert_eq!(
+            X402DiscoveryQuery::parse_url(&query.url(&base)).unwrap(),
+            (base, query)
+        );
+
+        assert_eq!(
+            X402DiscoveryQuery::from_params([("pk", "Pk"), ("sig", "Sig")]),
+            Ok(X402DiscoveryQuery::new())
+        );
+        assert_eq!(
+            X402DiscoveryQuery::from_params([("type", "grpc")]),
+            Err(X402DiscoveryQueryError::UnsupportedType("grpc".to_string()))
+        );
+        assert_eq!(
+            X402DiscoveryQuery::from_params([("minAmount", "2"), ("maxAmount", "1")]),
+            Err(X402DiscoveryQueryError::InvalidAmountRange)
+        );
+        assert_eq!(
+            X402DiscoveryQuery::from_params([("limit", "0")]),
+            Err(X402DiscoveryQueryError::ZeroLimit)
+        );
+        assert!(matches!(
+            X402DiscoveryQuery::from_params([("offset", "-1")]),
+            Err(X402DiscoveryQueryError::InvalidNumber { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pagination() {
+        let mut query = X402DiscoveryQuery::new();
+        query.set_limit(2);
+
+        let first = X402DiscoveryPagination::new(&query, 5);
+        assert_eq!(first.range(5), 0..2);
+        assert_eq!(first.next_offset(0, 2), Some(2));
+
+        query.set_offset(4);
+        let last = X402DiscoveryPagination::new(&query, 5);
+        assert_eq!(last.range(5), 4..5);
+        assert_eq!(last.next_offset(4, 1), None);
+
+        // An empty page or a server that echoes an earlier offset cannot make the client loop
+        assert_eq!(first.next_offset(2, 0), None);
+        assert_eq!(first.next_offset(3, 1), Some(4));
+        assert_eq!(first.next_offset(u32::MAX, 1), None);
+
+        query.set_offset(10);
+        assert_eq!(X402DiscoveryPagination::new(&query, 5).range(5), 5..5);
+
+        let payload = serde_json::json!({
+            "x402Version": 1,
+            "items": [],
+            "pagination": { "limit": 2, "offset": 0, "total": 5 }
+        });
+        assert_eq!(X402DiscoveryPagination::from_payload(&payload), Some(first));
+        assert_eq!(
+            X402DiscoveryPagination::from_payload(&serde_json::json!({ "items": [] })),
+            None
+        );
+    }
+}
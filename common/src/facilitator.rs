@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{SolanaChain, X402ErrorCode, X402ErrorResponse, X402PaymentPayload};
+
+/// The body of the `/verify` and `/settle` requests to a x402 facilitator
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402FacilitatorRequest {
+    pub x402_version: u8,
+    pub payment_payload: X402PaymentPayload,
+    pub payment_requirements: X402PaymentRequirements,
+}
+
+/// A payment method of a resource as it appears in its `accepts` list
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402PaymentRequirements {
+    pub scheme: String,
+    pub network: String,
+    /// The amount in the smallest unit of the asset
+    pub max_amount_required: String,
+    pub resource: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub mime_type: String,
+    pub pay_to: String,
+    pub max_timeout_seconds: u64,
+    /// The mint address, or the system program for SOL
+    pub asset: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<X402SvmExtra>,
+}
+
+impl X402PaymentRequirements {
+    pub fn max_amount_required(&self) -> Result<u64, X402ErrorResponse> {
+        self.max_amount_required
+            .parse::<u64>()
+            .or(Err(X402ErrorResponse::new(
+                X402ErrorCode::InvalidRequest,
+                &self.resource,
+                "The `maxAmountRequired` is not an unsigned 64 bit integer",
+            )))
+    }
+
+    pub fn chain(&self) -> Result<SolanaChain, X402ErrorResponse> {
+        self.network
+            .parse::<SolanaChain>()
+            .or(Err(X402ErrorResponse::new(
+                X402ErrorCode::UnsupportedNetwork,
+                &self.resource,
+                &format!("The `{}` network is not supported", self.network),
+            )))
+    }
+
+    pub fn fee_payer(&self) -> Option<&str> {
+        self.extra
+            .as_ref()
+            .and_then(|extra| extra.fee_payer.as_deref())
+    }
+}
+
+/// The `extra` field of Solana payment requirements
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402SvmExtra {
+    /// The facilitator address that pays the transaction fees
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_payer: Option<String>,
+    /// The decimals of the token asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+}
+
+/// The response of the `/verify` facilitator endpoint
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402VerifyResponse {
+    pub is_valid: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_reason: Option<X402ErrorCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
+}
+
+impl X402VerifyResponse {
+    pub fn valid(payer: &str) -> Self {
+        Self {
+            is_valid: true,
+            invalid_reason: Option::None,
+            error: Option::None,
+            payer: Some(payer.to_string()),
+        }
+    }
+
+    pub fn invalid(error: X402ErrorResponse) -> Self {
+        Self {
+            is_valid: false,
+            invalid_reason: Some(error.code),
+            error: Some(error.error),
+            payer: Option::None,
+        }
+    }
+}
+
+/// The response of the `/settle` facilitator endpoint
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402SettleResponse {
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<X402ErrorCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The signature of the settled transaction, empty if it was not sent
+    pub transaction: String,
+    pub network: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
+}
+
+impl X402SettleResponse {
+    pub fn failed(network: &str, error: X402ErrorResponse) -> Self {
+        Self {
+            success: false,
+            error_reason: Some(error.code),
+            error: Some(error.error),
+            transaction: String::default(),
+            network: network.to_string(),
+            payer: Option::None,
+        }
+    }
+}
+
+/// The response of the `/supported` facilitator endpoint
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct X402SupportedResponse {
+    pub kinds: Vec<X402SupportedKind>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402SupportedKind {
+    pub x402_version: u8,
+    pub scheme: String,
+    pub network: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<X402SvmExtra>,
+}
+
+impl X402SupportedKind {
+    /// The `exact` scheme on `chain`
+    pub fn exact(chain: SolanaChain, fee_payer: Option<&str>) -> Self {
+        Self {
+            x402_version: X402PaymentPayload::X402_VERSION,
+            scheme: X402PaymentPayload::EXACT_SCHEME.to_string(),
+            network: chain.x402_id().to_string(),
+            extra: fee_payer.map(|fee_payer| X402SvmExtra {
+                fee_payer: Some(fee_payer.to_string()),
+                decimals: Option::None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod x402_facilitator_sanity {
+    use super::*;
+
+    const REQUEST: &str = r#"{
+        "x402Version": 1,
+        "paymentPayload": {
+            "x402Version": 1,
+            "scheme": "exact",
+            "network": "solana-devnet",
+            "payload": { "transaction": "AQID" }
+        },
+        "paymentRequirements": {
+            "scheme": "exact",
+            "network": "solana-devnet",
+            "maxAmountRequired": "100000",
+            "resource": "https://lagoon.markets/latest_newsletter",
+            "payTo": "HboHBxyjJh4jvrb2XkusMHqZ7rnHNMuVmca8XDN2doS",
+            "maxTimeoutSeconds": 100,
+            "asset": "Gh9ZwEmdLJ8DscKNTkTqPbNwLNNBjuSzaG9Vp2KGtKJr",
+            "extra": { "feePayer": "8BnEgHoWFysVcuFFX7QztDmzuH8r5ZFvyP3sYwn1XTh6" }
+        }
+    }"#;
+
+    #[test]
+    fn test_request() {
+        let request = serde_json::from_str::<X402FacilitatorRequest>(REQUEST).unwrap();
+        let requirements = &request.payment_requirements;
+
+        assert_eq!(requirements.max_amount_required(), Ok(100_000));
+        assert_eq!(requirements.chain(), Ok(SolanaChain::Devnet));
+        assert_eq!(
+            requirements.fee_payer(),
+            Some("8BnEgHoWFysVcuFFX7QztDmzuH8r5ZFvyP3sYwn1XTh6")
+        );
+        assert_eq!(
+            request.payment_payload,
+            X402PaymentPayload::new(SolanaChain::Devnet, "AQID")
+        );
+
+        let mut invalid = requirements.clone();
+        invalid.max_amount_required = "0.1".to_string();
+        invalid.network = "base".to_string();
+        assert_eq!(
+            invalid.max_amount_required().map_err(|error| error.code),
+            Err(X402ErrorCode::InvalidRequest)
+        );
+        assert_eq!(
+            invalid.chain().map_err(|error| error.code),
+            Err(X402ErrorCode::UnsupportedNetwork)
+        );
+    }
+
+    #[test]
+    fn test_responses() {
+        let invalid = X402VerifyResponse::invalid(X402ErrorResponse::new(
+            X402ErrorCode::PaymentRejected,
+            "https://lagoon.markets/latest_newsletter",
+            "Not enough",
+        ));
+        assert_eq!(
+            serde_json::to_value(&invalid).unwrap(),
+            serde_json::json!({
+                "isValid": false,
+                "invalidReason": "payment_rejected",
+                "error": "Not enough"
+            })
+        );
+
+        assert_eq!(
+            serde_json::to_value(X402SupportedKind::exact(SolanaChain::Devnet, None)).unwrap(),
+            serde_json::json!({ "x402Version": 1, "scheme": "exact", "network": "solana-devnet" })
+        );
+    }
+}
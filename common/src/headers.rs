@@ -3,4 +3,10 @@ pub struct CommonHeaders;
 impl CommonHeaders {
     pub const X402_ADDRESS_HEADER: &str = "X402-Client-Address";
     pub const X402_CHAIN_HEADER: &str = "X402-Chain";
+    /// A base64url [crate::X402ClientProof] that authenticates `X402-Client-Address`
+    pub const X402_CLIENT_PROOF_HEADER: &str = "X402-Client-Proof";
+    /// A base64 [crate::X402PaymentResponse] returned with the content of a paid request
+    pub const X402_PAYMENT_RESPONSE_HEADER: &str = "X-PAYMENT-RESPONSE";
+    /// A signed [crate::X402AccessToken] issued with a settled payment and sent back instead of paying again
+    pub const X402_ACCESS_TOKEN_HEADER: &str = "X402-Access-Token";
 }
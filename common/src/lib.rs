@@ -1,8 +1,23 @@
 mod headers;
 pub use headers::*;
 
-mod status_400_response;
-pub use status_400_response::*;
+mod client_proof;
+pub use client_proof::*;
+
+mod access_token;
+pub use access_token::*;
+
+mod payment_payload;
+pub use payment_payload::*;
+
+mod facilitator;
+pub use facilitator::*;
+
+mod discovery;
+pub use discovery::*;
+
+mod x402_error_response;
+pub use x402_error_response::*;
 
 mod chain;
 pub use chain::*;
@@ -10,9 +25,15 @@ pub use chain::*;
 mod tx_base64_json;
 pub use tx_base64_json::*;
 
+mod sanctum_tip;
+pub use sanctum_tip::*;
+
 mod rpc_response;
 pub use rpc_response::*;
 
+mod rpc_client;
+pub use rpc_client::*;
+
 mod live_updates;
 pub use live_updates::*;
 
@@ -12,6 +12,50 @@ pub struct EventSourceData {
     pub style: EventSourceProgressStyle,
 }
 
+impl EventSourceData {
+    /// The `id` of the events whose data is an [EventSourceData]
+    pub const STREAMING_ID: &str = "streaming";
+    /// The `id` of the last event of a stream, its data is empty
+    pub const DONE_ID: &str = "done";
+    /// The `id` of the events whose data is an [EventSourceControl]
+    pub const CONTROL_ID: &str = "control";
+}
+
+/// The control events of a paid live updates stream, sent with the
+/// [EventSourceData::CONTROL_ID] id between the [EventSourceData] events.
+///
+/// A stream paid up front with an access token ends with [Self::AccessExpired] when the token
+/// expires. A metered stream starts with [Self::Metered] and sends [Self::PaymentRequired]
+/// whenever its allowance is used up, including before the first event. The stream is paused
+/// until `top_up` is paid like any x402 resource: a `POST` without `X-PAYMENT` is answered with
+/// 402 and the payment requirements, and a `POST` with `X-PAYMENT` settles the top-up.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum EventSourceControl {
+    /// Each payment to `top_up` allows `events` more events, `seconds` more seconds, or both
+    /// and the stream pauses at whichever runs out first
+    Metered {
+        stream_id: String,
+        top_up: String,
+        events: Option<u64>,
+        seconds: Option<u64>,
+    },
+    /// The stream is paused until `top_up` is paid before `expires_at`, a unix timestamp in seconds
+    PaymentRequired {
+        stream_id: String,
+        top_up: String,
+        expires_at: u64,
+    },
+    /// A top-up was settled and the stream resumes
+    PaymentAccepted { transaction: String },
+    /// The access token expired or no top-up was paid in time. The done event follows.
+    AccessExpired,
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub struct EventSourceProgressPoint {
     pub point: u32,
@@ -29,3 +73,31 @@ pub struct EventSourceProgressStyle {
     pub points: Vec<EventSourceProgressPoint>,
     pub segments: Vec<EventSourceProgressSegment>,
 }
+
+#[cfg(test)]
+mod event_source_control_sanity {
+    use super::*;
+
+    #[test]
+    fn test_wire_format() {
+        let required = EventSourceControl::PaymentRequired {
+            stream_id: "stream".to_string(),
+            top_up: "https://lagoon.markets/x402/voting/top-up?stream=stream".to_string(),
+            expires_at: 10,
+        };
+        let json = serde_json::to_string(&required).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"paymentRequired","streamId":"stream","topUp":"https://lagoon.markets/x402/voting/top-up?stream=stream","expiresAt":10}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<EventSourceControl>(&json).unwrap(),
+            required
+        );
+
+        assert_eq!(
+            serde_json::to_string(&EventSourceControl::AccessExpired).unwrap(),
+            r#"{"type":"accessExpired"}"#
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use base64ct::{Base64, Encoding};
+use serde::{Deserialize, Serialize};
+
+use crate::SolanaChain;
+
+/// The decoded `X-PAYMENT` header of a request to a x402 resource
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402PaymentPayload {
+    pub x402_version: u8,
+    pub scheme: String,
+    pub network: String,
+    pub payload: X402ExactSvmPayload,
+}
+
+impl X402PaymentPayload {
+    pub const X402_VERSION: u8 = 1;
+    pub const EXACT_SCHEME: &str = "exact";
+
+    /// A version 1 `exact` payment with a base64 encoded transaction
+    pub fn new(chain: SolanaChain, transaction: &str) -> Self {
+        Self {
+            x402_version: Self::X402_VERSION,
+            scheme: Self::EXACT_SCHEME.to_string(),
+            network: chain.x402_id().to_string(),
+            payload: X402ExactSvmPayload {
+                transaction: transaction.to_string(),
+            },
+        }
+    }
+
+    pub fn to_header(&self) -> String {
+        // Only strings and integers are serialized which cannot fail
+        Base64::encode_string(serde_json::to_string(self).unwrap_or_default().as_bytes())
+    }
+
+    /// Decodes the base64 JSON header and checks the version and scheme
+    pub fn from_header(value: &str) -> Result<Self, X402PaymentPayloadError> {
+        let json =
+            Base64::decode_vec(value.trim()).or(Err(X402PaymentPayloadError::InvalidHeader))?;
+        let payload = serde_json::from_slice::<Self>(&json)
+            .or(Err(X402PaymentPayloadError::InvalidHeader))?;
+
+        if payload.x402_version != Self::X402_VERSION {
+            return Err(X402PaymentPayloadError::UnsupportedVersion(
+                payload.x402_version,
+            ));
+        }
+
+        if payload.scheme != Self::EXACT_SCHEME {
+            return Err(X402PaymentPayloadError::UnsupportedScheme(payload.scheme));
+        }
+
+        Ok(payload)
+    }
+
+    pub fn chain(&self) -> Result<SolanaChain, X402PaymentPayloadError> {
+        self.network
+            .parse::<SolanaChain>()
+            .or(Err(X402PaymentPayloadError::UnsupportedNetwork(
+                self.network.clone(),
+            )))
+    }
+
+    /// The bytes of the transaction that makes the payment
+    pub fn transaction_bytes(&self) -> Result<Vec<u8>, X402PaymentPayloadError> {
+        Base64::decode_vec(&self.payload.transaction)
+            .or(Err(X402PaymentPayloadError::InvalidTransaction))
+    }
+}
+
+/// The payload of the `exact` scheme on Solana
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct X402ExactSvmPayload {
+    /// The base64 encoded signed transaction
+    pub transaction: String,
+}
+
+/// The decoded `X-PAYMENT-RESPONSE` header of a settled payment
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402PaymentResponse {
+    pub success: bool,
+    /// The signature of the settled transaction
+    pub transaction: String,
+    pub network: String,
+    pub payer: String,
+}
+
+impl X402PaymentResponse {
+    pub fn to_header(&self) -> String {
+        // Only strings and booleans are serialized which cannot fail
+        Base64::encode_string(serde_json::to_string(self).unwrap_or_default().as_bytes())
+    }
+
+    pub fn from_header(value: &str) -> Result<Self, X402PaymentPayloadError> {
+        let json =
+            Base64::decode_vec(value.trim()).or(Err(X402PaymentPayloadError::InvalidHeader))?;
+
+        serde_json::from_slice(&json).or(Err(X402PaymentPayloadError::InvalidHeader))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, thiserror::Error)]
+pub enum X402PaymentPayloadError {
+    /// The header is not base64 encoded JSON of the payload
+    #[error("The payment header is not valid base64 encoded JSON")]
+    InvalidHeader,
+    /// Only version 1 of the x402 protocol is supported
+    #[error("The x402 version `{0}` is not supported")]
+    UnsupportedVersion(u8),
+    /// Only the `exact` scheme is supported
+    #[error("The payment scheme `{0}` is not supported")]
+    UnsupportedScheme(String),
+    /// The network is not a Solana cluster
+    #[error("The payment network `{0}` is not supported")]
+    UnsupportedNetwork(String),
+    /// The transaction is not base64 encoded
+    #[error("The payment transaction is not valid base64")]
+    InvalidTransaction,
+}
+
+#[cfg(test)]
+mod x402_payment_payload_sanity {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let payload = X402PaymentPayload::new(SolanaChain::Devnet, "AQID");
+        let header = payload.to_header();
+
+        assert_eq!(
+            X402PaymentPayload::from_header(&header),
+            Ok(payload.clone())
+        );
+        assert_eq!(payload.chain(), Ok(SolanaChain::Devnet));
+        assert_eq!(payload.transaction_bytes(), Ok(vec![1, 2, 3]));
+        assert!(serde_json::to_string(&payload)
+            .unwrap()
+            .contains("\"x402Version\":1"));
+
+        let response = X402PaymentResponse {
+            success: true,
+            transaction: "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW".to_string(),
+            network: SolanaChain::Devnet.x402_id().to_string(),
+            payer: "8BnEgHoWFysVcuFFX7QztDmzuH8r5ZFvyP3sYwn1XTh6".to_string(),
+        };
+        assert_eq!(
+            X402PaymentResponse::from_header(&response.to_header()),
+            Ok(response)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            X402PaymentPayload::from_header("not base64!"),
+            Err(X402PaymentPayloadError::InvalidHeader)
+        );
+
+        let mut payload = X402PaymentPayload::new(SolanaChain::Devnet, "AQID");
+        payload.scheme = "upto".to_string();
+        assert_eq!(
+            X402PaymentPayload::from_header(&payload.to_header()),
+            Err(X402PaymentPayloadError::UnsupportedScheme(
+                "upto".to_string()
+            ))
+        );
+
+        payload.scheme = X402PaymentPayload::EXACT_SCHEME.to_string();
+        payload.x402_version = 2;
+        assert_eq!(
+            X402PaymentPayload::from_header(&payload.to_header()),
+            Err(X402PaymentPayloadError::UnsupportedVersion(2))
+        );
+
+        payload.network = "base-sepolia".to_string();
+        assert_eq!(
+            payload.chain(),
+            Err(X402PaymentPayloadError::UnsupportedNetwork(
+                "base-sepolia".to_string()
+            ))
+        );
+
+        payload.payload.transaction = "%%%".to_string();
+        assert_eq!(
+            payload.transaction_bytes(),
+            Err(X402PaymentPayloadError::InvalidTransaction)
+        );
+    }
+}
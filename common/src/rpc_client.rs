@@ -0,0 +1,347 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    RpcBlockhash, RpcCommitment, RpcErrorObject, RpcId, RpcResponse, RpcResponseAccountInfo,
+    RpcResponseWithContext, RpcSignatureStatus, RpcSimulateTransactionResult,
+};
+
+/// Sends a serialized JSON-RPC request and returns the raw response body
+pub trait RpcTransport {
+    fn send(&self, body: String) -> RpcClientResult<String>;
+}
+
+/// Blocking HTTP transport that posts requests to a RPC endpoint
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HttpTransport {
+    url: String,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+}
+
+impl RpcTransport for HttpTransport {
+    fn send(&self, body: String) -> RpcClientResult<String> {
+        let response = minreq::post(self.url.as_str())
+            .with_header("Content-Type", "application/json")
+            .with_body(body)
+            .send()
+            .map_err(|error| RpcClientError::Transport(error.to_string()))?;
+
+        response
+            .as_str()
+            .map(|body| body.to_string())
+            .map_err(|error| RpcClientError::Transport(error.to_string()))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RpcRequest<'a, P> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: P,
+}
+
+/// A typed Solana JSON-RPC 2.0 client
+#[derive(Debug)]
+pub struct SolanaRpcClient<T> {
+    transport: T,
+    next_id: AtomicU64,
+}
+
+impl SolanaRpcClient<HttpTransport> {
+    /// A client that posts requests to `url` over HTTP
+    pub fn new(url: &str) -> Self {
+        Self::with_transport(HttpTransport::new(url))
+    }
+}
+
+impl<T: RpcTransport> SolanaRpcClient<T> {
+    pub const JSON_RPC_VERSION: &str = "2.0";
+
+    pub fn with_transport(transport: T) -> Self {
+        Self {
+            transport,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Calls `method` and returns the whole response including the `id`
+    pub fn call<P: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> RpcClientResult<RpcResponse<R>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let body = serde_json::to_string(&RpcRequest {
+            jsonrpc: Self::JSON_RPC_VERSION,
+            id,
+            method,
+            params,
+        })
+        .map_err(|error| RpcClientError::InvalidRequest(error.to_string()))?;
+
+        let response = serde_json::from_str::<RpcResponse<R>>(&self.transport.send(body)?)
+            .map_err(|error| RpcClientError::InvalidResponse(error.to_string()))?;
+
+        // Servers reply with a `null` id when they cannot parse the request
+        if response.id != RpcId::Number(id) && response.id != RpcId::Null {
+            return Err(RpcClientError::MismatchedId(response.id));
+        }
+
+        Ok(response)
+    }
+
+    /// Calls `method` and returns the `result` or the `error` object as [RpcClientError::Rpc]
+    pub fn request<P: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> RpcClientResult<R> {
+        self.call(method, params)?
+            .into_result()
+            .map_err(RpcClientError::Rpc)
+    }
+
+    /// Fetches an account with its data encoded as base64
+    pub fn get_account_info(
+        &self,
+        address: &str,
+        commitment: RpcCommitment,
+    ) -> RpcClientResult<RpcResponseWithContext<Option<RpcResponseAccountInfo>>> {
+        self.request(
+            "getAccountInfo",
+            (
+                address,
+                serde_json::json!({ "commitment": commitment, "encoding": "base64" }),
+            ),
+        )
+    }
+
+    /// Fetches up to 100 accounts with their data encoded as base64
+    pub fn get_multiple_accounts(
+        &self,
+        addresses: &[&str],
+        commitment: RpcCommitment,
+    ) -> RpcClientResult<RpcResponseWithContext<Vec<Option<RpcResponseAccountInfo>>>> {
+        self.request(
+            "getMultipleAccounts",
+            (
+                addresses,
+                serde_json::json!({ "commitment": commitment, "encoding": "base64" }),
+            ),
+        )
+    }
+
+    pub fn get_latest_blockhash(
+        &self,
+        commitment: RpcCommitment,
+    ) -> RpcClientResult<RpcResponseWithContext<RpcBlockhash>> {
+        self.request(
+            "getLatestBlockhash",
+            [serde_json::json!({ "commitment": commitment })],
+        )
+    }
+
+    /// The statuses in the same order as `signatures`, `None` for unknown signatures
+    pub fn get_signature_statuses(
+        &self,
+        signatures: &[&str],
+        search_transaction_history: bool,
+    ) -> RpcClientResult<RpcResponseWithContext<Vec<Option<RpcSignatureStatus>>>> {
+        self.request(
+            "getSignatureStatuses",
+            (
+                signatures,
+                serde_json::json!({ "searchTransactionHistory": search_transaction_history }),
+            ),
+        )
+    }
+
+    /// Simulates a base64 encoded transaction, replacing its blockhash when `sig_verify` is false
+    pub fn simulate_transaction(
+        &self,
+        transaction: &str,
+        sig_verify: bool,
+        commitment: RpcCommitment,
+    ) -> RpcClientResult<RpcResponseWithContext<RpcSimulateTransactionResult>> {
+        self.request(
+            "simulateTransaction",
+            (
+                transaction,
+                serde_json::json!({
+                    "encoding": "base64",
+                    "sigVerify": sig_verify,
+                    "replaceRecentBlockhash": !sig_verify,
+                    "commitment": commitment,
+                }),
+            ),
+        )
+    }
+
+    /// Sends a base64 encoded signed transaction and returns its signature
+    pub fn send_transaction(
+        &self,
+        transaction: &str,
+        skip_preflight: bool,
+    ) -> RpcClientResult<String> {
+        self.request(
+            "sendTransaction",
+            (
+                transaction,
+                serde_json::json!({ "encoding": "base64", "skipPreflight": skip_preflight }),
+            ),
+        )
+    }
+}
+
+pub type RpcClientResult<T> = Result<T, RpcClientError>;
+
+#[derive(Debug, PartialEq, Eq, Clone, thiserror::Error)]
+pub enum RpcClientError {
+    /// The transport was unable to deliver the request or read the response
+    #[error("Unable to reach the RPC. Error: {0}")]
+    Transport(String),
+    /// The request parameters could not be serialized
+    #[error("Unable to serialize the JSON-RPC request. Error: {0}")]
+    InvalidRequest(String),
+    /// The response is not a JSON-RPC 2.0 response of the expected type
+    #[error("Unable to parse the JSON-RPC response. Error: {0}")]
+    InvalidResponse(String),
+    /// The response answers a different request
+    #[error("The JSON-RPC response id `{0}` does not match the request")]
+    MismatchedId(RpcId),
+    /// The RPC returned an `error` object
+    #[error(transparent)]
+    Rpc(RpcErrorObject),
+}
+
+#[cfg(test)]
+mod rpc_client_sanity {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct MockTransport {
+        requests: Mutex<Vec<serde_json::Value>>,
+        response: &'static str,
+    }
+
+    impl MockTransport {
+        fn new(response: &'static str) -> Self {
+            Self {
+                requests: Mutex::default(),
+                response,
+            }
+        }
+    }
+
+    impl RpcTransport for MockTransport {
+        fn send(&self, body: String) -> RpcClientResult<String> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push(serde_json::from_str(&body).unwrap());
+
+            Ok(self.response.to_string())
+        }
+    }
+
+    #[test]
+    fn test_result() {
+        let client = SolanaRpcClient::with_transport(MockTransport::new(
+            r#"{"jsonrpc":"2.0","id":1,"result":{"context":{"slot":2792},"value":{"blockhash":"EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N","lastValidBlockHeight":3090}}}"#,
+        ));
+
+        let blockhash = client
+            .get_latest_blockhash(RpcCommitment::Confirmed)
+            .unwrap();
+        assert_eq!(blockhash.context.slot, 2792);
+        assert_eq!(blockhash.context.api_version, None);
+        assert_eq!(blockhash.value.last_valid_block_height, 3090);
+
+        assert_eq!(
+            client.transport().requests.lock().unwrap()[0],
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "getLatestBlockhash",
+                "params": [{ "commitment": "confirmed" }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_nullable_values() {
+        let client = SolanaRpcClient::with_transport(MockTransport::new(
+            r#"{"jsonrpc":"2.0","id":1,"result":{"context":{"slot":82},"value":[{"slot":72,"confirmations":10,"err":null,"status":{"Ok":null},"confirmationStatus":"confirmed"},null]}}"#,
+        ));
+
+        let statuses = client
+            .get_signature_statuses(&["5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW", "unknown"], true)
+            .unwrap()
+            .value;
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(
+            statuses[0].as_ref().unwrap().confirmation_status,
+            Some(RpcCommitment::Confirmed)
+        );
+        assert_eq!(statuses[1], None);
+
+        let client = SolanaRpcClient::with_transport(MockTransport::new(
+            r#"{"jsonrpc":"2.0","id":1,"result":{"context":{"apiVersion":"2.2.1","slot":1},"value":null}}"#,
+        ));
+        let account = client
+            .get_account_info("11111111111111111111111111111111", RpcCommitment::Finalized)
+            .unwrap();
+        assert_eq!(account.context.api_version.as_deref(), Some("2.2.1"));
+        assert_eq!(account.value, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let client = SolanaRpcClient::with_transport(MockTransport::new(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"Invalid param: WrongSize"}}"#,
+        ));
+        assert_eq!(
+            client.send_transaction("AQ==", false),
+            Err(RpcClientError::Rpc(RpcErrorObject {
+                code: RpcErrorObject::INVALID_PARAMS,
+                message: "Invalid param: WrongSize".to_string(),
+                data: None,
+            }))
+        );
+
+        let client = SolanaRpcClient::with_transport(MockTransport::new(
+            r#"{"jsonrpc":"2.0","id":"other","result":"signature"}"#,
+        ));
+        assert_eq!(
+            client.send_transaction("AQ==", false),
+            Err(RpcClientError::MismatchedId(RpcId::String(
+                "other".to_string()
+            )))
+        );
+
+        let client = SolanaRpcClient::with_transport(MockTransport::new("<html>"));
+        assert!(matches!(
+            client.send_transaction("AQ==", false),
+            Err(RpcClientError::InvalidResponse(_))
+        ));
+    }
+}
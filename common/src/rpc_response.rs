@@ -1,12 +1,76 @@
+use core::fmt;
+
 use serde::{Deserialize, Serialize};
 
+/// A JSON-RPC 2.0 response which has either a `result` or an `error`
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 pub struct RpcResponse<T> {
-    pub id: u8,
+    pub id: RpcId,
     pub jsonrpc: String,
-    pub result: T,
+    #[serde(flatten)]
+    pub outcome: RpcOutcome<T>,
+}
+
+impl<T> RpcResponse<T> {
+    pub fn into_result(self) -> Result<T, RpcErrorObject> {
+        match self.outcome {
+            RpcOutcome::Success { result } => Ok(result),
+            RpcOutcome::Failure { error } => Err(error),
+        }
+    }
 }
 
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum RpcOutcome<T> {
+    Failure { error: RpcErrorObject },
+    Success { result: T },
+}
+
+/// The request id which servers echo back as a number or a string
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RpcId {
+    Number(u64),
+    String(String),
+    Null,
+}
+
+impl fmt::Display for RpcId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(id) => write!(f, "{id}"),
+            Self::String(id) => f.write_str(id),
+            Self::Null => f.write_str("null"),
+        }
+    }
+}
+
+/// The `error` object of a failed JSON-RPC call
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RpcErrorObject {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl RpcErrorObject {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+}
+
+impl fmt::Display for RpcErrorObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for RpcErrorObject {}
+
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 pub struct RpcResponseWithContext<U> {
     pub context: RpcContext,
@@ -16,11 +80,11 @@ pub struct RpcResponseWithContext<U> {
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcContext {
-    pub api_version: String,
+    pub api_version: Option<String>,
     pub slot: u64,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcResponseAccountInfo {
     pub data: (String, String),
@@ -31,6 +95,44 @@ pub struct RpcResponseAccountInfo {
     pub space: u64,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSignatureStatus {
+    pub slot: u64,
+    /// `None` once the transaction is rooted
+    pub confirmations: Option<u64>,
+    pub err: Option<serde_json::Value>,
+    pub confirmation_status: Option<RpcCommitment>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSimulateTransactionResult {
+    pub err: Option<serde_json::Value>,
+    pub logs: Option<Vec<String>>,
+    pub units_consumed: Option<u64>,
+    #[serde(default)]
+    pub replacement_blockhash: Option<RpcBlockhash>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBlockhash {
+    pub blockhash: String,
+    pub last_valid_block_height: u64,
+}
+
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum RpcCommitment {
+    Processed,
+    Confirmed,
+    #[default]
+    Finalized,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct MintInfo {
     pub program_id: String,
@@ -0,0 +1,282 @@
+use core::str::FromStr;
+use std::collections::BTreeMap;
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+use crate::{RpcClientResult, RpcTransport, SolanaRpcClient};
+
+/// The `getTipInstructions` request of the Sanctum gateway
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TipInstructionsRequest {
+    pub fee_payer: String,
+    /// Defaults to the project parameters of the gateway
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jito_tip_range: Option<JitoTipRange>,
+    /// Defaults to the project parameters of the gateway
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivery_method_type: Option<DeliveryMethodType>,
+}
+
+impl TipInstructionsRequest {
+    pub const METHOD: &str = "getTipInstructions";
+
+    pub fn new(fee_payer: &str) -> Self {
+        Self {
+            fee_payer: fee_payer.to_string(),
+            jito_tip_range: Option::None,
+            delivery_method_type: Option::None,
+        }
+    }
+
+    pub fn set_jito_tip_range(&mut self, jito_tip_range: JitoTipRange) -> &mut Self {
+        self.jito_tip_range.replace(jito_tip_range);
+
+        self
+    }
+
+    pub fn set_delivery_method_type(
+        &mut self,
+        delivery_method_type: DeliveryMethodType,
+    ) -> &mut Self {
+        self.delivery_method_type.replace(delivery_method_type);
+
+        self
+    }
+
+    /// Requests the tip instructions from the Sanctum gateway
+    pub fn send<T: RpcTransport>(
+        &self,
+        client: &SolanaRpcClient<T>,
+    ) -> RpcClientResult<Vec<TipResponse>> {
+        client.request(Self::METHOD, [self])
+    }
+}
+
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum JitoTipRange {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Max,
+}
+
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeliveryMethodType {
+    Rpc,
+    Jito,
+    #[default]
+    SanctumSender,
+    HeliusSender,
+}
+
+/// A tip instruction in the `@solana/kit` JSON format
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TipResponse {
+    pub accounts: Vec<TipAccountInfo>,
+    pub program_address: String,
+    /// Serialized as a `Uint8Array` which is an object keyed by the byte index
+    #[serde(deserialize_with = "TipResponse::deserialize_data")]
+    pub data: Vec<u8>,
+}
+
+impl TipResponse {
+    pub fn to_instruction(&self) -> Result<Instruction, TipInstructionError> {
+        let accounts = self
+            .accounts
+            .iter()
+            .map(TipAccountInfo::to_account_meta)
+            .collect::<Result<Vec<AccountMeta>, TipInstructionError>>()?;
+
+        Ok(Instruction {
+            program_id: TipAccountInfo::parse_address(&self.program_address)?,
+            accounts,
+            data: self.data.clone(),
+        })
+    }
+
+    /// Orders the bytes by their numeric index since a JSON object has no order
+    /// and sorting the keys as strings puts `"10"` before `"2"`
+    fn deserialize_data<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Uint8Array {
+            Array(Vec<u8>),
+            Indexed(BTreeMap<String, u8>),
+        }
+
+        let indexed = match Uint8Array::deserialize(deserializer)? {
+            Uint8Array::Array(bytes) => return Ok(bytes),
+            Uint8Array::Indexed(indexed) => indexed,
+        };
+
+        let ordered = indexed
+            .into_iter()
+            .map(|(index, byte)| {
+                index
+                    .parse::<usize>()
+                    .map(|index| (index, byte))
+                    .map_err(|_| de::Error::custom(format!("Invalid byte index `{index}`")))
+            })
+            .collect::<Result<BTreeMap<usize, u8>, D::Error>>()?;
+
+        ordered
+            .into_iter()
+            .enumerate()
+            .map(|(expected, (index, byte))| {
+                if expected == index {
+                    Ok(byte)
+                } else {
+                    Err(de::Error::custom(format!(
+                        "Missing the byte at index {expected}"
+                    )))
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct TipAccountInfo {
+    pub address: String,
+    /// The `AccountRole` of `@solana/kit`, bit 0 is writable and bit 1 is signer
+    pub role: u8,
+    #[serde(default)]
+    pub signer: Option<TipSignerInfo>,
+}
+
+impl TipAccountInfo {
+    pub const WRITABLE: u8 = 0b01;
+    pub const SIGNER: u8 = 0b10;
+
+    pub fn is_writable(&self) -> bool {
+        self.role & Self::WRITABLE == Self::WRITABLE
+    }
+
+    pub fn is_signer(&self) -> bool {
+        self.role & Self::SIGNER == Self::SIGNER
+    }
+
+    pub fn to_account_meta(&self) -> Result<AccountMeta, TipInstructionError> {
+        if self.role > (Self::WRITABLE | Self::SIGNER) {
+            return Err(TipInstructionError::InvalidAccountRole(self.role));
+        }
+
+        let pubkey = Self::parse_address(&self.address)?;
+
+        Ok(if self.is_writable() {
+            AccountMeta::new(pubkey, self.is_signer())
+        } else {
+            AccountMeta::new_readonly(pubkey, self.is_signer())
+        })
+    }
+
+    fn parse_address(address: &str) -> Result<Pubkey, TipInstructionError> {
+        Pubkey::from_str(address)
+            .map_err(|_| TipInstructionError::InvalidAddress(address.to_string()))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct TipSignerInfo {
+    pub address: String,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, thiserror::Error)]
+pub enum TipInstructionError {
+    /// An account or program address is not a base58 public key
+    #[error("Invalid base58 address `{0}` in the tip instruction")]
+    InvalidAddress(String),
+    /// The account role is not one of the four `AccountRole` values
+    #[error("Invalid account role `{0}` in the tip instruction")]
+    InvalidAccountRole(u8),
+}
+
+#[cfg(test)]
+mod sanctum_tip_sanity {
+    use super::*;
+
+    const TIP_RESPONSE: &str = r#"{
+        "accounts": [
+            {
+                "address": "8BnEgHoWFysVcuFFX7QztDmzuH8r5ZFvyP3sYwn1XTh6",
+                "role": 3,
+                "signer": { "address": "8BnEgHoWFysVcuFFX7QztDmzuH8r5ZFvyP3sYwn1XTh6" }
+            },
+            { "address": "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5", "role": 1 }
+        ],
+        "programAddress": "11111111111111111111111111111111",
+        "data": {
+            "0": 2, "1": 0, "2": 0, "3": 0, "4": 64, "5": 66,
+            "6": 15, "7": 0, "8": 0, "9": 0, "10": 0, "11": 0
+        }
+    }"#;
+
+    #[test]
+    fn test_to_instruction() {
+        let tip = serde_json::from_str::<TipResponse>(TIP_RESPONSE).unwrap();
+        // System program transfer of 1_000_000 lamports
+        assert_eq!(tip.data, vec![2, 0, 0, 0, 64, 66, 15, 0, 0, 0, 0, 0]);
+
+        let instruction = tip.to_instruction().unwrap();
+        assert_eq!(instruction.program_id, Pubkey::default());
+        assert!(instruction.accounts[0].is_signer && instruction.accounts[0].is_writable);
+        assert!(!instruction.accounts[1].is_signer && instruction.accounts[1].is_writable);
+
+        let from_array = serde_json::from_str::<TipResponse>(
+            r#"{"accounts":[],"programAddress":"11111111111111111111111111111111","data":[2,0]}"#,
+        )
+        .unwrap();
+        assert_eq!(from_array.data, vec![2, 0]);
+    }
+
+    #[test]
+    fn test_request() {
+        let mut request =
+            TipInstructionsRequest::new("8BnEgHoWFysVcuFFX7QztDmzuH8r5ZFvyP3sYwn1XTh6");
+        request
+            .set_jito_tip_range(JitoTipRange::High)
+            .set_delivery_method_type(DeliveryMethodType::SanctumSender);
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "feePayer": "8BnEgHoWFysVcuFFX7QztDmzuH8r5ZFvyP3sYwn1XTh6",
+                "jitoTipRange": "high",
+                "deliveryMethodType": "sanctum-sender"
+            })
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(serde_json::from_str::<TipResponse>(
+            r#"{"accounts":[],"programAddress":"11111111111111111111111111111111","data":{"0":2,"2":0}}"#,
+        )
+        .is_err());
+
+        let mut tip = serde_json::from_str::<TipResponse>(TIP_RESPONSE).unwrap();
+        tip.accounts[1].role = 4;
+        assert_eq!(
+            tip.to_instruction(),
+            Err(TipInstructionError::InvalidAccountRole(4))
+        );
+
+        tip.accounts[1].role = 1;
+        tip.program_address = "0OIl".to_string();
+        assert_eq!(
+            tip.to_instruction(),
+            Err(TipInstructionError::InvalidAddress("0OIl".to_string()))
+        );
+    }
+}
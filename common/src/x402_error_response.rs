@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+/// The JSON body of a failed request to a x402 resource
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct X402ErrorResponse {
+    pub status: u16,
+    pub code: X402ErrorCode,
+    pub error: String,
+    pub resource: String,
+    /// The request header that caused the error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+}
+
+impl X402ErrorResponse {
+    /// The status defaults to [X402ErrorCode::status]
+    pub fn new(code: X402ErrorCode, resource: &str, error: &str) -> Self {
+        Self {
+            status: code.status(),
+            code,
+            error: error.to_string(),
+            resource: resource.to_string(),
+            header: Option::None,
+        }
+    }
+
+    /// A [X402ErrorCode::MissingHeader] error for `header`
+    pub fn missing_header(resource: &str, header: &str, expected: &str) -> Self {
+        let error = format!(
+            "Bad request. The `{header}` header is missing or malformed. It requires {expected}"
+        );
+        let mut response = Self::new(X402ErrorCode::MissingHeader, resource, &error);
+        response.set_header(header);
+
+        response
+    }
+
+    pub fn set_header(&mut self, header: &str) -> &mut Self {
+        self.header.replace(header.to_string());
+
+        self
+    }
+
+    pub fn set_status(&mut self, status: u16) -> &mut Self {
+        self.status = status;
+
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        // Only strings and integers are serialized which cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Parses the body of a response with a status of 400 and above.
+    /// Returns `None` if the body is not a x402 error response.
+    pub fn parse(status: u16, body: &str) -> Option<Self> {
+        if status < 400 {
+            return Option::None;
+        }
+
+        serde_json::from_str::<Self>(body).ok().map(|mut response| {
+            // The status line of the response takes precedence over the body
+            response.status = status;
+
+            response
+        })
+    }
+}
+
+/// Machine-readable error codes of [X402ErrorResponse]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum X402ErrorCode {
+    /// A required header is missing or malformed
+    MissingHeader,
+    /// The request body or parameters are invalid
+    InvalidRequest,
+    /// The `X402-Client-Proof` header does not authenticate the `X402-Client-Address`
+    InvalidClientProof,
+    /// The resource does not accept payments on the requested network
+    UnsupportedNetwork,
+    /// The `X-PAYMENT` header is not a valid payment payload
+    InvalidPayment,
+    /// The payment does not satisfy the payment requirements of the resource
+    PaymentRejected,
+    /// The payment or its access token has expired
+    PaymentExpired,
+    /// The payment was already used to access the resource
+    PaymentAlreadyUsed,
+    /// The facilitator could not verify or settle the payment
+    FacilitatorUnavailable,
+    /// The payment was verified but settling it onchain failed
+    SettlementFailed,
+    InternalError,
+    /// A code added by a newer server
+    #[serde(other)]
+    Unknown,
+}
+
+impl X402ErrorCode {
+    /// The HTTP status code of the error
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::MissingHeader
+            | Self::InvalidRequest
+            | Self::UnsupportedNetwork
+            | Self::InvalidPayment => 400,
+            Self::InvalidClientProof => 401,
+            Self::PaymentRejected | Self::PaymentExpired => 402,
+            Self::PaymentAlreadyUsed => 409,
+            Self::FacilitatorUnavailable | Self::SettlementFailed => 502,
+            Self::InternalError | Self::Unknown => 500,
+        }
+    }
+}
+
+#[cfg(test)]
+mod x402_error_response_sanity {
+    use super::*;
+    use crate::CommonHeaders;
+
+    #[test]
+    fn test_round_trip() {
+        let response = X402ErrorResponse::missing_header(
+            "https://lagoon.markets/latest_newsletter",
+            CommonHeaders::X402_CHAIN_HEADER,
+            "the chain identification in x402 format",
+        );
+        assert_eq!(response.status, 400);
+        assert_eq!(
+            response.header.as_deref(),
+            Some(CommonHeaders::X402_CHAIN_HEADER)
+        );
+
+        let json = response.to_json();
+        assert!(json.contains("\"code\":\"missing_header\""));
+        assert_eq!(X402ErrorResponse::parse(400, &json), Some(response));
+
+        let mut conflict = X402ErrorResponse::new(
+            X402ErrorCode::PaymentAlreadyUsed,
+            "https://lagoon.markets/latest_newsletter",
+            "Replayed payment",
+        );
+        assert_eq!(conflict.status, 409);
+        conflict.set_status(500);
+        assert_eq!(
+            X402ErrorResponse::parse(409, &conflict.to_json()).map(|parsed| parsed.status),
+            Some(409)
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            X402ErrorResponse::parse(
+                503,
+                r#"{"status":503,"code":"rate_limited","error":"Slow down","resource":"https://example.com"}"#
+            )
+            .map(|response| response.code),
+            Some(X402ErrorCode::Unknown)
+        );
+        assert_eq!(X402ErrorResponse::parse(500, "Internal Server Error"), None);
+        assert_eq!(
+            X402ErrorResponse::parse(
+                200,
+                &X402ErrorResponse::new(X402ErrorCode::InternalError, "", "").to_json()
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use common::{CommonHeaders, X402AccessToken};
+
+use crate::{api::client_proof::HttpHeaderFfi, AppStorage, NativeError, NativeResult};
+
+/// Stores the `X402-Access-Token` header of a paid response so that later requests
+/// to the resource are not paid again until the token expires
+#[uniffi::export]
+pub fn rustffi_store_access_token(token: String) -> NativeResult<AccessTokenFfi> {
+    AppStorage::get_store()?
+        .set_access_token(&token)
+        .map(AccessTokenFfi::from)
+}
+
+/// The unexpired access token stored for the resource at `url`
+#[uniffi::export]
+pub fn rustffi_access_token(url: String) -> NativeResult<Option<AccessTokenFfi>> {
+    AppStorage::get_store()?
+        .get_access_token(&url, AccessTokenFfi::now())?
+        .map(|token| X402AccessToken::decode(&token).map(AccessTokenFfi::from))
+        .transpose()
+        .map_err(|error| NativeError::InvalidAccessToken(error.to_string()))
+}
+
+#[derive(Debug, uniffi::Record, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct AccessTokenFfi {
+    pub resource: String,
+    pub payer: String,
+    /// Unix timestamp in seconds
+    pub expires_at: u64,
+}
+
+impl AccessTokenFfi {
+    /// The `X402-Access-Token` header for a request to `url` if a token is stored for it
+    pub(crate) fn header(url: &str) -> NativeResult<Option<HttpHeaderFfi>> {
+        Ok(AppStorage::get_store()?
+            .get_access_token(url, Self::now())?
+            .map(|token| HttpHeaderFfi {
+                name: CommonHeaders::X402_ACCESS_TOKEN_HEADER.to_string(),
+                value: token,
+            }))
+    }
+
+    /// `url` with the stored access token as a query parameter, for `EventSource`
+    /// which cannot set headers. `url` is unchanged when there is no token.
+    pub(crate) fn url_with_token(url: &str) -> String {
+        AppStorage::get_store()
+            .and_then(|store| store.get_access_token(url, Self::now()))
+            .ok()
+            .flatten()
+            .map_or(url.to_string(), |token| {
+                X402AccessToken::append_to_url(url, &token)
+            })
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+impl From<X402AccessToken> for AccessTokenFfi {
+    fn from(value: X402AccessToken) -> Self {
+        Self {
+            resource: value.resource,
+            payer: value.payer,
+            expires_at: value.expires_at,
+        }
+    }
+}
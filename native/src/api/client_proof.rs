@@ -0,0 +1,138 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use common::{CommonHeaders, X402ClientProof, X402ClientProofSigner};
+
+use crate::{api::access_token::AccessTokenFfi, AppStorage, NativeError, NativeResult};
+
+static NONCE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The resource to add to the Sign In With Solana message so that the session key
+/// can sign `X402-Client-Proof` headers on behalf of the address
+#[uniffi::export]
+pub fn rustffi_siws_session_resource() -> NativeResult<String> {
+    let secret = AppStorage::get_store()?.get_or_create_session_secret()?;
+
+    Ok(X402ClientProofSigner::new(&secret).session_resource())
+}
+
+/// The `X402-Client-Address` and `X402-Client-Proof` headers for a request to `url`,
+/// and the `X402-Access-Token` header when an unexpired access token is stored for it
+#[uniffi::export]
+pub fn rustffi_x402_client_headers(
+    method: String,
+    url: String,
+) -> NativeResult<Vec<HttpHeaderFfi>> {
+    let (address, proof) = sign_request(&method, &url)?;
+
+    let mut headers = vec![
+        HttpHeaderFfi {
+            name: CommonHeaders::X402_ADDRESS_HEADER.to_string(),
+            value: address,
+        },
+        HttpHeaderFfi {
+            name: CommonHeaders::X402_CLIENT_PROOF_HEADER.to_string(),
+            value: proof.to_header(),
+        },
+    ];
+    headers.extend(AccessTokenFfi::header(&url)?);
+
+    Ok(headers)
+}
+
+/// `url` with the client proof of a `GET` request in its query, for `EventSource`
+/// which cannot set headers
+pub(crate) fn url_with_client_proof(url: &str) -> NativeResult<String> {
+    let (address, proof) = sign_request("GET", url)?;
+
+    Ok(proof.append_to_url(url, &address))
+}
+
+/// The address of the signed in user and its client proof for a request to `url`
+fn sign_request(method: &str, url: &str) -> NativeResult<(String, X402ClientProof)> {
+    let store = AppStorage::get_store()?;
+    let auth = store.get_auth()?.ok_or(NativeError::MissingUserAddress)?;
+    let secret = store.get_or_create_session_secret()?;
+
+    let mut signer = X402ClientProofSigner::new(&secret);
+    signer.set_siws_delegation(&auth.signed_message, &auth.signature);
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let address = auth.address();
+    let proof = signer.sign(
+        &address,
+        method,
+        &HttpHeaderFfi::origin_form(url),
+        timestamp,
+        &HttpHeaderFfi::nonce(&secret),
+    );
+
+    Ok((address, proof))
+}
+
+#[derive(Debug, uniffi::Record, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct HttpHeaderFfi {
+    pub name: String,
+    pub value: String,
+}
+
+impl HttpHeaderFfi {
+    /// The path and query of `url` which is what the server sees as the request target
+    fn origin_form(url: &str) -> String {
+        let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let without_fragment = without_scheme
+            .split_once('#')
+            .map_or(without_scheme, |(rest, _)| rest);
+
+        match without_fragment.find(['/', '?']) {
+            Some(index) if without_fragment[index..].starts_with('/') => {
+                without_fragment[index..].to_string()
+            }
+            Some(index) => String::from("/") + &without_fragment[index..],
+            None => String::from("/"),
+        }
+    }
+
+    /// Unique per request without a source of randomness since it only has to
+    /// not repeat for the session key
+    fn nonce(secret: &[u8; 32]) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+
+        let mut hasher = blake3::Hasher::new_keyed(secret);
+        hasher.update(&nanos.to_le_bytes());
+        hasher.update(&NONCE_COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+
+        bs58::encode(&hasher.finalize().as_bytes()[..16]).into_string()
+    }
+}
+
+#[cfg(test)]
+mod client_proof_sanity {
+    use super::*;
+
+    #[test]
+    fn test_origin_form() {
+        assert_eq!(
+            HttpHeaderFfi::origin_form("https://lagoon.markets/latest_newsletter?page=2#top"),
+            "/latest_newsletter?page=2"
+        );
+        assert_eq!(
+            HttpHeaderFfi::origin_form("https://lagoon.markets?page=2"),
+            "/?page=2"
+        );
+        assert_eq!(HttpHeaderFfi::origin_form("https://lagoon.markets"), "/");
+    }
+
+    #[test]
+    fn test_nonce() {
+        let first = HttpHeaderFfi::nonce(&[7u8; 32]);
+        assert_ne!(first, HttpHeaderFfi::nonce(&[7u8; 32]));
+        assert!(first.len() >= common::X402ClientProof::MIN_NONCE_LENGTH);
+    }
+}
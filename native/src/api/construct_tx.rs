@@ -1,6 +1,7 @@
 use std::str::FromStr;
 
-use common::MintInfo;
+use common::{MintInfo, SolanaChain};
+use solana_instruction::Instruction;
 use solana_message::Message;
 use solana_pubkey::Pubkey;
 use solana_transaction::Transaction;
@@ -11,7 +12,7 @@ use crate::{api::discovery::DiscoveryFfi, AppStorage, NativeError, NativeResult}
 #[uniffi::export]
 pub async fn rustffi_construct_tx(
     x402_resource: DiscoveryFfi,
-    mainnet: bool,
+    chain: SolanaChainFfi,
 ) -> NativeResult<Vec<u8>> {
     let asset_pubkey =
         Pubkey::from_str(&x402_resource.asset).or(Err(NativeError::InvalidBase58String))?;
@@ -33,7 +34,7 @@ pub async fn rustffi_construct_tx(
         .or(Err(NativeError::AmountNotU64))?;
 
     if asset_pubkey == solana_system_interface::program::ID {
-        transfer_sol(user_pubkey, fee_payer, pay_to, amount)
+        transfer_sol(user_pubkey, fee_payer, pay_to, amount, &[])
     } else {
         transfer_token(
             user_pubkey,
@@ -41,7 +42,8 @@ pub async fn rustffi_construct_tx(
             pay_to,
             amount,
             asset_pubkey,
-            mainnet,
+            chain.into(),
+            &[],
         )
         .await
     }
@@ -53,11 +55,13 @@ pub async fn transfer_token(
     pay_to: Pubkey,
     amount: u64,
     asset: Pubkey,
-    mainnet: bool,
+    chain: SolanaChain,
+    tip_instructions: &[Instruction],
 ) -> NativeResult<Vec<u8>> {
     let mut url = "https://lagoon.markets/mint-info/".to_string();
     url.push_str(asset.to_string().as_str());
-    url.push_str(if mainnet { "/mainnet" } else { "/devnet" });
+    url.push('/');
+    url.push_str(chain.x402_id());
 
     let response = blocking::unblock(move || {
         minreq::post(url)
@@ -66,6 +70,14 @@ pub async fn transfer_token(
     })
     .await
     .map_err(|error| NativeError::Https(error.to_string()))?;
+
+    if response.status_code >= 400 {
+        return Err(NativeError::from_response(
+            response.status_code,
+            response.as_str().unwrap_or_default(),
+        ));
+    }
+
     let mint_data = serde_json::from_str::<MintInfo>(
         response
             .as_str()
@@ -97,7 +109,9 @@ pub async fn transfer_token(
     )
     .map_err(|error| NativeError::InvalidTransferCheckedData(error.to_string()))?;
 
-    let message = Message::new(&[transfer_ix], Some(&fee_payer));
+    let mut instructions = vec![transfer_ix];
+    instructions.extend_from_slice(tip_instructions);
+    let message = Message::new(&instructions, Some(&fee_payer));
     let transfer_tx = Transaction::new_unsigned(message);
 
     bincode::serialize(&transfer_tx).or(Err(NativeError::UnableToSerializeTransaction))
@@ -108,11 +122,45 @@ pub fn transfer_sol(
     fee_payer: Pubkey,
     pay_to: Pubkey,
     amount: u64,
+    tip_instructions: &[Instruction],
 ) -> NativeResult<Vec<u8>> {
     let transfer_ix = solana_system_interface::instruction::transfer(&user_pubkey, &pay_to, amount);
 
-    let message = Message::new(&[transfer_ix], Some(&fee_payer));
+    let mut instructions = vec![transfer_ix];
+    instructions.extend_from_slice(tip_instructions);
+    let message = Message::new(&instructions, Some(&fee_payer));
     let transfer_tx = Transaction::new_unsigned(message);
 
     bincode::serialize(&transfer_tx).or(Err(NativeError::UnableToSerializeTransaction))
 }
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Default, uniffi::Enum)]
+pub enum SolanaChainFfi {
+    MainnetBeta,
+    Testnet,
+    #[default]
+    Devnet,
+    Localnet,
+}
+
+impl From<SolanaChainFfi> for SolanaChain {
+    fn from(value: SolanaChainFfi) -> Self {
+        match value {
+            SolanaChainFfi::MainnetBeta => Self::MainnetBeta,
+            SolanaChainFfi::Testnet => Self::Testnet,
+            SolanaChainFfi::Devnet => Self::Devnet,
+            SolanaChainFfi::Localnet => Self::Localnet,
+        }
+    }
+}
+
+impl From<SolanaChain> for SolanaChainFfi {
+    fn from(value: SolanaChain) -> Self {
+        match value {
+            SolanaChain::MainnetBeta => Self::MainnetBeta,
+            SolanaChain::Testnet => Self::Testnet,
+            SolanaChain::Devnet => Self::Devnet,
+            SolanaChain::Localnet => Self::Localnet,
+        }
+    }
+}
@@ -1,15 +1,61 @@
+use std::sync::{Arc, Mutex};
+
 use blocking::unblock;
+use common::{X402DiscoveryPagination, X402DiscoveryQuery};
 use rusty_x402::DiscoveryPayload;
 use wincode::{SchemaRead, SchemaWrite};
-use x402_uri::{X402UriAction, X402UriError, X402UriScheme};
+use x402_uri::{X402Uri, X402UriAction, X402UriError, X402UriScheme};
 
-use crate::{AppStorage, NativeError, NativeResult, TokenInfo};
+use crate::{api::X402UriSignatureFfi, AppStorage, NativeError, NativeResult, TokenInfo};
 
+/// The first page of resources of the discovery URL with the pager of the next pages
 #[uniffi::export]
 pub async fn rustffi_discover_resources(
     x402_resource_uri: String,
-) -> Result<Vec<DiscoveryFfi>, NativeError> {
-    DiscoveryFfi::fetch(&x402_resource_uri).await
+) -> Result<DiscoveryPageFfi, NativeError> {
+    let (resources, pager) = DiscoveryFfi::fetch(&x402_resource_uri).await?;
+
+    Ok(DiscoveryPageFfi { resources, pager })
+}
+
+/// Discovers the resources of a `x402://discover/` URI and verifies the publisher signature
+#[uniffi::export]
+pub async fn rustffi_discover_x402_uri(x402_uri: String) -> NativeResult<X402DiscoveryFfi> {
+    let parsed =
+        X402Uri::new(&x402_uri).map_err(|error| NativeError::InvalidX402Uri(error.to_string()))?;
+
+    if parsed.action() != X402UriAction::Discover {
+        return Err(NativeError::InvalidX402Uri(
+            "Expected a `x402://discover/` URI".to_string(),
+        ));
+    }
+
+    let signature = X402UriSignatureFfi::verify(&parsed)?;
+    let (resources, pager) = DiscoveryFfi::fetch(parsed.uri()).await?;
+
+    Ok(X402DiscoveryFfi {
+        signature,
+        resources,
+        pager,
+    })
+}
+
+#[derive(Debug, uniffi::Record, Clone)]
+pub struct X402DiscoveryFfi {
+    /// Whether the publisher signed the `x402://discover/` URI
+    pub signature: X402UriSignatureFfi,
+    /// The first page of resources
+    pub resources: Vec<DiscoveryFfi>,
+    /// Fetches the pages after `resources`
+    pub pager: Arc<DiscoveryPagerFfi>,
+}
+
+#[derive(Debug, uniffi::Record, Clone)]
+pub struct DiscoveryPageFfi {
+    /// The first page of resources
+    pub resources: Vec<DiscoveryFfi>,
+    /// Fetches the pages after `resources`
+    pub pager: Arc<DiscoveryPagerFfi>,
 }
 
 #[derive(Debug, uniffi::Record, PartialEq, Eq, PartialOrd, Ord, Clone, SchemaRead, SchemaWrite)]
@@ -28,29 +74,59 @@ pub struct DiscoveryFfi {
 }
 
 impl DiscoveryFfi {
-    pub async fn fetch(x402_resource_uri: &str) -> NativeResult<Vec<Self>> {
+    /// Fetches the first page of the discovery URL, the pager fetches the next pages on demand
+    pub async fn fetch(
+        x402_resource_uri: &str,
+    ) -> NativeResult<(Vec<Self>, Arc<DiscoveryPagerFfi>)> {
+        let pager = DiscoveryPagerFfi::new(
+            x402_resource_uri.to_string(),
+            DiscoveryFiltersFfi::default(),
+        )?;
+        let resources = pager.next_page().await?;
+
+        Ok((resources, pager))
+    }
+
+    /// Splits the discovery URL into its filters and the URL without them
+    pub fn parse_uri(x402_resource_uri: &str) -> NativeResult<(String, X402DiscoveryQuery)> {
         let scheme: X402UriScheme = x402_resource_uri
             .try_into()
             .map_err(|error: X402UriError| NativeError::InvalidX402Uri(error.to_string()))?;
 
-        match scheme {
-            X402UriScheme::Https => Self::fetch_https(x402_resource_uri).await,
-            _ => Err(NativeError::UnsupportedX402Scheme),
+        if scheme != X402UriScheme::Https {
+            return Err(NativeError::UnsupportedX402Scheme);
         }
+
+        X402DiscoveryQuery::parse_url(x402_resource_uri)
+            .map_err(|error| NativeError::InvalidDiscoveryQuery(error.to_string()))
     }
 
-    pub async fn fetch_https(x402_resource_uri: &str) -> NativeResult<Vec<Self>> {
+    /// One page of resources and the pagination of the response, `None` for servers
+    /// that do not paginate
+    pub async fn fetch_page(
+        x402_resource_uri: &str,
+    ) -> NativeResult<(Vec<Self>, Option<X402DiscoveryPagination>)> {
         let owned_uri = x402_resource_uri.to_owned();
         let response = unblock(move || minreq::get(owned_uri).send())
             .await
             .map_err(|error| NativeError::Https(error.to_string()))?;
 
-        let parse_json = serde_json::from_str::<DiscoveryPayload>(
-            response
-                .as_str()
-                .map_err(|error| NativeError::Https(error.to_string()))?,
-        )
-        .map_err(|error| NativeError::Https(error.to_string()))?;
+        if response.status_code >= 400 {
+            return Err(NativeError::from_response(
+                response.status_code,
+                response.as_str().unwrap_or_default(),
+            ));
+        }
+
+        let body = response
+            .as_str()
+            .map_err(|error| NativeError::Https(error.to_string()))?;
+
+        let parse_json = serde_json::from_str::<DiscoveryPayload>(body)
+            .map_err(|error| NativeError::Https(error.to_string()))?;
+        let pagination = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|payload| X402DiscoveryPagination::from_payload(&payload));
 
         let mut output = Vec::<Self>::new();
 
@@ -96,7 +172,131 @@ impl DiscoveryFfi {
             Ok::<_, NativeError>(())
         })?;
 
-        Ok(output)
+        Ok((output, pagination))
+    }
+}
+
+/// The filters of a discovery request, `None` fields are not filtered on
+#[derive(Debug, uniffi::Record, PartialEq, Eq, Clone, Default)]
+pub struct DiscoveryFiltersFfi {
+    pub uri_scheme: Option<X402UriSchemeFfi>,
+    /// The mint address, or the system program for SOL
+    pub asset: Option<String>,
+    /// The minimum price in the smallest unit of the asset
+    pub min_amount: Option<u64>,
+    /// The maximum price in the smallest unit of the asset
+    pub max_amount: Option<u64>,
+    /// Words to search for in the title, description and URL
+    pub query: Option<String>,
+    /// The number of resources per page
+    pub limit: Option<u32>,
+}
+
+impl DiscoveryFiltersFfi {
+    /// Applies the filters on top of the ones already in the discovery URL
+    fn apply(&self, query: &mut X402DiscoveryQuery) -> NativeResult<()> {
+        if let Some(uri_scheme) = self.uri_scheme {
+            query.set_type(uri_scheme.resource_type());
+        }
+        if let Some(asset) = self.asset.as_deref() {
+            query.set_asset(asset);
+        }
+        if let Some(min_amount) = self.min_amount {
+            query.set_min_amount(min_amount);
+        }
+        if let Some(max_amount) = self.max_amount {
+            query.set_max_amount(max_amount);
+        }
+        if let Some(text) = self.query.as_deref() {
+            query.set_text(text);
+        }
+        if let Some(limit) = self.limit {
+            query.set_limit(limit);
+        }
+
+        query
+            .validate()
+            .map_err(|error| NativeError::InvalidDiscoveryQuery(error.to_string()))
+    }
+}
+
+/// Fetches the resources of a discovery URL one page at a time
+#[derive(Debug, uniffi::Object)]
+pub struct DiscoveryPagerFfi {
+    base_uri: String,
+    state: Mutex<DiscoveryPagerState>,
+}
+
+#[derive(Debug, Clone)]
+struct DiscoveryPagerState {
+    query: X402DiscoveryQuery,
+    /// `None` once the last page was fetched
+    next_offset: Option<u32>,
+    total: Option<u32>,
+    pages: u32,
+}
+
+#[uniffi::export]
+impl DiscoveryPagerFfi {
+    #[uniffi::constructor]
+    pub fn new(x402_resource_uri: String, filters: DiscoveryFiltersFfi) -> NativeResult<Arc<Self>> {
+        let (base_uri, mut query) = DiscoveryFfi::parse_uri(&x402_resource_uri)?;
+        filters.apply(&mut query)?;
+
+        Ok(Arc::new(Self {
+            base_uri,
+            state: Mutex::new(DiscoveryPagerState {
+                next_offset: Some(query.offset()),
+                query,
+                total: Option::None,
+                pages: 0,
+            }),
+        }))
+    }
+
+    /// The next page of resources, empty once every page was fetched
+    #[uniffi::method]
+    pub async fn next_page(&self) -> NativeResult<Vec<DiscoveryFfi>> {
+        let (mut query, next_offset) = {
+            let state = self.state();
+            (state.query.clone(), state.next_offset)
+        };
+        let Some(offset) = next_offset else {
+            return Ok(Vec::default());
+        };
+        query.set_offset(offset);
+
+        let (page, pagination) = DiscoveryFfi::fetch_page(&query.url(&self.base_uri)).await?;
+
+        let mut state = self.state();
+        state.pages += 1;
+        // Stops on an empty page or after the most pages so a server cannot page forever
+        let below_max_pages = state.pages < X402DiscoveryPagination::MAX_PAGES;
+        state.next_offset = pagination
+            .and_then(|pagination| pagination.next_offset(offset, page.len()))
+            .filter(|_| below_max_pages);
+        state.total = pagination.map(|pagination| pagination.total);
+
+        Ok(page)
+    }
+
+    #[uniffi::method]
+    pub fn has_more(&self) -> bool {
+        self.state().next_offset.is_some()
+    }
+
+    /// The number of matching resources across all pages, known after the first page
+    #[uniffi::method]
+    pub fn total(&self) -> Option<u32> {
+        self.state().total
+    }
+}
+
+impl DiscoveryPagerFfi {
+    fn state(&self) -> std::sync::MutexGuard<'_, DiscoveryPagerState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
 }
 
@@ -107,6 +307,7 @@ pub enum X402UriActionFfi {
     Subscribe,
     Unsubscribe,
     Once,
+    Pay,
 }
 
 impl From<X402UriAction> for X402UriActionFfi {
@@ -116,6 +317,7 @@ impl From<X402UriAction> for X402UriActionFfi {
             X402UriAction::Subscribe => Self::Subscribe,
             X402UriAction::Unsubscribe => Self::Unsubscribe,
             X402UriAction::Once => Self::Once,
+            X402UriAction::Pay => Self::Pay,
         }
     }
 }
@@ -151,6 +353,17 @@ impl From<X402UriScheme> for X402UriSchemeFfi {
     }
 }
 
+impl X402UriSchemeFfi {
+    /// The `type` of a resource in the discovery payload
+    pub fn resource_type(&self) -> &'static str {
+        match self {
+            Self::Https => "http",
+            Self::A2a => "a2a",
+            Self::Mcp => "mcp",
+        }
+    }
+}
+
 impl From<&str> for X402UriSchemeFfi {
     fn from(value: &str) -> Self {
         match value.to_lowercase().as_str() {
@@ -160,3 +373,38 @@ impl From<&str> for X402UriSchemeFfi {
         }
     }
 }
+
+#[cfg(test)]
+mod discovery_sanity {
+    use super::*;
+
+    #[test]
+    fn test_filters() {
+        let (base_uri, mut query) =
+            DiscoveryFfi::parse_uri("https://lagoon.markets/x402/discover?limit=5&pk=Pk&sig=Sig")
+                .unwrap();
+
+        DiscoveryFiltersFfi {
+            uri_scheme: Some(X402UriSchemeFfi::A2a),
+            query: Some("timeline".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut query)
+        .unwrap();
+
+        assert_eq!(
+            query.url(&base_uri),
+            "https://lagoon.markets/x402/discover?pk=Pk&sig=Sig&type=a2a&query=timeline&limit=5"
+        );
+
+        let invalid = DiscoveryFiltersFfi {
+            min_amount: Some(2),
+            max_amount: Some(1),
+            ..Default::default()
+        };
+        assert!(matches!(
+            invalid.apply(&mut query),
+            Err(NativeError::InvalidDiscoveryQuery(_))
+        ));
+    }
+}
@@ -14,7 +14,9 @@ pub async fn rustffi_init_db(app_dir_path: &str) -> Result<(), NativeError> {
     APP_STORAGE.set(store).err();
     // .or(Err(NativeError::UnableToSetGlobalStorageObject))?;
 
-    AppStorage::get_store()?.load_token_list().await
+    let store = AppStorage::get_store()?;
+    store.migrate_x402_keys()?;
+    store.load_token_list().await
 }
 
 #[derive(uniffi::Object)]
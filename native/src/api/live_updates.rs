@@ -1,12 +1,18 @@
 use base64ct::{Base64, Encoding};
 use common::{
-    EventSourceData, EventSourceProgressPoint, EventSourceProgressSegment, EventSourceProgressStyle,
+    EventSourceControl, EventSourceData, EventSourceProgressPoint, EventSourceProgressSegment,
+    EventSourceProgressStyle,
 };
 use serde::{Deserialize, Serialize};
 use sse_client::EventSource;
 use std::sync::{Arc, Mutex};
 
-use crate::{api::utils::log_to_logcat, NativeError, NativeResult};
+use crate::{
+    api::{
+        access_token::AccessTokenFfi, client_proof::url_with_client_proof, utils::log_to_logcat,
+    },
+    NativeError, NativeResult,
+};
 
 #[uniffi::export(with_foreign)]
 pub trait EventListenerFfi: Send + Sync {
@@ -16,6 +22,8 @@ pub trait EventListenerFfi: Send + Sync {
 #[derive(uniffi::Object)]
 pub struct EventEmitterFfi {
     listener: Arc<Mutex<Option<Arc<dyn EventListenerFfi>>>>,
+    /// The top-up URL of a metered stream
+    top_up: Arc<Mutex<Option<String>>>,
 }
 
 #[uniffi::export]
@@ -24,31 +32,64 @@ impl EventEmitterFfi {
     pub fn new() -> Arc<Self> {
         Arc::new(Self {
             listener: Arc::new(Mutex::new(None)),
+            top_up: Arc::new(Mutex::new(None)),
         })
     }
 
+    /// The URL to pay with `X-PAYMENT` to resume a metered stream, see [EventSourceControl]
+    pub fn top_up_uri(&self) -> Option<String> {
+        self.top_up.lock().ok().and_then(|top_up| top_up.clone())
+    }
+
     pub fn set_listener(&self, listener: Arc<dyn EventListenerFfi>) {
         if let Ok(mut value) = self.listener.lock() {
             *value = Some(listener)
         }
     }
 
+    /// Opens the event stream with the access token stored for the resource, if any,
+    /// and the client proof of the signed in user
     pub fn start(&self, eventsource_uri: String) {
         // let listener = self.listener.clone();
 
         let listener = self.listener.clone();
+        let top_up = self.top_up.clone();
         std::thread::spawn(move || {
+            let eventsource_uri = AccessTokenFfi::url_with_token(&eventsource_uri);
+            // A metered stream only opens for a signed in client
+            let eventsource_uri =
+                url_with_client_proof(&eventsource_uri).unwrap_or(eventsource_uri);
             let event_source = match EventSource::new(&eventsource_uri) {
                 Ok(value) => value,
                 Err(_) => return,
             };
 
             for event in event_source.receiver().iter() {
-                if event.id.as_bytes() == "done".as_bytes() {
+                if event.id.as_bytes() == EventSourceData::DONE_ID.as_bytes() {
                     log_to_logcat("DONE");
 
                     event_source.close();
                     break;
+                } else if event.id.as_bytes() == EventSourceData::CONTROL_ID.as_bytes() {
+                    log_to_logcat(&event.data);
+
+                    let Ok(control) = serde_json::from_str::<EventSourceControl>(&event.data)
+                    else {
+                        continue;
+                    };
+
+                    if let EventSourceControl::Metered { top_up: uri, .. } = &control {
+                        if let Ok(mut top_up) = top_up.lock() {
+                            top_up.replace(uri.clone());
+                        }
+                    }
+
+                    if let (Some(notification), Some(listener)) = (
+                        Self::control_notification(&control),
+                        &*listener.lock().unwrap(),
+                    ) {
+                        let _ = listener.on_event(notification.into());
+                    }
                 } else {
                     log_to_logcat(&event.data);
 
@@ -85,6 +126,43 @@ impl EventEmitterFfi {
     }
 }
 
+impl EventEmitterFfi {
+    /// The notification of the control events the user has to act on
+    fn control_notification(control: &EventSourceControl) -> Option<EventSourceData> {
+        let (content_title, content_text, short_critical_text) = match control {
+            EventSourceControl::PaymentRequired { .. } => (
+                "Payment required",
+                "The live updates are paused until the stream is topped up",
+                "paused",
+            ),
+            EventSourceControl::AccessExpired => (
+                "Access expired",
+                "Pay for the resource again to resume the live updates",
+                "expired",
+            ),
+            EventSourceControl::Metered { .. } | EventSourceControl::PaymentAccepted { .. } => {
+                return Option::None
+            }
+        };
+
+        Some(EventSourceData {
+            content_title: content_title.to_string(),
+            content_text: content_text.to_string(),
+            short_critical_text: short_critical_text.to_string(),
+            progress: EventSourceProgressPoint {
+                point: 0,
+                color: "#FFFFA500".to_string(),
+            },
+            is_progress_indeterminate: true,
+            actions: vec![],
+            style: EventSourceProgressStyle {
+                points: vec![],
+                segments: vec![],
+            },
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize, uniffi::Record)]
 pub struct EventSourceDataFfi {
     pub content_title: String,
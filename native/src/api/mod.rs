@@ -1,11 +1,17 @@
+mod access_token;
+mod client_proof;
 mod construct_tx;
 mod discovery;
 mod init;
 mod live_updates;
+mod nfc;
 mod optimize_tx;
+mod qr_scan;
 mod siws;
 mod user_profile;
 mod utils;
 mod x402;
+mod x402_uri;
 
 pub(crate) use discovery::*;
+pub(crate) use x402_uri::*;
@@ -0,0 +1,53 @@
+use x402_uri::{X402NdefMessage, X402Uri, X402UriBuf};
+
+use crate::{api::X402UriFfi, NativeError, NativeResult};
+
+/// Parses the raw payload of a NFC tag, either the bytes of a NDEF message
+/// or the memory of a NFC Forum Type 2 tag
+#[uniffi::export]
+pub fn rustffi_parse_nfc_payload(payload: Vec<u8>) -> NativeResult<X402UriFfi> {
+    let message = X402NdefMessage::decode_tag(&payload)
+        .map_err(|error| NativeError::InvalidNfcPayload(error.to_string()))?;
+
+    let displayed = message.x402_uri().to_string();
+    let parsed =
+        X402Uri::new(&displayed).map_err(|error| NativeError::InvalidX402Uri(error.to_string()))?;
+
+    (&parsed).try_into()
+}
+
+/// Encodes a x402-URI as a NDEF message to write to a NFC tag. If `launch_explorer` is true
+/// an Android Application Record opens Lagoon Explorer when the tag is tapped.
+#[uniffi::export]
+pub fn rustffi_encode_nfc_x402_uri(
+    x402_uri: String,
+    launch_explorer: bool,
+) -> NativeResult<Vec<u8>> {
+    let x402_uri = X402UriBuf::new(&x402_uri)
+        .map_err(|error| NativeError::InvalidX402Uri(error.to_string()))?;
+
+    let mut message = X402NdefMessage::new(x402_uri);
+    if launch_explorer {
+        message.set_explorer_package();
+    }
+
+    Ok(message.encode())
+}
+
+#[cfg(test)]
+mod x402_nfc_sanity {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let x402_uri = "x402://discover/https://lagoon.markets/x402/discover";
+        let payload = rustffi_encode_nfc_x402_uri(x402_uri.to_string(), true).unwrap();
+        let parsed = rustffi_parse_nfc_payload(payload).unwrap();
+
+        assert_eq!(parsed.uri, "https://lagoon.markets/x402/discover");
+        assert!(matches!(
+            rustffi_parse_nfc_payload(vec![0xD1, 0x01]),
+            Err(NativeError::InvalidNfcPayload(_))
+        ));
+    }
+}
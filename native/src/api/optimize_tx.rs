@@ -1,12 +1,16 @@
 use std::str::FromStr;
 
 use base64ct::{Base64, Encoding};
-use common::{SanctumBuilderResponse, SanctumRpcResponse, TxBase64Encoded};
+use common::{
+    JitoTipRange, SanctumRpcResponse, TipInstructionsRequest, TipResponse, TxBase64Encoded,
+};
+use solana_instruction::Instruction;
 use solana_pubkey::Pubkey;
 
 use crate::{
     api::{
-        construct_tx::{transfer_sol, transfer_token},
+        client_proof::rustffi_x402_client_headers,
+        construct_tx::{transfer_sol, transfer_token, SolanaChainFfi},
         discovery::DiscoveryFfi,
         utils::log_to_logcat,
     },
@@ -16,7 +20,8 @@ use crate::{
 #[uniffi::export]
 pub async fn rustffi_optimize_transaction(
     x402_resource: DiscoveryFfi,
-    mainnet: bool,
+    chain: SolanaChainFfi,
+    tip: Option<JitoTipRangeFfi>,
 ) -> NativeResult<Vec<u8>> {
     let asset_pubkey =
         Pubkey::from_str(&x402_resource.asset).or(Err(NativeError::InvalidBase58String))?;
@@ -37,8 +42,20 @@ pub async fn rustffi_optimize_transaction(
         .parse::<u64>()
         .or(Err(NativeError::AmountNotU64))?;
 
+    let tip_instructions = if let Some(tip) = tip {
+        get_tip_instructions(fee_payer, tip.into()).await?
+    } else {
+        Vec::default()
+    };
+
     let encoded_tx = if asset_pubkey == solana_system_interface::program::ID {
-        Base64::encode_string(&transfer_sol(user_pubkey, fee_payer, pay_to, amount)?)
+        Base64::encode_string(&transfer_sol(
+            user_pubkey,
+            fee_payer,
+            pay_to,
+            amount,
+            &tip_instructions,
+        )?)
     } else {
         Base64::encode_string(
             &transfer_token(
@@ -47,7 +64,8 @@ pub async fn rustffi_optimize_transaction(
                 pay_to,
                 amount,
                 asset_pubkey,
-                mainnet,
+                chain.into(),
+                &tip_instructions,
             )
             .await?,
         )
@@ -64,6 +82,13 @@ pub async fn rustffi_optimize_transaction(
     })
     .await
     .map_err(|error| NativeError::Https(error.to_string()))?;
+
+    if optimized_tx_response.status_code >= 400 {
+        return Err(NativeError::from_response(
+            optimized_tx_response.status_code,
+            optimized_tx_response.as_str().unwrap_or_default(),
+        ));
+    }
     let optimized_tx_decoded =
         serde_json::from_str::<TxBase64Encoded>(optimized_tx_response.as_str().or(Err(
             NativeError::Https("The response from `optimize-tx` route was not JSON".to_string()),
@@ -75,6 +100,80 @@ pub async fn rustffi_optimize_transaction(
     Base64::decode_vec(&optimized_tx_decoded.data).or(Err(NativeError::UnableToDecodeOptimizedTx))
 }
 
+const TIP_INSTRUCTIONS_URL: &str = "https://lagoon.markets/x402/tip-instructions";
+
+/// Fetches the Sanctum tip instructions paid by `fee_payer` to land the transaction faster
+async fn get_tip_instructions(
+    fee_payer: Pubkey,
+    jito_tip_range: JitoTipRange,
+) -> NativeResult<Vec<Instruction>> {
+    let mut request = TipInstructionsRequest::new(&fee_payer.to_string());
+    request.set_jito_tip_range(jito_tip_range);
+    let request_body =
+        serde_json::to_string(&request).map_err(|error| NativeError::Https(error.to_string()))?;
+
+    // The route only serves clients authenticated with a client proof
+    let client_headers =
+        rustffi_x402_client_headers("POST".to_string(), TIP_INSTRUCTIONS_URL.to_string())?;
+
+    let tip_response = blocking::unblock(move || {
+        minreq::post(TIP_INSTRUCTIONS_URL)
+            .with_header("Content-Type", "application/json")
+            .with_headers(
+                client_headers
+                    .into_iter()
+                    .map(|header| (header.name, header.value)),
+            )
+            .with_body(request_body)
+            .send()
+    })
+    .await
+    .map_err(|error| NativeError::Https(error.to_string()))?;
+
+    if tip_response.status_code >= 400 {
+        return Err(NativeError::from_response(
+            tip_response.status_code,
+            tip_response.as_str().unwrap_or_default(),
+        ));
+    }
+
+    serde_json::from_str::<Vec<TipResponse>>(
+        tip_response
+            .as_str()
+            .map_err(|error| NativeError::Https(error.to_string()))?,
+    )
+    .or(Err(NativeError::Https(
+        "Unable to deserialize the tip instructions response".to_string(),
+    )))?
+    .iter()
+    .map(|tip| {
+        tip.to_instruction()
+            .map_err(|error| NativeError::InvalidTipInstruction(error.to_string()))
+    })
+    .collect()
+}
+
+/// How much to tip for faster landing of the transaction
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Default, uniffi::Enum)]
+pub enum JitoTipRangeFfi {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Max,
+}
+
+impl From<JitoTipRangeFfi> for JitoTipRange {
+    fn from(value: JitoTipRangeFfi) -> Self {
+        match value {
+            JitoTipRangeFfi::Low => Self::Low,
+            JitoTipRangeFfi::Medium => Self::Medium,
+            JitoTipRangeFfi::High => Self::High,
+            JitoTipRangeFfi::Max => Self::Max,
+        }
+    }
+}
+
 #[uniffi::export]
 pub async fn rustffi_send_optimized_transaction(tx: Vec<u8>) -> NativeResult<String> {
     let encoded_tx = serde_json::to_string(&TxBase64Encoded {
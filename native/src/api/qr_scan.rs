@@ -0,0 +1,145 @@
+use crate::{api::ScannedCodeFfi, NativeError, NativeResult};
+
+/// Scans a grayscale camera frame, like the `Y` plane of a `YUV_420_888` image, for a x402-URI
+/// or Solana Pay QR code.
+/// `row_stride` is the number of bytes between the start of two rows which can be larger than `width`.
+/// Returns `None` if the frame has no QR code.
+#[uniffi::export]
+pub fn rustffi_scan_x402_qr(
+    frame: Vec<u8>,
+    width: u32,
+    height: u32,
+    row_stride: u32,
+) -> NativeResult<Option<ScannedCodeFfi>> {
+    X402QrScanner::scan(&frame, width as usize, height as usize, row_stride as usize)
+}
+
+pub struct X402QrScanner;
+
+impl X402QrScanner {
+    pub fn scan(
+        frame: &[u8],
+        width: usize,
+        height: usize,
+        row_stride: usize,
+    ) -> NativeResult<Option<ScannedCodeFfi>> {
+        if width == 0 || height == 0 || row_stride < width {
+            return Err(NativeError::InvalidFrameDimensions);
+        }
+
+        if frame.len() < row_stride * (height - 1) + width {
+            return Err(NativeError::FrameBufferTooSmall);
+        }
+
+        let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| {
+            frame[y * row_stride + x]
+        });
+
+        let mut outcome = Ok(Option::None);
+
+        for grid in prepared.detect_grids() {
+            // Damaged or partially visible codes are common in camera frames, skip them
+            let Ok((_, content)) = grid.decode() else {
+                continue;
+            };
+
+            match ScannedCodeFfi::parse(content.trim()) {
+                Ok(scanned) => return Ok(Some(scanned)),
+                Err(error) => outcome = Err(error),
+            }
+        }
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod x402_qr_scan_sanity {
+    use x402_uri::{X402QrOptions, X402UriBuf};
+
+    use super::*;
+    use crate::api::{X402UriActionFfi, X402UriSchemeFfi};
+
+    /// Renders a x402-URI QR code fixture and returns it as a grayscale frame
+    /// padded to `row_stride` like camera frames are
+    fn grayscale_fixture(x402_uri: &str, row_stride_padding: usize) -> (Vec<u8>, usize, usize) {
+        let png_bytes = X402UriBuf::new(x402_uri)
+            .unwrap()
+            .to_qr_code(X402QrOptions::new().set_module_size(4))
+            .unwrap()
+            .to_png()
+            .unwrap();
+
+        let mut reader = png::Decoder::new(png_bytes.as_slice()).read_info().unwrap();
+        let mut rgb = vec![0u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut rgb).unwrap();
+        let (width, height) = (info.width as usize, info.height as usize);
+        let row_stride = width + row_stride_padding;
+
+        let mut frame = vec![0u8; row_stride * height];
+        (0..height).for_each(|y| {
+            (0..width).for_each(|x| frame[y * row_stride + x] = rgb[(y * width + x) * 3])
+        });
+
+        (frame, width, row_stride)
+    }
+
+    #[test]
+    fn test_scan_fixture() {
+        let x402_uri = "x402://discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover";
+        let (frame, width, row_stride) = grayscale_fixture(x402_uri, 32);
+        let height = frame.len() / row_stride;
+
+        let Some(ScannedCodeFfi::X402Uri { x402_uri: scanned }) =
+            X402QrScanner::scan(&frame, width, height, row_stride).unwrap()
+        else {
+            panic!("Expected a x402-URI");
+        };
+
+        assert_eq!(scanned.action, X402UriActionFfi::Discover);
+        assert_eq!(scanned.uri_scheme, X402UriSchemeFfi::Https);
+        assert_eq!(scanned.uri, "https://lagoon.markets/x402/discover");
+        assert_eq!(scanned.x402_uri, x402_uri);
+    }
+
+    #[test]
+    fn test_solana_pay_codes() {
+        let transaction =
+            ScannedCodeFfi::parse("solana:https%3A%2F%2Fexample.com%2Fsolana-pay%3Forder%3D1")
+                .unwrap();
+        let ScannedCodeFfi::X402Uri { x402_uri } = transaction else {
+            panic!("Expected a x402-URI");
+        };
+        assert_eq!(x402_uri.action, X402UriActionFfi::Once);
+        assert_eq!(x402_uri.uri, "https://example.com/solana-pay?order=1");
+
+        let solana_uri =
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael";
+        let ScannedCodeFfi::SolanaPayTransfer { pay_request, .. } =
+            ScannedCodeFfi::parse(solana_uri).unwrap()
+        else {
+            panic!("Expected a Solana Pay transfer request");
+        };
+        assert_eq!(pay_request.amount.as_deref(), Some("1"));
+        assert_eq!(pay_request.label.as_deref(), Some("Michael"));
+
+        assert!(matches!(
+            ScannedCodeFfi::parse("solana:?amount=1"),
+            Err(NativeError::InvalidSolanaPayUri(_))
+        ));
+    }
+
+    #[test]
+    fn test_scan_empty_and_invalid_frames() {
+        let blank = vec![255u8; 64 * 64];
+        assert_eq!(X402QrScanner::scan(&blank, 64, 64, 64), Ok(None));
+        assert_eq!(
+            X402QrScanner::scan(&blank, 64, 65, 64),
+            Err(NativeError::FrameBufferTooSmall)
+        );
+        assert_eq!(
+            X402QrScanner::scan(&blank, 64, 64, 32),
+            Err(NativeError::InvalidFrameDimensions)
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use x402_uri::{X402PayRequest, X402SolanaPay, X402Uri, X402UriAction, X402UriSignature};
+
+use crate::{
+    api::{X402UriActionFfi, X402UriSchemeFfi},
+    NativeError, NativeResult,
+};
+
+/// Parses a x402-URI, for example one opened from a deep link
+#[uniffi::export]
+pub fn rustffi_parse_x402_uri(x402_uri: String) -> NativeResult<X402UriFfi> {
+    let parsed =
+        X402Uri::new(&x402_uri).map_err(|error| NativeError::InvalidX402Uri(error.to_string()))?;
+
+    (&parsed).try_into()
+}
+
+#[derive(Debug, uniffi::Record, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct X402UriFfi {
+    pub action: X402UriActionFfi,
+    pub uri_scheme: X402UriSchemeFfi,
+    /// The decoded resource URI after `x402://<action>/`
+    pub uri: String,
+    /// The x402-URI as it was parsed
+    pub x402_uri: String,
+    /// The payment parameters of a `x402://pay/` URI
+    pub pay_request: Option<X402PayRequestFfi>,
+    /// Whether the publisher signed the x402-URI
+    pub signature: X402UriSignatureFfi,
+}
+
+impl TryFrom<&X402Uri<'_>> for X402UriFfi {
+    type Error = NativeError;
+
+    fn try_from(value: &X402Uri<'_>) -> Result<Self, Self::Error> {
+        let pay_request = if value.action() == X402UriAction::Pay {
+            let pay_request = value
+                .pay_request()
+                .map_err(|error| NativeError::InvalidX402Uri(error.to_string()))?;
+
+            Some(pay_request.into())
+        } else {
+            Option::None
+        };
+
+        Ok(Self {
+            action: value.action().into(),
+            uri_scheme: value.uri_scheme().into(),
+            uri: value.uri().to_string(),
+            x402_uri: value.raw().to_string(),
+            pay_request,
+            signature: X402UriSignatureFfi::verify(value)?,
+        })
+    }
+}
+
+#[derive(Debug, uniffi::Record, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct X402PayRequestFfi {
+    pub recipient: String,
+    /// The decimal amount in user units like `1.5`
+    pub amount: Option<String>,
+    /// The mint address of the token, `None` for SOL
+    pub asset: Option<String>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    pub memo: Option<String>,
+}
+
+impl From<X402PayRequest> for X402PayRequestFfi {
+    fn from(value: X402PayRequest) -> Self {
+        Self {
+            recipient: value.recipient,
+            amount: value.amount.map(|amount| amount.to_string()),
+            asset: value.asset,
+            label: value.label,
+            message: value.message,
+            memo: value.memo,
+        }
+    }
+}
+
+/// Lets the UI show "verified by publisher key X" or warn that a x402-URI is unsigned
+#[derive(Debug, uniffi::Enum, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub enum X402UriSignatureFfi {
+    Unsigned,
+    Verified { public_key: String },
+}
+
+impl X402UriSignatureFfi {
+    /// Fails with [NativeError::InvalidX402UriSignature] if the x402-URI was tampered with
+    pub fn verify(x402_uri: &X402Uri<'_>) -> NativeResult<Self> {
+        x402_uri
+            .verify_signature()
+            .map(Self::from)
+            .map_err(|error| NativeError::InvalidX402UriSignature(error.to_string()))
+    }
+}
+
+impl From<X402UriSignature> for X402UriSignatureFfi {
+    fn from(value: X402UriSignature) -> Self {
+        match value {
+            X402UriSignature::Unsigned => Self::Unsigned,
+            X402UriSignature::Verified { public_key } => Self::Verified { public_key },
+        }
+    }
+}
+
+/// A code from the scanner, either a x402-URI or a Solana Pay URI.
+/// Solana Pay transaction requests are converted into `x402://once/` URIs.
+#[derive(Debug, uniffi::Enum, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub enum ScannedCodeFfi {
+    X402Uri {
+        x402_uri: X402UriFfi,
+    },
+    SolanaPayTransfer {
+        pay_request: X402PayRequestFfi,
+        /// The base58 `reference` addresses to add to the transfer
+        references: Vec<String>,
+        solana_uri: String,
+    },
+}
+
+impl ScannedCodeFfi {
+    pub fn parse(content: &str) -> NativeResult<Self> {
+        let Some(solana_pay) = content
+            .get(..X402SolanaPay::SCHEME.len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case(X402SolanaPay::SCHEME))
+            .map(|_| X402SolanaPay::parse(content))
+        else {
+            let parsed = X402Uri::new(content)
+                .map_err(|error| NativeError::InvalidX402Uri(error.to_string()))?;
+
+            return Ok(Self::X402Uri {
+                x402_uri: (&parsed).try_into()?,
+            });
+        };
+
+        match solana_pay.map_err(|error| NativeError::InvalidSolanaPayUri(error.to_string()))? {
+            X402SolanaPay::Transaction(x402_uri) => {
+                let displayed = x402_uri.to_string();
+                let parsed = X402Uri::new(&displayed)
+                    .map_err(|error| NativeError::InvalidX402Uri(error.to_string()))?;
+
+                Ok(Self::X402Uri {
+                    x402_uri: (&parsed).try_into()?,
+                })
+            }
+            X402SolanaPay::Transfer(transfer) => Ok(Self::SolanaPayTransfer {
+                pay_request: transfer.pay_request.into(),
+                references: transfer.references,
+                solana_uri: content.to_string(),
+            }),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use common::{X402ErrorCode, X402ErrorResponse};
+
 pub type NativeResult<T> = Result<T, NativeError>;
 
 #[derive(thiserror::Error, uniffi::Error, Debug, PartialEq, Eq)]
@@ -28,14 +30,50 @@ pub enum NativeError {
     SerializeSiwsAuthResultToBytes,
     #[error("Unable to deserialize `SiwsAuthResult` from bytes")]
     DeserializeSiwsAuthResultToBytes,
+    #[error("The stored session key is corrupted")]
+    CorruptedSessionKey,
+    #[error("Invalid x402 access token. Error: `{0}`.")]
+    InvalidAccessToken(String),
     #[error("X402Uri error: `{0}`")]
     X402Uri(String),
     #[error("The scheme for x402://.../<scheme>:// is not supported")]
     UnsupportedX402Scheme,
     #[error("Invalid X402Uri. Error: `{0}`.")]
     InvalidX402Uri(String),
+    #[error("The publisher signature of the X402Uri is invalid, it may have been tampered with. Error: `{0}`.")]
+    InvalidX402UriSignature(String),
+    #[error("Invalid discovery filters. Error: `{0}`.")]
+    InvalidDiscoveryQuery(String),
+    #[error("Invalid Solana Pay URI. Error: `{0}`.")]
+    InvalidSolanaPayUri(String),
+    #[error("The NFC tag does not contain a valid x402Uri. Error: `{0}`.")]
+    InvalidNfcPayload(String),
+    #[error("Invalid tip instruction. Error: `{0}`.")]
+    InvalidTipInstruction(String),
     #[error("Encountered HTTPS error: `{0}`")]
     Https(String),
+    #[error("The x402 resource requires the `{0}` header")]
+    X402MissingHeader(String),
+    #[error("The x402 resource rejected the request. Error: `{0}`")]
+    X402InvalidRequest(String),
+    #[error("The x402 resource did not accept the signed client address. Error: `{0}`")]
+    X402InvalidClientProof(String),
+    #[error("The x402 resource does not support the network. Error: `{0}`")]
+    X402UnsupportedNetwork(String),
+    #[error("The x402 payment payload is invalid. Error: `{0}`")]
+    X402InvalidPayment(String),
+    #[error("The x402 resource rejected the payment. Error: `{0}`")]
+    X402PaymentRejected(String),
+    #[error("The x402 payment has expired. Error: `{0}`")]
+    X402PaymentExpired(String),
+    #[error("The x402 payment was already used. Error: `{0}`")]
+    X402PaymentAlreadyUsed(String),
+    #[error("The facilitator of the x402 resource is unavailable. Error: `{0}`")]
+    X402FacilitatorUnavailable(String),
+    #[error("Settling the x402 payment failed. Error: `{0}`")]
+    X402SettlementFailed(String),
+    #[error("The x402 resource server failed with status `{status}`. Error: `{error}`")]
+    X402ServerError { status: u16, error: String },
     #[error("The x402 resource needs at least one payment method in `accepts` field")]
     AtLeastOneAcceptsItemIsNeeded,
     #[error("Unable to deserialize `solana.tokenlist.json` ")]
@@ -64,6 +102,48 @@ pub enum NativeError {
     SerializeX402DataToBytes,
     #[error("Unable to deserialize a X402Resource from DB")]
     DeserializeX402Resource,
+    #[error("The camera frame width and height must be greater than zero and the row stride at least the width")]
+    InvalidFrameDimensions,
+    #[error("The camera frame buffer is smaller than its row stride and height require")]
+    FrameBufferTooSmall,
+}
+
+impl NativeError {
+    /// Maps a failed HTTP response to a specific error if the body is a [X402ErrorResponse]
+    pub fn from_response(status_code: i32, body: &str) -> Self {
+        let status = u16::try_from(status_code).unwrap_or_default();
+
+        X402ErrorResponse::parse(status, body)
+            .map(Self::from)
+            .unwrap_or_else(|| {
+                Self::Https(
+                    String::from("Status ") + status_code.to_string().as_str() + ": " + body,
+                )
+            })
+    }
+}
+
+impl From<X402ErrorResponse> for NativeError {
+    fn from(value: X402ErrorResponse) -> Self {
+        match value.code {
+            X402ErrorCode::MissingHeader => {
+                Self::X402MissingHeader(value.header.unwrap_or(value.error))
+            }
+            X402ErrorCode::InvalidRequest => Self::X402InvalidRequest(value.error),
+            X402ErrorCode::InvalidClientProof => Self::X402InvalidClientProof(value.error),
+            X402ErrorCode::UnsupportedNetwork => Self::X402UnsupportedNetwork(value.error),
+            X402ErrorCode::InvalidPayment => Self::X402InvalidPayment(value.error),
+            X402ErrorCode::PaymentRejected => Self::X402PaymentRejected(value.error),
+            X402ErrorCode::PaymentExpired => Self::X402PaymentExpired(value.error),
+            X402ErrorCode::PaymentAlreadyUsed => Self::X402PaymentAlreadyUsed(value.error),
+            X402ErrorCode::FacilitatorUnavailable => Self::X402FacilitatorUnavailable(value.error),
+            X402ErrorCode::SettlementFailed => Self::X402SettlementFailed(value.error),
+            X402ErrorCode::InternalError | X402ErrorCode::Unknown => Self::X402ServerError {
+                status: value.status,
+                error: value.error,
+            },
+        }
+    }
 }
 
 impl From<redb::Error> for NativeError {
@@ -71,3 +151,38 @@ impl From<redb::Error> for NativeError {
         Self::StorageError(error.to_string())
     }
 }
+
+#[cfg(test)]
+mod native_error_sanity {
+    use common::CommonHeaders;
+
+    use super::*;
+
+    #[test]
+    fn test_from_response() {
+        let missing_header = X402ErrorResponse::missing_header(
+            "https://lagoon.markets/latest_newsletter",
+            CommonHeaders::X402_ADDRESS_HEADER,
+            "a base58 encoded Ed25519 public address",
+        );
+        assert_eq!(
+            NativeError::from_response(400, &missing_header.to_json()),
+            NativeError::X402MissingHeader(CommonHeaders::X402_ADDRESS_HEADER.to_string())
+        );
+
+        let replay = X402ErrorResponse::new(
+            X402ErrorCode::PaymentAlreadyUsed,
+            "https://lagoon.markets/latest_newsletter",
+            "Replayed payment",
+        );
+        assert_eq!(
+            NativeError::from_response(409, &replay.to_json()),
+            NativeError::X402PaymentAlreadyUsed("Replayed payment".to_string())
+        );
+
+        assert_eq!(
+            NativeError::from_response(502, "Bad Gateway"),
+            NativeError::Https("Status 502: Bad Gateway".to_string())
+        );
+    }
+}
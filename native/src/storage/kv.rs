@@ -1,12 +1,16 @@
 use std::borrow::Borrow;
 
 use camino::Utf8PathBuf;
+use common::X402AccessToken;
 use redb::{
     Database, Error as RedbError, Key, ReadableDatabase, ReadableTable, TableDefinition, Value,
 };
 use serde::Deserialize;
+use x402_uri::X402UriCanonical;
 
-use crate::{NativeError, NativeResult, SiwsAuthResult, X402Data, X402Resource, APP_STORAGE};
+use crate::{
+    Byte32Array, NativeError, NativeResult, SiwsAuthResult, X402Data, X402Resource, APP_STORAGE,
+};
 
 pub type RedbResult<T> = Result<T, RedbError>;
 
@@ -20,6 +24,8 @@ type AuthSchema = TableDefinition<'static, &'static str, Vec<u8>>;
 type X402Schema = TableDefinition<'static, [u8; 32], Vec<u8>>;
 type TasksSchema = TableDefinition<'static, &'static str, String>;
 type SubscriptionsSchema = TableDefinition<'static, &'static str, String>;
+type MetaSchema = TableDefinition<'static, &'static str, u32>;
+type AccessTokensSchema = TableDefinition<'static, [u8; 32], String>;
 
 impl AppStorage {
     pub const APP_DIR_PATH: &str = "lagoon_markets.redb";
@@ -69,6 +75,21 @@ impl AppStorage {
         Ok(table.get(key)?)
     }
 
+    pub fn remove<'a, K: Key, V: Value>(
+        &self,
+        table: TableDefinition<'_, K, V>,
+        key: impl Borrow<K::SelfType<'a>>,
+    ) -> Result<(), RedbError> {
+        let write_txn = self.store.begin_write()?;
+        {
+            let mut table = write_txn.open_table(table)?;
+            table.remove(key)?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
     fn table_data_exists<'a, K: Key, V: Value>(
         &self,
         table: TableDefinition<'_, K, V>,
@@ -107,6 +128,7 @@ impl AppStorage {
 
 impl AppStorage {
     const AUTH_KEY: &str = "auth";
+    const SESSION_KEY: &str = "session";
     const AUTH_TABLE: AuthSchema = AuthSchema::new("user_auth");
 
     pub fn set_auth(&self, auth: SiwsAuthResult) -> NativeResult<()> {
@@ -130,6 +152,27 @@ impl AppStorage {
                 .transpose(),
         }
     }
+
+    /// The secret of the session key that signs `X402-Client-Proof` headers,
+    /// generated on first use
+    pub fn get_or_create_session_secret(&self) -> NativeResult<Byte32Array> {
+        let existing = match self.get(Self::AUTH_TABLE, Self::SESSION_KEY) {
+            Err(redb::Error::TableDoesNotExist(_)) => Option::None,
+            Err(error) => return Err(error.into()),
+            Ok(secret) => secret.map(|secret_inner| secret_inner.value()),
+        };
+
+        if let Some(secret) = existing {
+            return Byte32Array::try_from(secret.as_slice())
+                .or(Err(NativeError::CorruptedSessionKey));
+        }
+
+        let mut secret = Byte32Array::default();
+        secret.copy_from_slice(&solana_keypair::Keypair::new().to_bytes()[..32]);
+        self.set(Self::AUTH_TABLE, Self::SESSION_KEY, secret.to_vec())?;
+
+        Ok(secret)
+    }
 }
 
 impl AppStorage {
@@ -174,8 +217,71 @@ impl AppStorage {
 impl AppStorage {
     const X402_TABLE: X402Schema = X402Schema::new("x402_data");
 
+    const META_TABLE: MetaSchema = MetaSchema::new("meta");
+    const X402_KEYS_VERSION_KEY: &str = "x402_keys_version";
+    /// Version 1 keys x402 resources by the hash of their canonical resource URI
+    const X402_KEYS_VERSION: u32 = 1;
+
+    /// Resources are keyed by their canonical URI so that `https://Example.com/a`
+    /// and `https://example.com/a/` are the same resource. A URI that can not be
+    /// canonicalized is keyed by its raw URI like before the migration.
+    pub fn x402_key(uri: &str) -> [u8; 32] {
+        let canonical = X402UriCanonical::resource_uri(uri);
+        let key = canonical.as_deref().unwrap_or(uri);
+
+        *blake3::hash(key.as_bytes()).as_bytes()
+    }
+
+    /// Rekeys the x402 resources stored by the hash of their raw URI before
+    /// resources were keyed by their canonical URI. Rows whose URI can not be
+    /// canonicalized keep their key.
+    pub fn migrate_x402_keys(&self) -> NativeResult<()> {
+        let version = match self.get(Self::META_TABLE, Self::X402_KEYS_VERSION_KEY) {
+            Err(redb::Error::TableDoesNotExist(_)) => None,
+            Err(error) => return Err(error.into()),
+            Ok(version) => version.map(|inner| inner.value()),
+        };
+
+        if version.unwrap_or_default() >= Self::X402_KEYS_VERSION {
+            return Ok(());
+        }
+
+        Ok(self.rekey_x402_table()?)
+    }
+
+    fn rekey_x402_table(&self) -> RedbResult<()> {
+        let write_txn = self.store.begin_write()?;
+        {
+            let mut table = write_txn.open_table(Self::X402_TABLE)?;
+
+            let rows = table
+                .iter()?
+                .map(|entry| entry.map(|(key, value)| (key.value(), value.value())))
+                .collect::<Result<Vec<([u8; 32], Vec<u8>)>, _>>()?;
+
+            rows.into_iter().try_for_each(|(key, value)| {
+                let new_key = wincode::deserialize::<X402Data>(&value)
+                    .ok()
+                    .map(|data| Self::x402_key(&data.uri));
+
+                if let Some(new_key) = new_key.filter(|new_key| *new_key != key) {
+                    table.remove(key)?;
+                    table.insert(new_key, value)?;
+                }
+
+                Ok::<_, RedbError>(())
+            })?;
+
+            let mut meta = write_txn.open_table(Self::META_TABLE)?;
+            meta.insert(Self::X402_KEYS_VERSION_KEY, Self::X402_KEYS_VERSION)?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
     pub fn set_x402(&self, data: X402Data) -> NativeResult<()> {
-        let key = *blake3::hash(data.uri.as_bytes()).as_bytes();
+        let key = Self::x402_key(&data.uri);
         let data_bytes =
             wincode::serialize(&data).or(Err(NativeError::SerializeX402DataToBytes))?;
 
@@ -183,7 +289,7 @@ impl AppStorage {
     }
 
     pub fn get_x402(&self, key: &str) -> NativeResult<Option<X402Resource>> {
-        let key = *blake3::hash(key.as_bytes()).as_bytes();
+        let key = Self::x402_key(key);
 
         self.get(Self::X402_TABLE, key)
             .map(|data| {
@@ -242,6 +348,59 @@ impl AppStorage {
     }
 }
 
+impl AppStorage {
+    const ACCESS_TOKENS_TABLE: AccessTokensSchema = AccessTokensSchema::new("access_tokens");
+
+    /// Access tokens are keyed like x402 resources, without the query and fragment
+    /// of the URI since they grant access to every request of the resource
+    fn access_token_key(uri: &str) -> [u8; 32] {
+        let without_fragment = uri.split_once('#').map_or(uri, |(rest, _)| rest);
+        let resource = without_fragment
+            .split_once('?')
+            .map_or(without_fragment, |(rest, _)| rest);
+
+        Self::x402_key(resource)
+    }
+
+    /// Stores the access token of the resource it was issued for, replacing the previous one
+    pub fn set_access_token(&self, token: &str) -> NativeResult<X402AccessToken> {
+        let access_token = X402AccessToken::decode(token)
+            .map_err(|error| NativeError::InvalidAccessToken(error.to_string()))?;
+
+        self.set(
+            Self::ACCESS_TOKENS_TABLE,
+            Self::access_token_key(&access_token.resource),
+            token.trim().to_string(),
+        )?;
+
+        Ok(access_token)
+    }
+
+    /// The unexpired access token of the resource at `uri`. An expired token is removed.
+    pub fn get_access_token(&self, uri: &str, now: u64) -> NativeResult<Option<String>> {
+        let key = Self::access_token_key(uri);
+
+        let token = match self.get(Self::ACCESS_TOKENS_TABLE, key) {
+            Err(redb::Error::TableDoesNotExist(_)) => return Ok(Option::None),
+            Err(error) => return Err(error.into()),
+            Ok(token) => token.map(|inner| inner.value()),
+        };
+
+        let Some(token) = token else {
+            return Ok(Option::None);
+        };
+
+        match X402AccessToken::decode(&token) {
+            Ok(access_token) if !access_token.is_expired(now) => Ok(Some(token)),
+            _ => {
+                self.remove(Self::ACCESS_TOKENS_TABLE, key)?;
+
+                Ok(Option::None)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct SolanaTokenListMetadata {
     pub tokens: Vec<TokenInfo>,
@@ -268,3 +427,65 @@ pub struct TokenInfo {
     pub decimals: u8,
     pub logoURI: String,
 }
+
+#[cfg(test)]
+mod app_storage_sanity {
+    use super::*;
+    use crate::api::{DiscoveryFfi, X402UriSchemeFfi};
+
+    fn resource(uri: &str) -> X402Resource {
+        let data = X402Data {
+            uri: uri.to_string(),
+            tx: None,
+            resource_data: DiscoveryFfi {
+                uri_scheme: X402UriSchemeFfi::Https,
+                uri: uri.to_string(),
+                title: None,
+                description: None,
+                header_image: None,
+                amount: "1000".to_string(),
+                asset: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
+                pay_to: "2wKupLR9q6wXYppw8Gr2NvWxKBUqm4PPJKkQfoxHDBg4".to_string(),
+                maxtimeout_seconds: "60".to_string(),
+                fee_payer: "2wKupLR9q6wXYppw8Gr2NvWxKBUqm4PPJKkQfoxHDBg4".to_string(),
+                asset_info: None,
+            },
+        };
+
+        X402Resource {
+            id: AppStorage::x402_key(uri),
+            data,
+            resource_data: Vec::default(),
+        }
+    }
+
+    #[test]
+    fn test_non_canonical_key_migration() {
+        let mut path = Utf8PathBuf::from_path_buf(std::env::temp_dir()).unwrap();
+        path.push(format!("x402_keys_{}.redb", std::process::id()));
+        let storage = AppStorage {
+            store: Database::create(&path).unwrap(),
+            path: path.clone(),
+        };
+
+        let uri = "not a resource uri";
+        assert!(X402UriCanonical::resource_uri(uri).is_err());
+
+        let stored = resource(uri);
+        let raw_key = *blake3::hash(uri.as_bytes()).as_bytes();
+        storage
+            .set(
+                AppStorage::X402_TABLE,
+                raw_key,
+                wincode::serialize(&stored).unwrap(),
+            )
+            .unwrap();
+
+        storage.migrate_x402_keys().unwrap();
+        assert_eq!(AppStorage::x402_key(uri), raw_key);
+        assert_eq!(storage.get_x402(uri).unwrap(), Some(stored));
+
+        drop(storage);
+        std::fs::remove_file(path).unwrap();
+    }
+}
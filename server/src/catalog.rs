@@ -0,0 +1,622 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use common::SolanaChain;
+use rocket::fairing::AdHoc;
+use rocket::Route;
+use serde::Deserialize;
+use solana_pubkey::Pubkey;
+
+use crate::SERVER_CONFIG;
+
+static RESOURCE_CATALOG: once_cell::sync::Lazy<RwLock<Arc<ResourceCatalog>>> =
+    once_cell::sync::Lazy::new(|| {
+        let catalog = ResourceCatalog::load(&SERVER_CONFIG.catalog_path())
+            .unwrap_or_else(|error| panic!("Invalid resource catalog. Error: {error}"));
+
+        RwLock::new(Arc::new(catalog))
+    });
+
+/// The modification times of the catalog files when they were last loaded
+static CATALOG_FINGERPRINT: Mutex<Option<Vec<(PathBuf, SystemTime)>>> = Mutex::new(None);
+
+/// The paid resources of the server loaded from a TOML or JSON file, or a directory of them
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ResourceCatalog {
+    resources: Vec<CatalogResource>,
+    /// Unix timestamp in seconds
+    loaded_at: u64,
+}
+
+impl ResourceCatalog {
+    /// How often the catalog files are checked for changes
+    pub const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+    /// The catalog that is currently served
+    pub fn current() -> Arc<Self> {
+        RESOURCE_CATALOG
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CatalogError> {
+        let resources = Self::files(path)?
+            .iter()
+            .map(|file| Self::load_file(file))
+            .collect::<Result<Vec<Vec<CatalogResource>>, CatalogError>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<CatalogResource>>();
+
+        Self::new(resources)
+    }
+
+    /// Validates the resources
+    pub fn new(resources: Vec<CatalogResource>) -> Result<Self, CatalogError> {
+        let mut handlers = BTreeSet::<CatalogHandler>::new();
+
+        for resource in &resources {
+            resource.validate()?;
+
+            if !handlers.insert(resource.handler) {
+                return Err(CatalogError::DuplicateHandler(resource.handler));
+            }
+        }
+
+        Ok(Self {
+            resources,
+            loaded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Parses one catalog file, the format is chosen by the `toml` or `json` extension
+    pub fn parse(extension: &str, contents: &str) -> Result<Vec<CatalogResource>, CatalogError> {
+        let file = match extension {
+            "toml" => toml::from_str::<CatalogFile>(contents)
+                .map_err(|error| CatalogError::Parse(error.to_string()))?,
+            "json" => serde_json::from_str::<CatalogFile>(contents)
+                .map_err(|error| CatalogError::Parse(error.to_string()))?,
+            _ => return Err(CatalogError::UnsupportedFormat(extension.to_string())),
+        };
+
+        Ok(file.resources)
+    }
+
+    pub fn resources(&self) -> &[CatalogResource] {
+        self.resources.as_slice()
+    }
+
+    pub fn get(&self, handler: CatalogHandler) -> Option<&CatalogResource> {
+        self.resources
+            .iter()
+            .find(|resource| resource.handler == handler)
+    }
+
+    pub fn handlers(&self) -> BTreeSet<CatalogHandler> {
+        self.resources
+            .iter()
+            .map(|resource| resource.handler)
+            .collect()
+    }
+
+    pub fn loaded_at(&self) -> u64 {
+        self.loaded_at
+    }
+
+    /// Reloads the catalog if a file changed. An invalid catalog is reported and the
+    /// current one is kept until the files change again.
+    pub fn reload_if_changed(path: &Path) {
+        let fingerprint = Self::fingerprint(path);
+        let mut last_fingerprint = CATALOG_FINGERPRINT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if last_fingerprint.as_ref() == Some(&fingerprint) {
+            return;
+        }
+
+        let is_first_check = last_fingerprint.is_none();
+        last_fingerprint.replace(fingerprint);
+
+        // The files were loaded at startup
+        if is_first_check {
+            return;
+        }
+
+        match Self::load(path) {
+            Ok(catalog) => {
+                let current = Self::current();
+                let added = catalog
+                    .handlers()
+                    .difference(&current.handlers())
+                    .copied()
+                    .collect::<Vec<CatalogHandler>>();
+
+                if !added.is_empty() {
+                    eprintln!(
+                        "The resource catalog added {added:?} which are served after a restart"
+                    );
+                }
+
+                *RESOURCE_CATALOG
+                    .write()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(catalog);
+            }
+            Err(error) => {
+                eprintln!("Keeping the previous resource catalog. Error: {error}")
+            }
+        }
+    }
+
+    /// Polls the catalog files for changes while the server is running
+    pub fn hot_reload() -> AdHoc {
+        AdHoc::on_liftoff("Resource catalog hot reload", |_| {
+            Box::pin(async {
+                let path = SERVER_CONFIG.catalog_path();
+
+                rocket::tokio::spawn(async move {
+                    let mut interval = rocket::tokio::time::interval(Self::RELOAD_INTERVAL);
+
+                    loop {
+                        interval.tick().await;
+                        Self::reload_if_changed(&path);
+                    }
+                });
+            })
+        })
+    }
+
+    /// The routes of the handlers in the catalog
+    pub fn routes(&self) -> Vec<(&'static str, Vec<Route>)> {
+        self.handlers()
+            .into_iter()
+            .map(|handler| (handler.mount_base(), handler.routes()))
+            .collect()
+    }
+
+    fn files(path: &Path) -> Result<Vec<PathBuf>, CatalogError> {
+        if !path.is_dir() {
+            return Ok(vec![path.to_path_buf()]);
+        }
+
+        let mut files = std::fs::read_dir(path)
+            .map_err(|error| CatalogError::Io(path.display().to_string(), error.to_string()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| {
+                matches!(
+                    file.extension().and_then(|extension| extension.to_str()),
+                    Some("toml" | "json")
+                )
+            })
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+
+        Ok(files)
+    }
+
+    fn load_file(file: &Path) -> Result<Vec<CatalogResource>, CatalogError> {
+        let contents = std::fs::read_to_string(file)
+            .map_err(|error| CatalogError::Io(file.display().to_string(), error.to_string()))?;
+        let extension = file
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+
+        Self::parse(extension, &contents)
+    }
+
+    fn fingerprint(path: &Path) -> Vec<(PathBuf, SystemTime)> {
+        Self::files(path)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|file| {
+                let modified = std::fs::metadata(&file).and_then(|metadata| metadata.modified());
+
+                modified.ok().map(|modified| (file, modified))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogFile {
+    #[serde(default)]
+    resources: Vec<CatalogResource>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogResource {
+    /// The route that serves the resource
+    pub handler: CatalogHandler,
+    /// The public URL of the resource
+    pub resource: String,
+    #[serde(default)]
+    pub r#type: CatalogResourceType,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub header_image: Option<String>,
+    pub max_timeout_seconds: u64,
+    /// How long a payment grants access with an access token. Every request is paid when unset
+    pub access_seconds: Option<u64>,
+    /// Meters a streamed resource per payment instead of paying for it up front
+    pub metering: Option<CatalogMetering>,
+    /// Defaults to the `resource_server` of the server config
+    pub pay_to: Option<String>,
+    pub accepts: Vec<CatalogPaymentOption>,
+}
+
+impl CatalogResource {
+    /// The payment option of the resource on `chain`
+    pub fn payment_option(&self, chain: SolanaChain) -> Option<&CatalogPaymentOption> {
+        self.accepts.iter().find(|option| option.network == chain)
+    }
+
+    pub fn pay_to(&self) -> &str {
+        self.pay_to
+            .as_deref()
+            .unwrap_or(SERVER_CONFIG.resource_server_address())
+    }
+
+    pub fn max_timeout(&self) -> Duration {
+        Duration::from_secs(self.max_timeout_seconds)
+    }
+
+    fn validate(&self) -> Result<(), CatalogError> {
+        let invalid = |error: &str| CatalogError::InvalidResource {
+            resource: self.resource.clone(),
+            error: error.to_string(),
+        };
+
+        let path = self
+            .resource
+            .strip_prefix("https://")
+            .and_then(|without_scheme| {
+                without_scheme
+                    .find('/')
+                    .map(|index| &without_scheme[index..])
+            })
+            .ok_or_else(|| invalid("The resource must be a https URL"))?;
+
+        if path != self.handler.path() {
+            return Err(invalid(&format!(
+                "The path of the resource must be `{}` to be served by `{:?}`",
+                self.handler.path(),
+                self.handler
+            )));
+        }
+
+        if self
+            .header_image
+            .as_ref()
+            .is_some_and(|header_image| !header_image.starts_with("https://"))
+        {
+            return Err(invalid("The header image must be a https URL"));
+        }
+
+        if self.max_timeout_seconds == 0 {
+            return Err(invalid(
+                "The `max_timeout_seconds` must be greater than zero",
+            ));
+        }
+
+        if self.access_seconds == Some(0) {
+            return Err(invalid("The `access_seconds` must be greater than zero"));
+        }
+
+        if let Some(metering) = self.metering.as_ref() {
+            if !self.handler.is_stream() {
+                return Err(invalid("Only streamed resources can be metered"));
+            }
+
+            if self.access_seconds.is_some() {
+                return Err(invalid(
+                    "A metered resource is paid per allowance, remove `access_seconds`",
+                ));
+            }
+
+            metering.validate().map_err(|error| invalid(&error))?;
+        }
+
+        if let Some(pay_to) = self.pay_to.as_ref() {
+            Pubkey::from_str(pay_to).or(Err(invalid("The `pay_to` is not a base58 address")))?;
+        }
+
+        if self.accepts.is_empty() {
+            return Err(invalid(
+                "At least one payment option is required in `accepts`",
+            ));
+        }
+
+        let mut networks = BTreeSet::<SolanaChain>::new();
+
+        for option in &self.accepts {
+            if !networks.insert(option.network) {
+                return Err(invalid(&format!(
+                    "There is more than one payment option on `{}`",
+                    option.network
+                )));
+            }
+
+            option.validate().map_err(|error| invalid(&error))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogPaymentOption {
+    pub network: SolanaChain,
+    /// The mint address, or the system program for SOL
+    pub asset: String,
+    pub decimals: u8,
+    /// The price in the smallest unit of the asset
+    pub max_amount_required: u64,
+    pub description: String,
+    /// Whether the mint is owned by the Token-2022 program instead of the legacy token program
+    #[serde(default)]
+    pub token_2022: bool,
+}
+
+impl CatalogPaymentOption {
+    pub const SOL_DECIMALS: u8 = 9;
+
+    pub fn is_sol(&self) -> bool {
+        self.asset == solana_system_interface::program::ID.to_string()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        Pubkey::from_str(&self.asset).or(Err(format!(
+            "The asset `{}` is not a base58 address",
+            self.asset
+        )))?;
+
+        if self.is_sol() && self.decimals != Self::SOL_DECIMALS {
+            return Err("SOL has 9 decimals".to_string());
+        }
+
+        if self.is_sol() && self.token_2022 {
+            return Err("SOL is not a Token-2022 mint".to_string());
+        }
+
+        if self.max_amount_required == 0 {
+            return Err("The `max_amount_required` must be greater than zero".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// The allowance of each payment for a metered stream. The stream is paused for another
+/// payment at whichever of the two runs out first.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogMetering {
+    /// The number of events
+    pub events: Option<u64>,
+    /// The number of seconds of streaming
+    pub seconds: Option<u64>,
+}
+
+impl CatalogMetering {
+    fn validate(&self) -> Result<(), String> {
+        if self.events.is_none() && self.seconds.is_none() {
+            return Err("The metering requires `events`, `seconds` or both".to_string());
+        }
+
+        if self.events == Some(0) || self.seconds == Some(0) {
+            return Err("The metering allowance must be greater than zero".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// The `type` of a resource in the discovery payload
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CatalogResourceType {
+    #[default]
+    Http,
+    A2a,
+    Mcp,
+}
+
+impl CatalogResourceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Http => "http",
+            Self::A2a => "a2a",
+            Self::Mcp => "mcp",
+        }
+    }
+}
+
+/// The routes that can serve a paid resource
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatalogHandler {
+    LatestNewsletter,
+    Voting,
+}
+
+impl CatalogHandler {
+    /// The path of the route including its mount base
+    pub fn path(&self) -> &'static str {
+        match self {
+            Self::LatestNewsletter => "/latest_newsletter",
+            Self::Voting => "/x402/voting",
+        }
+    }
+
+    /// Whether the route streams server-sent events
+    pub fn is_stream(&self) -> bool {
+        matches!(self, Self::Voting)
+    }
+
+    pub fn mount_base(&self) -> &'static str {
+        match self {
+            Self::LatestNewsletter => "/",
+            Self::Voting => "/x402",
+        }
+    }
+
+    pub fn routes(&self) -> Vec<Route> {
+        match self {
+            Self::LatestNewsletter => routes![crate::latest_newsletter],
+            Self::Voting => routes![crate::voting_handler, crate::voting_top_up],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, thiserror::Error)]
+pub enum CatalogError {
+    /// A catalog file or directory could not be read
+    #[error("Unable to read `{0}`. Error: {1}")]
+    Io(String, String),
+    /// A catalog file is not valid TOML or JSON of the catalog
+    #[error("Unable to parse the resource catalog. Error: {0}")]
+    Parse(String),
+    /// Catalog files must have the `toml` or `json` extension
+    #[error("Unsupported resource catalog format `{0}`, use `toml` or `json`")]
+    UnsupportedFormat(String),
+    /// A resource failed validation
+    #[error("Invalid resource `{resource}`. Error: {error}")]
+    InvalidResource { resource: String, error: String },
+    /// A route can only serve one resource
+    #[error("More than one resource uses the `{0:?}` handler")]
+    DuplicateHandler(CatalogHandler),
+}
+
+#[cfg(test)]
+mod resource_catalog_sanity {
+    use super::*;
+
+    #[test]
+    fn test_shipped_catalog() {
+        let resources = ResourceCatalog::parse(
+            "toml",
+            include_str!(concat!(std::env!("CARGO_WORKSPACE_DIR"), "catalog.toml")),
+        )
+        .unwrap();
+        let catalog = ResourceCatalog::new(resources).unwrap();
+
+        let newsletter = catalog.get(CatalogHandler::LatestNewsletter).unwrap();
+        assert_eq!(newsletter.r#type, CatalogResourceType::Http);
+        assert_eq!(
+            newsletter
+                .payment_option(SolanaChain::Devnet)
+                .map(|option| option.max_amount_required),
+            Some(100_000)
+        );
+        assert_eq!(newsletter.payment_option(SolanaChain::MainnetBeta), None);
+        let voting = catalog.get(CatalogHandler::Voting).unwrap();
+        assert!(voting
+            .payment_option(SolanaChain::Devnet)
+            .is_some_and(CatalogPaymentOption::is_sol));
+        assert_eq!(
+            voting.metering,
+            Some(CatalogMetering {
+                events: None,
+                seconds: Some(60)
+            })
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let resources = ResourceCatalog::parse(
+            "json",
+            r#"{"resources":[{
+                "handler": "voting",
+                "resource": "https://example.com/x402/voting",
+                "max_timeout_seconds": 60,
+                "accepts": [{
+                    "network": "solana-devnet",
+                    "asset": "11111111111111111111111111111111",
+                    "decimals": 9,
+                    "max_amount_required": 1,
+                    "description": "Live updates"
+                }]
+            }]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ResourceCatalog::new(resources).map(|catalog| catalog.handlers()),
+            Ok(BTreeSet::from([CatalogHandler::Voting]))
+        );
+        assert_eq!(
+            ResourceCatalog::parse("yaml", ""),
+            Err(CatalogError::UnsupportedFormat("yaml".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let valid = ResourceCatalog::parse(
+            "toml",
+            include_str!(concat!(std::env!("CARGO_WORKSPACE_DIR"), "catalog.toml")),
+        )
+        .unwrap();
+
+        let mut wrong_path = valid.clone();
+        wrong_path[0].resource = "https://lagoon.markets/newsletter".to_string();
+        assert!(matches!(
+            ResourceCatalog::new(wrong_path),
+            Err(CatalogError::InvalidResource { .. })
+        ));
+
+        let mut duplicate = valid.clone();
+        duplicate.push(valid[0].clone());
+        assert_eq!(
+            ResourceCatalog::new(duplicate),
+            Err(CatalogError::DuplicateHandler(
+                CatalogHandler::LatestNewsletter
+            ))
+        );
+
+        let mut wrong_decimals = valid.clone();
+        wrong_decimals[1].accepts[0].decimals = 6;
+        assert!(ResourceCatalog::new(wrong_decimals).is_err());
+
+        let mut free = valid.clone();
+        free[0].accepts[0].max_amount_required = 0;
+        assert!(ResourceCatalog::new(free).is_err());
+
+        let mut no_access = valid.clone();
+        no_access[0].access_seconds = Some(0);
+        assert!(ResourceCatalog::new(no_access).is_err());
+
+        let mut metered_newsletter = valid.clone();
+        metered_newsletter[0].access_seconds = None;
+        metered_newsletter[0].metering = valid[1].metering;
+        assert!(ResourceCatalog::new(metered_newsletter).is_err());
+
+        let mut no_allowance = valid.clone();
+        no_allowance[1].metering = Some(CatalogMetering {
+            events: None,
+            seconds: None,
+        });
+        assert!(ResourceCatalog::new(no_allowance).is_err());
+
+        let mut no_accepts = valid;
+        no_accepts[0].accepts.clear();
+        assert!(ResourceCatalog::new(no_accepts).is_err());
+
+        assert!(matches!(
+            ResourceCatalog::parse("toml", "[[resources]]\nhandler = \"unknown\""),
+            Err(CatalogError::Parse(_))
+        ));
+    }
+}
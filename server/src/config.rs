@@ -1,6 +1,11 @@
 use std::path::{Path, PathBuf};
 
+use common::SolanaChain;
 use serde::Deserialize;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+use crate::FeePayerLimits;
 
 #[derive(Debug, Deserialize)]
 pub struct ServerConfig {
@@ -8,17 +13,34 @@ pub struct ServerConfig {
     devnet_endpoint: String,
     mainnet_endpoint: String,
     santum_api: String,
+    /// A TOML or JSON file, or a directory of them, relative to `secrets.toml`.
+    /// Defaults to `catalog.toml`
+    catalog: Option<String>,
+    /// The redb database of settled payments, relative to `secrets.toml`.
+    /// Defaults to `payments.redb`
+    ledger: Option<String>,
+    /// The base58 32 byte secret key that signs access tokens. A random key is used when unset
+    /// which invalidates the issued access tokens on restart
+    access_token_secret_key: Option<String>,
+    /// The domain of the Sign In With Solana messages that delegate session keys.
+    /// Defaults to `lagoon.markets`
+    siws_domain: Option<String>,
+    /// Parsed from `payment_details.facilitator_secret_key` when the config is loaded
+    #[serde(skip)]
+    facilitator_keypair: Option<Keypair>,
+    /// Parsed from `access_token_secret_key` when the config is loaded
+    #[serde(skip)]
+    access_token_secret: [u8; 32],
+    /// The chains with a configured RPC endpoint, found when the config is loaded
+    #[serde(skip)]
+    configured_chains: Vec<SolanaChain>,
 }
 
 impl ServerConfig {
     pub fn parse() -> Self {
         use std::{fs::File, io::Read};
 
-        let mut path = if cfg!(debug_assertions) {
-            Path::new(std::env!("CARGO_WORKSPACE_DIR")).to_path_buf()
-        } else {
-            PathBuf::new()
-        };
+        let mut path = Self::config_dir();
         path.push("secrets.toml");
 
         dbg!(&path);
@@ -37,15 +59,51 @@ impl ServerConfig {
             })
             .unwrap();
 
-        let parsed_config = toml::from_str::<Self>(&contents).unwrap();
+        let mut parsed_config = toml::from_str::<Self>(&contents).unwrap();
+        parsed_config.facilitator_keypair = parsed_config.parse_facilitator_keypair();
+        parsed_config.access_token_secret = parsed_config.parse_access_token_secret();
+        parsed_config.configured_chains = SolanaChain::ALL
+            .into_iter()
+            .filter(|chain| parsed_config.configured_rpc_endpoint(*chain).is_some())
+            .collect();
 
         if parsed_config.facilitator_address().is_none() && !parsed_config.client_is_facilitator() {
             panic!("There needs to be a facilitator. Set the `client_is_facilitator` to true or add a `facilitator` address to the config file")
         }
 
+        // The server settles the payments itself so it must be able to sign as the fee payer
+        if parsed_config.facilitator_keypair().is_none() && !parsed_config.client_is_facilitator() {
+            panic!("The `facilitator` needs its `facilitator_secret_key` to co-sign the payments the server settles")
+        }
+
         parsed_config
     }
 
+    /// The directory of `secrets.toml`
+    fn config_dir() -> PathBuf {
+        if cfg!(debug_assertions) {
+            Path::new(std::env!("CARGO_WORKSPACE_DIR")).to_path_buf()
+        } else {
+            PathBuf::new()
+        }
+    }
+
+    /// The path of the resource catalog
+    pub fn catalog_path(&self) -> PathBuf {
+        let mut path = Self::config_dir();
+        path.push(self.catalog.as_deref().unwrap_or("catalog.toml"));
+
+        path
+    }
+
+    /// The path of the payment ledger
+    pub fn ledger_path(&self) -> PathBuf {
+        let mut path = Self::config_dir();
+        path.push(self.ledger.as_deref().unwrap_or("payments.redb"));
+
+        path
+    }
+
     pub fn resource_server_address(&self) -> &str {
         self.payment_details.resource_server.as_str()
     }
@@ -54,6 +112,80 @@ impl ServerConfig {
         self.payment_details.facilitator.as_ref()
     }
 
+    /// The keypair that co-signs as the fee payer when this server is the facilitator
+    pub fn facilitator_keypair(&self) -> Option<&Keypair> {
+        self.facilitator_keypair.as_ref()
+    }
+
+    fn parse_facilitator_keypair(&self) -> Option<Keypair> {
+        let secret_key = self.payment_details.facilitator_secret_key.as_ref()?;
+
+        let keypair = bs58::decode(secret_key)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| Keypair::try_from(bytes.as_slice()).ok())
+            .unwrap_or_else(|| {
+                panic!("The `facilitator_secret_key` must be a base58 encoded 64 byte keypair")
+            });
+
+        if self.facilitator_address() != Some(&keypair.pubkey().to_string()) {
+            panic!("The `facilitator_secret_key` is not the keypair of the `facilitator` address")
+        }
+
+        Some(keypair)
+    }
+
+    /// The secret key that signs the access tokens of paid resources
+    pub fn access_token_secret(&self) -> &[u8; 32] {
+        &self.access_token_secret
+    }
+
+    fn parse_access_token_secret(&self) -> [u8; 32] {
+        let Some(secret_key) = self.access_token_secret_key.as_ref() else {
+            let mut secret = [0u8; 32];
+            secret.copy_from_slice(&Keypair::new().to_bytes()[..32]);
+
+            return secret;
+        };
+
+        bs58::decode(secret_key)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+            .unwrap_or_else(|| {
+                panic!("The `access_token_secret_key` must be a base58 encoded 32 byte secret key")
+            })
+    }
+
+    /// The domain a SIWS message must be signed for to delegate a session key
+    pub fn siws_domain(&self) -> &str {
+        self.siws_domain.as_deref().unwrap_or("lagoon.markets")
+    }
+
+    /// The recipients of the payments the facilitator routes verify and settle
+    pub fn facilitator_pay_to(&self) -> &[String] {
+        self.payment_details
+            .facilitator_pay_to
+            .as_deref()
+            .unwrap_or(std::slice::from_ref(&self.payment_details.resource_server))
+    }
+
+    /// The compute budget of the transactions the facilitator co-signs as the fee payer
+    pub fn fee_payer_limits(&self) -> FeePayerLimits {
+        let defaults = FeePayerLimits::default();
+
+        FeePayerLimits {
+            max_compute_unit_limit: self
+                .payment_details
+                .max_compute_unit_limit
+                .unwrap_or(defaults.max_compute_unit_limit),
+            max_compute_unit_price: self
+                .payment_details
+                .max_compute_unit_price
+                .unwrap_or(defaults.max_compute_unit_price),
+        }
+    }
+
     pub fn client_is_facilitator(&self) -> bool {
         self.payment_details.client_is_facilitator
     }
@@ -66,6 +198,28 @@ impl ServerConfig {
         self.mainnet_endpoint.as_str()
     }
 
+    /// The configured RPC endpoint of the chain, falling back to the public cluster endpoint
+    pub fn rpc_endpoint(&self, chain: SolanaChain) -> &str {
+        self.configured_rpc_endpoint(chain)
+            .unwrap_or(chain.default_rpc_url())
+    }
+
+    /// The RPC endpoint of the chain set in `secrets.toml`
+    pub fn configured_rpc_endpoint(&self, chain: SolanaChain) -> Option<&str> {
+        let endpoint = match chain {
+            SolanaChain::MainnetBeta => self.mainnet_endpoint(),
+            SolanaChain::Devnet => self.devnet_endpoint(),
+            SolanaChain::Testnet | SolanaChain::Localnet => return Option::None,
+        };
+
+        (!endpoint.is_empty()).then_some(endpoint)
+    }
+
+    /// The chains whose RPC endpoint is set in `secrets.toml`
+    pub fn configured_chains(&self) -> &[SolanaChain] {
+        &self.configured_chains
+    }
+
     pub fn sanctum_uri(&self) -> &str {
         self.santum_api.as_str()
     }
@@ -75,5 +229,14 @@ impl ServerConfig {
 pub struct PaymentDetailsConfig {
     resource_server: String,
     facilitator: Option<String>,
+    /// The base58 keypair of `facilitator` used to settle payments as the fee payer
+    facilitator_secret_key: Option<String>,
     client_is_facilitator: bool,
+    /// The recipients of the payments `/facilitator/verify` and `/facilitator/settle` accept.
+    /// Defaults to the `resource_server`
+    facilitator_pay_to: Option<Vec<String>>,
+    /// The highest compute unit limit of a transaction co-signed as the fee payer
+    max_compute_unit_limit: Option<u32>,
+    /// The highest compute unit price in micro-lamports of a transaction co-signed as the fee payer
+    max_compute_unit_price: Option<u64>,
 }
@@ -9,8 +9,8 @@ pub use config::*;
 mod newsletter;
 pub use newsletter::*;
 
-mod allowed_assets;
-pub use allowed_assets::*;
+mod catalog;
+pub use catalog::*;
 
 mod x402;
 pub use x402::*;
@@ -21,26 +21,66 @@ pub use deeplinks_handler::*;
 mod fetch_mint_info;
 pub use fetch_mint_info::*;
 
+mod x402_error;
+pub use x402_error::*;
+
+mod x402_client;
+pub use x402_client::*;
+
+mod payment_verifier;
+pub use payment_verifier::*;
+
+mod paywall;
+pub use paywall::*;
+
+mod payment_ledger;
+pub use payment_ledger::*;
+
+mod stream_meter;
+pub use stream_meter::*;
+
 #[allow(clippy::redundant_closure)]
 pub(crate) static SERVER_CONFIG: once_cell::sync::Lazy<ServerConfig> =
     once_cell::sync::Lazy::new(|| ServerConfig::parse());
 
 #[rocket::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    rocket::build()
+    let mut server = rocket::build()
         .mount("/", FileServer::from("static"))
-        .mount("/", routes![latest_newsletter, mint_info])
+        .mount("/", routes![mint_info])
         .mount(
             "/x402",
             routes![
                 x402_discover,
-                voting_handler,
                 optimize_tx,
-                send_optimized_tx
+                send_optimized_tx,
+                tip_instructions,
+                facilitator_supported,
+                facilitator_verify,
+                facilitator_settle,
+                resource_payments
+            ],
+        )
+        .register(
+            "/",
+            catchers![
+                x402_bad_request,
+                x402_unauthorized,
+                x402_payment_required,
+                x402_conflict,
+                x402_internal_error,
+                x402_bad_gateway
             ],
         )
-        .launch()
-        .await?;
+        .attach(ResourceCatalog::hot_reload())
+        .attach(X402Paywall::fairing());
+
+    // Only the handlers of resources in the catalog are served
+    for (base, routes) in ResourceCatalog::current().routes() {
+        server = server.mount(base, routes);
+    }
+
+    server.launch().await?;
 
     Ok(())
 }
@@ -1,28 +1,27 @@
-use std::collections::VecDeque;
-use std::time::Duration;
-
 use common::{
-    CommonHeaders, CommonUtils, EventSourceData, EventSourceProgressPoint,
-    EventSourceProgressSegment, EventSourceProgressStyle, SolanaChain, X402400BadRequest,
+    EventSourceControl, EventSourceData, EventSourceProgressPoint, EventSourceProgressSegment,
+    EventSourceProgressStyle, X402AccessToken, X402ErrorCode, X402ErrorResponse,
+};
+use rocket::http::{Header, Status};
+use rocket::response::content::RawJson;
+use rocket::response::stream::Event;
+use rocket::serde::json::Json;
+
+use crate::{
+    CatalogHandler, CatalogMetering, LatestNewsletter, Paid, PaidStream, PaymentLedger,
+    ResourceCatalog, StreamMeter, TopUpStream, Voting, X402Error, X402PaymentVerifier, X402Paywall,
 };
-use rocket::http::{self, Status};
-use rocket::request::{Outcome, Request};
-use rocket::response::stream::{Event, EventStream};
-use rocket::response::{self, Responder, Response};
-use rocket::tokio::time;
-use rusty_x402::PaymentRequestExtras;
-use rusty_x402::X_PAYMENT_HEADER_KEY;
-use rusty_x402::{PaymentRequirementsBuilder, PaymentRequirementsResponse};
-
-use crate::{AllowedAssets, SERVER_CONFIG};
 
-pub const NEWSLETTER_URI: &str = "https://lagoon.markets/latest_newsletter";
-pub const VOTING: &str = "https://lagoon.markets/x402/voting";
+/// How often [voting_handler] sends an event
+const VOTING_EVENT_INTERVAL: u64 = 3;
 
+/// Streams the live updates paid up front, or paused for a top-up whenever the metered
+/// allowance runs out. See [EventSourceControl] for the control events.
 #[get("/voting")]
-pub async fn voting_handler() -> Result<rocket::response::stream::EventStream![], (Status, String)>
-{
-    use rocket::response::stream::{Event, EventStream};
+pub async fn voting_handler(
+    access: PaidStream<Voting>,
+) -> Result<rocket::response::stream::EventStream![], (Status, String)> {
+    use rocket::response::stream::EventStream;
     use rocket::tokio::time::{self, Duration};
     use std::collections::VecDeque;
 
@@ -38,138 +37,185 @@ pub async fn voting_handler() -> Result<rocket::response::stream::EventStream![]
         })?;
 
     Ok(EventStream! {
-        let mut interval = time::interval(Duration::from_secs(3));
+        let (meter, access_expires_at) = match access {
+            PaidStream::Paid(paid) => (None, paid.access_token.map(|token| token.expires_at)),
+            PaidStream::Metered { stream, resource } => {
+                let top_up = resource.resource.clone()
+                    + "/top-up?"
+                    + TopUpStream::QUERY_PARAM
+                    + "="
+                    + stream.id.as_str();
+
+                yield control_event(&EventSourceControl::Metered {
+                    stream_id: stream.id.clone(),
+                    top_up: top_up.clone(),
+                    events: stream.metering.events,
+                    seconds: stream.metering.seconds,
+                });
+
+                (Some((stream, top_up, resource.max_timeout())), None)
+            }
+        };
+
+        let mut interval = time::interval(Duration::from_secs(VOTING_EVENT_INTERVAL));
         interval.tick().await; // wait before sending the first event
         loop {
-            if let Some(value) = events.pop_front() {
-                yield Event::data(value).id("streaming");
-            } else {
-                yield Event::data("").id("done");
+            let Some(value) = events.pop_front() else {
+                yield Event::data("").id(EventSourceData::DONE_ID);
                 break;
+            };
+
+            let now = PaymentLedger::now();
+
+            if access_expires_at.is_some_and(|expires_at| now >= expires_at) {
+                yield control_event(&EventSourceControl::AccessExpired);
+                yield Event::data("").id(EventSourceData::DONE_ID);
+                break;
+            }
+
+            if let Some((stream, top_up, max_timeout)) = meter.as_ref() {
+                if !stream.has_allowance(now) {
+                    yield control_event(&EventSourceControl::PaymentRequired {
+                        stream_id: stream.id.clone(),
+                        top_up: top_up.clone(),
+                        expires_at: now.saturating_add(max_timeout.as_secs()),
+                    });
+
+                    let payment = stream.wait_for_top_up(PaymentLedger::now);
+                    let mut paid = time::timeout(*max_timeout, payment).await.ok();
+
+                    // A top-up that is still settling at the timeout keeps the stream open,
+                    // at most as long as one settlement can take
+                    let settled_by = time::Instant::now()
+                        + X402PaymentVerifier::CONFIRMATION_INTERVAL
+                            * (X402PaymentVerifier::CONFIRMATION_ATTEMPTS + 1);
+                    while paid.is_none()
+                        && stream.has_pending_top_up()
+                        && time::Instant::now() < settled_by
+                    {
+                        let payment = stream.wait_for_top_up(PaymentLedger::now);
+                        paid = time::timeout(Duration::from_secs(1), payment).await.ok();
+                    }
+
+                    match paid {
+                        Some(transaction) => {
+                            yield control_event(&EventSourceControl::PaymentAccepted {
+                                transaction: transaction.unwrap_or_default(),
+                            });
+                        }
+                        None => {
+                            yield control_event(&EventSourceControl::AccessExpired);
+                            yield Event::data("").id(EventSourceData::DONE_ID);
+                            break;
+                        }
+                    }
+                }
+
+                stream.consume_event();
             }
+
+            yield Event::data(value).id(EventSourceData::STREAMING_ID);
             interval.tick().await;
         }
     })
 }
 
-#[get("/latest_newsletter")]
-pub(crate) async fn latest_newsletter() -> X402HttpResponse {
-    X402HttpResponse
-}
+/// Settles a payment for the `stream` of [voting_handler] and resumes it. The [TopUpStream]
+/// guard goes before [Paid] so that a closed stream is not paid for.
+#[post("/voting/top-up")]
+pub async fn voting_top_up(
+    stream: TopUpStream,
+    paid: Paid<Voting>,
+) -> Result<Json<EventSourceControl>, X402TopUpError> {
+    let Some(payment) = paid.payment else {
+        return Err(X402ErrorResponse::new(
+            X402ErrorCode::PaymentRejected,
+            &paid
+                .access_token
+                .map(|access_token| access_token.resource)
+                .unwrap_or_default(),
+            "A metered stream is topped up with an `X-PAYMENT`, not an access token",
+        )
+        .into());
+    };
 
-pub struct X402HttpResponse;
-
-impl<'r> Responder<'r, 'static> for X402HttpResponse {
-    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
-        let latest_newsletter_uri = NEWSLETTER_URI;
-        let json_body;
-        let status: http::Status;
-
-        let x_public_key: String;
-        if let Some(x_public_key_inner) = req.headers().get_one(CommonHeaders::X402_ADDRESS_HEADER)
-        {
-            x_public_key = x_public_key_inner.to_string();
-        } else {
-            let inner_status = http::Status::BadRequest;
-            let x_public_key_json_error = jzon::object! {
-                status: inner_status.code,
-                error: format!("Bad request. The `{}` header is missing or malformed. It requires a base58 encoded Ed25519 public address", CommonHeaders::X402_ADDRESS_HEADER),
-                resource: latest_newsletter_uri,
-                header: CommonHeaders::X402_CHAIN_HEADER
-            }.to_string();
-
-            return Response::build()
-                .sized_body(
-                    x_public_key_json_error.len(),
-                    std::io::Cursor::new(x_public_key_json_error),
-                )
-                .header(rocket::http::ContentType::JSON)
-                .status(inner_status)
-                .ok();
-        }
+    // Settling takes a while, the stream may have closed in the meantime. The payment is
+    // credited to an access token for the allowance of the top-up instead.
+    if StreamMeter::get(&stream.0.id).is_none() {
+        let resource = ResourceCatalog::current()
+            .get(CatalogHandler::Voting)
+            .map(|resource| resource.resource.clone())
+            .unwrap_or_default();
+
+        let access_token = X402AccessToken::new(
+            &resource,
+            &payment.payer,
+            &payment.transaction,
+            PaymentLedger::now(),
+            voting_credit_secs(&stream.0.metering),
+        );
+        let error = X402ErrorResponse::new(
+            X402ErrorCode::InvalidRequest,
+            &resource,
+            &format!(
+                "The stream closed while the payment `{}` settled, it is credited to the \
+                `X402-Access-Token` of the next stream",
+                payment.transaction
+            ),
+        );
+
+        return Err(X402TopUpError::Credited(
+            error.into(),
+            X402Paywall::access_token_header(&access_token),
+        ));
+    }
 
-        let chain: String;
-        if let Some(chain_inner) = req.headers().get_one(CommonHeaders::X402_CHAIN_HEADER) {
-            chain = chain_inner.to_string();
-        } else {
-            let inner_status = http::Status::BadRequest;
-            let x_chain_json_error = X402400BadRequest{
-                status: inner_status.code,
-                error: format!("Bad request. The `{}` header is missing or malformed. It requires the chain identification in x402 format", CommonHeaders::X402_CHAIN_HEADER),
-                resource: latest_newsletter_uri.to_string(),
-                header: CommonHeaders::X402_CHAIN_HEADER.to_string()
-            }.to_json().to_string();
-
-            return Response::build()
-                .sized_body(
-                    x_chain_json_error.len(),
-                    std::io::Cursor::new(x_chain_json_error),
-                )
-                .header(rocket::http::ContentType::JSON)
-                .status(inner_status)
-                .ok();
-        }
+    stream.0.top_up(&payment.transaction, PaymentLedger::now());
 
-        if let Some(x_payment_header) = req.headers().get_one(X_PAYMENT_HEADER_KEY) {
-            json_body = jzon::object! {
-                status: 200
-            }
-            .to_string();
-
-            status = http::Status::Ok;
-        } else {
-            let fee_payer = if SERVER_CONFIG.client_is_facilitator() {
-                x_public_key
-            } else {
-                SERVER_CONFIG.facilitator_address().unwrap().to_owned()
-            };
+    Ok(Json(EventSourceControl::PaymentAccepted {
+        transaction: payment.transaction,
+    }))
+}
 
-            json_body = construct_response(&fee_payer, &chain)
-                .or(Err(http::Status::InternalServerError))?
-                .to_json()
-                .or(Err(http::Status::InternalServerError))?
-                .to_string();
+/// How long the allowance of a top-up lasts, at whichever of its seconds and
+/// events runs out first
+fn voting_credit_secs(metering: &CatalogMetering) -> u64 {
+    let events_secs = metering
+        .events
+        .map(|events| events.saturating_mul(VOTING_EVENT_INTERVAL));
 
-            status = http::Status::PaymentRequired;
-        }
+    match (metering.seconds, events_secs) {
+        (Some(seconds), Some(events_secs)) => seconds.min(events_secs),
+        (seconds, events_secs) => seconds.or(events_secs).unwrap_or_default(),
+    }
+}
+
+/// Why [voting_top_up] did not resume its stream
+#[derive(Debug, Responder)]
+pub enum X402TopUpError {
+    Rejected(X402Error),
+    /// The stream closed after the payment settled, the `X402-Access-Token` header credits it
+    Credited(X402Error, Header<'static>),
+}
 
-        Response::build()
-            .sized_body(json_body.len(), std::io::Cursor::new(json_body))
-            .header(rocket::http::ContentType::JSON)
-            .status(status)
-            .ok()
+impl From<X402ErrorResponse> for X402TopUpError {
+    fn from(value: X402ErrorResponse) -> Self {
+        Self::Rejected(value.into())
     }
 }
 
-fn construct_response<'x>(
-    fee_payer: &'x str,
-    chain: &str,
-) -> Result<PaymentRequirementsResponse<'x>, String> {
-    let asset = if chain.to_lowercase().as_bytes() == SolanaChain::DEVNET_X402_ID.as_bytes() {
-        AllowedAssets::USDC_DEVNET
-    } else {
-        return Err("Unsupported network".to_string());
-    };
+#[get("/latest_newsletter")]
+pub(crate) async fn latest_newsletter(_paid: Paid<LatestNewsletter>) -> RawJson<String> {
+    RawJson(
+        jzon::object! {
+            status: 200
+        }
+        .to_string(),
+    )
+}
 
-    let extra = PaymentRequestExtras::new(fee_payer)
-        .set_legacy_token_mint()
-        .set_decimals(asset.decimals);
-
-    let mut requirement = PaymentRequirementsBuilder::new();
-    requirement
-        .set_amount((0.10 * 10f64.powi(6)) as u64)
-        .set_asset(asset.address)
-        .set_description("Read the latest on Solana developer tooling.")
-        .set_recipient(SERVER_CONFIG.resource_server_address())
-        .set_mime_as_json()
-        .set_max_timeout_seconds(Duration::from_secs(100))
-        .set_resource(NEWSLETTER_URI)
-        .set_extra(extra);
-
-    let mut body = PaymentRequirementsResponse::new();
-    body.add_payment_requirement(requirement.build().unwrap());
-
-    Ok(body)
+fn control_event(control: &EventSourceControl) -> Event {
+    Event::json(control).id(EventSourceData::CONTROL_ID)
 }
 
 fn create_test_events() -> Vec<EventSourceData> {
@@ -0,0 +1,375 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
+use serde::Serialize;
+use wincode::{SchemaRead, SchemaWrite};
+
+use crate::SERVER_CONFIG;
+
+static PAYMENT_LEDGER: once_cell::sync::Lazy<PaymentLedger> = once_cell::sync::Lazy::new(|| {
+    PaymentLedger::open(&SERVER_CONFIG.ledger_path())
+        .unwrap_or_else(|error| panic!("Unable to open the payment ledger. Error: {error}"))
+});
+
+type PaymentsSchema = TableDefinition<'static, &'static str, Vec<u8>>;
+/// Keyed by the `pay_to` address, the resource, the settlement time and the signature
+type ResourcePaymentsSchema =
+    TableDefinition<'static, (&'static str, &'static str, u64, &'static str), ()>;
+/// Signatures being settled, with the unix timestamp they were reserved at
+type PendingSchema = TableDefinition<'static, &'static str, u64>;
+/// Signatures sent without being confirmed in time, with the unix timestamp they timed out at
+type UnconfirmedSchema = TableDefinition<'static, &'static str, u64>;
+
+/// Every payment settled by the server. A payment transaction is settled once,
+/// so a valid `X-PAYMENT` header cannot be replayed by other clients.
+pub struct PaymentLedger {
+    store: Database,
+}
+
+impl PaymentLedger {
+    pub const DEFAULT_LIMIT: u32 = 50;
+    pub const MAX_LIMIT: u32 = 500;
+
+    const PAYMENTS_TABLE: PaymentsSchema = PaymentsSchema::new("payments");
+    const RESOURCE_PAYMENTS_TABLE: ResourcePaymentsSchema =
+        ResourcePaymentsSchema::new("resource_payments");
+    const PENDING_TABLE: PendingSchema = PendingSchema::new("pending_payments");
+    const UNCONFIRMED_TABLE: UnconfirmedSchema = UnconfirmedSchema::new("unconfirmed_payments");
+
+    /// The ledger at `ledger` in `secrets.toml`
+    pub fn current() -> &'static Self {
+        &PAYMENT_LEDGER
+    }
+
+    pub fn open(path: &Path) -> Result<Self, PaymentLedgerError> {
+        Self::init(Database::create(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Result<Self, PaymentLedgerError> {
+        Self::init(Database::builder().create_with_backend(redb::backends::InMemoryBackend::new())?)
+    }
+
+    /// Creates the tables. Reservations left by a server that stopped while settling are
+    /// dropped so those payments can be retried. Unconfirmed payments are kept.
+    fn init(store: Database) -> Result<Self, PaymentLedgerError> {
+        let write_txn = store.begin_write()?;
+        write_txn.delete_table(Self::PENDING_TABLE)?;
+        write_txn.open_table(Self::PENDING_TABLE)?;
+        write_txn.open_table(Self::UNCONFIRMED_TABLE)?;
+        write_txn.open_table(Self::PAYMENTS_TABLE)?;
+        write_txn.open_table(Self::RESOURCE_PAYMENTS_TABLE)?;
+        write_txn.commit()?;
+
+        Ok(Self { store })
+    }
+
+    /// Reserves the signature while its transaction is settled.
+    /// Fails if the signature was already settled or is being settled.
+    pub fn reserve(&self, signature: &str) -> Result<LedgerReservation, PaymentLedgerError> {
+        let write_txn = self.store.begin_write()?;
+        let reservation = {
+            let payments = write_txn.open_table(Self::PAYMENTS_TABLE)?;
+            let unconfirmed = write_txn.open_table(Self::UNCONFIRMED_TABLE)?;
+            let mut pending = write_txn.open_table(Self::PENDING_TABLE)?;
+
+            if payments.get(signature)?.is_some() || pending.get(signature)?.is_some() {
+                return Err(PaymentLedgerError::AlreadyUsed(signature.to_string()));
+            }
+
+            pending.insert(signature, Self::now())?;
+
+            if unconfirmed.get(signature)?.is_some() {
+                LedgerReservation::Unconfirmed
+            } else {
+                LedgerReservation::New
+            }
+        };
+        write_txn.commit()?;
+
+        Ok(reservation)
+    }
+
+    /// Drops the reservation of a payment whose settlement failed
+    pub fn release(&self, signature: &str) -> Result<(), PaymentLedgerError> {
+        let write_txn = self.store.begin_write()?;
+        {
+            write_txn
+                .open_table(Self::PENDING_TABLE)?
+                .remove(signature)?;
+            write_txn
+                .open_table(Self::UNCONFIRMED_TABLE)?
+                .remove(signature)?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Keeps a payment whose transaction was sent but not confirmed in time, so the next
+    /// reservation of its signature checks the status of the transaction before resending it
+    pub fn unconfirmed(&self, signature: &str) -> Result<(), PaymentLedgerError> {
+        let write_txn = self.store.begin_write()?;
+        {
+            write_txn
+                .open_table(Self::PENDING_TABLE)?
+                .remove(signature)?;
+            write_txn
+                .open_table(Self::UNCONFIRMED_TABLE)?
+                .insert(signature, Self::now())?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Records a settled payment in place of its reservation
+    pub fn record(&self, payment: &LedgerPayment) -> Result<(), PaymentLedgerError> {
+        let bytes = wincode::serialize(payment).or(Err(PaymentLedgerError::Corrupted(
+            payment.signature.clone(),
+        )))?;
+
+        let write_txn = self.store.begin_write()?;
+        {
+            write_txn
+                .open_table(Self::PENDING_TABLE)?
+                .remove(payment.signature.as_str())?;
+            write_txn
+                .open_table(Self::UNCONFIRMED_TABLE)?
+                .remove(payment.signature.as_str())?;
+            write_txn
+                .open_table(Self::PAYMENTS_TABLE)?
+                .insert(payment.signature.as_str(), bytes)?;
+            write_txn
+                .open_table(Self::RESOURCE_PAYMENTS_TABLE)?
+                .insert(
+                    (
+                        payment.pay_to.as_str(),
+                        payment.resource.as_str(),
+                        payment.settled_at,
+                        payment.signature.as_str(),
+                    ),
+                    (),
+                )?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// The payments of `resource` to `pay_to`, newest first
+    pub fn resource_payments(
+        &self,
+        pay_to: &str,
+        resource: &str,
+        limit: u32,
+        offset: u32,
+    ) -> Result<LedgerPage, PaymentLedgerError> {
+        let read_txn = self.store.begin_read()?;
+        let index = read_txn.open_table(Self::RESOURCE_PAYMENTS_TABLE)?;
+        let payments = read_txn.open_table(Self::PAYMENTS_TABLE)?;
+
+        let mut total = 0u32;
+        let mut page = Vec::<LedgerPayment>::new();
+
+        for entry in index
+            .range((pay_to, resource, 0u64, "")..(pay_to, resource, u64::MAX, ""))?
+            .rev()
+        {
+            let (key, _) = entry?;
+            let (_, _, _, signature) = key.value();
+
+            if total >= offset && page.len() < limit as usize {
+                let bytes = payments
+                    .get(signature)?
+                    .ok_or(PaymentLedgerError::Corrupted(signature.to_string()))?;
+                page.push(
+                    wincode::deserialize::<LedgerPayment>(&bytes.value())
+                        .or(Err(PaymentLedgerError::Corrupted(signature.to_string())))?,
+                );
+            }
+
+            total = total.saturating_add(1);
+        }
+
+        Ok(LedgerPage {
+            payments: page,
+            limit,
+            offset,
+            total,
+        })
+    }
+
+    /// Unix timestamp in seconds
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+/// How a signature was reserved
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LedgerReservation {
+    /// The transaction of the payment was never sent
+    New,
+    /// The transaction was sent before but was not confirmed in time
+    Unconfirmed,
+}
+
+/// A settled payment
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, SchemaRead, SchemaWrite)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerPayment {
+    /// The base58 signature of the fee payer which identifies the transaction
+    pub signature: String,
+    pub payer: String,
+    pub pay_to: String,
+    pub resource: String,
+    /// The x402 network identifier
+    pub network: String,
+    /// The mint address, or the system program for SOL
+    pub asset: String,
+    /// The amount in the smallest unit of the asset
+    pub amount: u64,
+    /// Unix timestamp in seconds
+    pub settled_at: u64,
+}
+
+/// A page of the payments of a resource
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct LedgerPage {
+    pub payments: Vec<LedgerPayment>,
+    pub limit: u32,
+    pub offset: u32,
+    /// The number of payments of the resource across all pages
+    pub total: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, thiserror::Error)]
+pub enum PaymentLedgerError {
+    /// The payment transaction was already settled or is being settled
+    #[error("The payment transaction `{0}` was already used")]
+    AlreadyUsed(String),
+    /// The ledger database failed
+    #[error("Payment ledger storage error. Error: {0}")]
+    Storage(String),
+    /// A stored payment could not be deserialized
+    #[error("The payment `{0}` in the ledger is corrupted")]
+    Corrupted(String),
+}
+
+impl From<redb::Error> for PaymentLedgerError {
+    fn from(value: redb::Error) -> Self {
+        Self::Storage(value.to_string())
+    }
+}
+
+impl From<redb::DatabaseError> for PaymentLedgerError {
+    fn from(value: redb::DatabaseError) -> Self {
+        Self::Storage(value.to_string())
+    }
+}
+
+impl From<redb::TransactionError> for PaymentLedgerError {
+    fn from(value: redb::TransactionError) -> Self {
+        Self::Storage(value.to_string())
+    }
+}
+
+impl From<redb::TableError> for PaymentLedgerError {
+    fn from(value: redb::TableError) -> Self {
+        Self::Storage(value.to_string())
+    }
+}
+
+impl From<redb::StorageError> for PaymentLedgerError {
+    fn from(value: redb::StorageError) -> Self {
+        Self::Storage(value.to_string())
+    }
+}
+
+impl From<redb::CommitError> for PaymentLedgerError {
+    fn from(value: redb::CommitError) -> Self {
+        Self::Storage(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod payment_ledger_sanity {
+    use super::*;
+
+    fn payment(signature: &str, settled_at: u64) -> LedgerPayment {
+        LedgerPayment {
+            signature: signature.to_string(),
+            payer: "payer".to_string(),
+            pay_to: "publisher".to_string(),
+            resource: "https://lagoon.markets/latest_newsletter".to_string(),
+            network: "solana-devnet".to_string(),
+            asset: "Gh9ZwEmdLJ8DscKNTkTqPbNwLNNBjuSzaG9Vp2KGtKJr".to_string(),
+            amount: 100_000,
+            settled_at,
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let ledger = PaymentLedger::in_memory().unwrap();
+
+        assert_eq!(ledger.reserve("first"), Ok(LedgerReservation::New));
+        assert_eq!(
+            ledger.reserve("first"),
+            Err(PaymentLedgerError::AlreadyUsed("first".to_string()))
+        );
+
+        // A failed settlement can be retried
+        ledger.release("first").unwrap();
+        assert_eq!(ledger.reserve("first"), Ok(LedgerReservation::New));
+
+        // A transaction that was not confirmed in time is checked again by the next retry
+        ledger.unconfirmed("first").unwrap();
+        assert_eq!(ledger.reserve("first"), Ok(LedgerReservation::Unconfirmed));
+        assert_eq!(
+            ledger.reserve("first"),
+            Err(PaymentLedgerError::AlreadyUsed("first".to_string()))
+        );
+
+        ledger.record(&payment("first", 10)).unwrap();
+        assert_eq!(
+            ledger.reserve("first"),
+            Err(PaymentLedgerError::AlreadyUsed("first".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_resource_payments() {
+        let ledger = PaymentLedger::in_memory().unwrap();
+        for (signature, settled_at) in [("a", 10), ("b", 30), ("c", 20)] {
+            ledger.reserve(signature).unwrap();
+            ledger.record(&payment(signature, settled_at)).unwrap();
+        }
+
+        let resource = "https://lagoon.markets/latest_newsletter";
+        let page = ledger
+            .resource_payments("publisher", resource, 2, 0)
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(
+            page.payments
+                .iter()
+                .map(|payment| payment.signature.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["b", "c"]
+        );
+
+        let last = ledger
+            .resource_payments("publisher", resource, 2, 2)
+            .unwrap();
+        assert_eq!(last.payments, vec![payment("a", 10)]);
+
+        let other_publisher = ledger.resource_payments("other", resource, 2, 0).unwrap();
+        assert_eq!(other_publisher.total, 0);
+    }
+}
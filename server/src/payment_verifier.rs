@@ -0,0 +1,1022 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use base64ct::{Base64, Encoding};
+use common::{
+    RpcClientError, RpcCommitment, RpcTransport, SolanaChain, SolanaRpcClient, X402ErrorCode,
+    X402ErrorResponse, X402PaymentPayload, X402PaymentResponse,
+};
+use solana_keypair::Keypair;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::instruction::TokenInstruction;
+
+use crate::{LedgerPayment, LedgerReservation, PaymentLedger, PaymentLedgerError, SERVER_CONFIG};
+
+/// Checks that the `X-PAYMENT` header of a request pays a resource and settles it onchain
+#[derive(Debug, Clone, Copy)]
+pub struct X402PaymentVerifier<'a> {
+    resource: &'a str,
+    chain: SolanaChain,
+    /// The mint address, or the system program for SOL
+    asset: &'a str,
+    decimals: Option<u8>,
+    max_amount_required: u64,
+    pay_to: &'a str,
+    fee_payer: Option<&'a Keypair>,
+    fee_payer_limits: FeePayerLimits,
+    confirmation_attempts: u32,
+    confirmation_interval: Duration,
+}
+
+impl<'a> X402PaymentVerifier<'a> {
+    /// How many times the signature status is polled after sending the transaction
+    pub const CONFIRMATION_ATTEMPTS: u32 = 30;
+    pub const CONFIRMATION_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(
+        resource: &'a str,
+        chain: SolanaChain,
+        asset: &'a str,
+        max_amount_required: u64,
+        pay_to: &'a str,
+    ) -> Self {
+        Self {
+            resource,
+            chain,
+            asset,
+            decimals: Option::None,
+            max_amount_required,
+            pay_to,
+            fee_payer: Option::None,
+            fee_payer_limits: FeePayerLimits::default(),
+            confirmation_attempts: Self::CONFIRMATION_ATTEMPTS,
+            confirmation_interval: Self::CONFIRMATION_INTERVAL,
+        }
+    }
+
+    /// Also checks the decimals of token transfers
+    pub fn set_decimals(&mut self, decimals: u8) -> &mut Self {
+        self.decimals.replace(decimals);
+
+        self
+    }
+
+    /// Co-signs transactions whose fee payer is the facilitator
+    pub fn set_fee_payer(&mut self, fee_payer: &'a Keypair) -> &mut Self {
+        self.fee_payer.replace(fee_payer);
+
+        self
+    }
+
+    /// The compute budget of the transactions co-signed as the fee payer
+    pub fn set_fee_payer_limits(&mut self, limits: FeePayerLimits) -> &mut Self {
+        self.fee_payer_limits = limits;
+
+        self
+    }
+
+    #[cfg(test)]
+    pub fn set_confirmation(&mut self, attempts: u32, interval: Duration) -> &mut Self {
+        self.confirmation_attempts = attempts;
+        self.confirmation_interval = interval;
+
+        self
+    }
+
+    /// Decodes the `X-PAYMENT` header and verifies its payload
+    pub fn verify(&self, header: &str) -> Result<X402VerifiedPayment, X402ErrorResponse> {
+        let payload = X402PaymentPayload::from_header(header)
+            .map_err(|error| self.invalid_payment(&error.to_string()))?;
+
+        self.verify_payload(&payload)
+    }
+
+    /// Checks that the transaction of the payment is signed and
+    /// transfers at least `max_amount_required` of the asset to `pay_to`
+    pub fn verify_payload(
+        &self,
+        payload: &X402PaymentPayload,
+    ) -> Result<X402VerifiedPayment, X402ErrorResponse> {
+        if payload.chain().ok() != Some(self.chain) {
+            return Err(X402ErrorResponse::new(
+                X402ErrorCode::UnsupportedNetwork,
+                self.resource,
+                &format!(
+                    "The payment is for `{}` but the resource requires `{}`",
+                    payload.network, self.chain
+                ),
+            ));
+        }
+
+        let mut transaction = payload
+            .transaction_bytes()
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<Transaction>(&bytes).ok())
+            .ok_or_else(|| self.invalid_payment("The payment transaction cannot be decoded"))?;
+
+        self.verify_signatures(&transaction)?;
+        let co_signed = self.co_sign(&mut transaction)?;
+
+        let signature = transaction
+            .signatures
+            .first()
+            .ok_or_else(|| self.invalid_payment("The payment transaction has no signatures"))?
+            .to_string();
+        let (payer, amount) = self.transferred(&transaction)?;
+
+        if amount < self.max_amount_required {
+            return Err(X402ErrorResponse::new(
+                X402ErrorCode::PaymentRejected,
+                self.resource,
+                &format!(
+                    "The payment transfers `{amount}` but the resource requires `{}`",
+                    self.max_amount_required
+                ),
+            ));
+        }
+
+        let transaction = if co_signed {
+            bincode::serialize(&transaction)
+                .map(|bytes| Base64::encode_string(&bytes))
+                .or(Err(self.internal_error(
+                    "Unable to serialize the co-signed transaction",
+                )))?
+        } else {
+            payload.payload.transaction.clone()
+        };
+
+        Ok(X402VerifiedPayment {
+            payer: payer.to_string(),
+            amount,
+            signature,
+            transaction,
+        })
+    }
+
+    /// Sends the verified transaction to the RPC of the chain and blocks until it is confirmed
+    pub fn settle(
+        &self,
+        payment: &X402VerifiedPayment,
+    ) -> Result<X402PaymentResponse, X402ErrorResponse> {
+        self.settle_with(
+            PaymentLedger::current(),
+            &SolanaRpcClient::new(SERVER_CONFIG.rpc_endpoint(self.chain)),
+            payment,
+        )
+    }
+
+    /// Settles the payment once. The transaction is reserved in the `ledger` while it is
+    /// sent so concurrent requests cannot replay it, and recorded once it is confirmed.
+    /// A transaction that was not confirmed in time stays reserved until a retry finds
+    /// its status.
+    pub fn settle_with<T: RpcTransport>(
+        &self,
+        ledger: &PaymentLedger,
+        client: &SolanaRpcClient<T>,
+        payment: &X402VerifiedPayment,
+    ) -> Result<X402PaymentResponse, X402ErrorResponse> {
+        let reservation = ledger
+            .reserve(&payment.signature)
+            .map_err(|error| self.ledger_error(error))?;
+
+        let confirmed = match reservation {
+            // The transaction may have landed since the previous attempt timed out
+            LedgerReservation::Unconfirmed => {
+                match self.confirm(client, payment, &payment.signature, 1) {
+                    Ok(Option::None) => self.send_and_confirm(client, payment),
+                    confirmed => confirmed,
+                }
+            }
+            LedgerReservation::New => self.send_and_confirm(client, payment),
+        };
+
+        let settled = match confirmed {
+            Ok(Some(settled)) => settled,
+            Ok(Option::None) => {
+                // The transaction can still land so the payment must not be sent again blindly
+                ledger.unconfirmed(&payment.signature).ok();
+
+                return Err(X402ErrorResponse::new(
+                    X402ErrorCode::SettlementFailed,
+                    self.resource,
+                    &format!(
+                        "The payment transaction `{}` was not confirmed in time",
+                        payment.signature
+                    ),
+                ));
+            }
+            Err(error) => {
+                // The transaction failed so the payment can be retried
+                ledger.release(&payment.signature).ok();
+
+                return Err(error);
+            }
+        };
+
+        let recorded = ledger.record(&LedgerPayment {
+            signature: payment.signature.clone(),
+            payer: payment.payer.clone(),
+            pay_to: self.pay_to.to_string(),
+            resource: self.resource.to_string(),
+            network: self.chain.x402_id().to_string(),
+            asset: self.asset.to_string(),
+            amount: payment.amount,
+            settled_at: PaymentLedger::now(),
+        });
+
+        // The payment is onchain so it is served, its reservation still blocks replays
+        if let Err(error) = recorded {
+            eprintln!(
+                "Unable to record the payment `{}` in the ledger. Error: {error}",
+                payment.signature
+            );
+        }
+
+        Ok(settled)
+    }
+
+    fn ledger_error(&self, error: PaymentLedgerError) -> X402ErrorResponse {
+        let code = match error {
+            PaymentLedgerError::AlreadyUsed(_) => X402ErrorCode::PaymentAlreadyUsed,
+            _ => X402ErrorCode::InternalError,
+        };
+
+        X402ErrorResponse::new(code, self.resource, &error.to_string())
+    }
+
+    /// Sends the transaction then polls its status. `None` if it was not confirmed in time
+    fn send_and_confirm<T: RpcTransport>(
+        &self,
+        client: &SolanaRpcClient<T>,
+        payment: &X402VerifiedPayment,
+    ) -> Result<Option<X402PaymentResponse>, X402ErrorResponse> {
+        let signature = client
+            .send_transaction(&payment.transaction, false)
+            .map_err(|error| match error {
+                RpcClientError::Rpc(error)
+                    if error.message.contains("already been processed")
+                        || error.message.contains("AlreadyProcessed") =>
+                {
+                    X402ErrorResponse::new(
+                        X402ErrorCode::PaymentAlreadyUsed,
+                        self.resource,
+                        "The payment transaction was already processed",
+                    )
+                }
+                RpcClientError::Rpc(error) => X402ErrorResponse::new(
+                    X402ErrorCode::SettlementFailed,
+                    self.resource,
+                    &error.to_string(),
+                ),
+                _ => X402ErrorResponse::new(
+                    X402ErrorCode::FacilitatorUnavailable,
+                    self.resource,
+                    &error.to_string(),
+                ),
+            })?;
+
+        self.confirm(client, payment, &signature, self.confirmation_attempts)
+    }
+
+    /// Polls the status of the transaction up to `attempts` times.
+    /// `None` if it was not confirmed in time
+    fn confirm<T: RpcTransport>(
+        &self,
+        client: &SolanaRpcClient<T>,
+        payment: &X402VerifiedPayment,
+        signature: &str,
+        attempts: u32,
+    ) -> Result<Option<X402PaymentResponse>, X402ErrorResponse> {
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                std::thread::sleep(self.confirmation_interval);
+            }
+
+            let status = client
+                .get_signature_statuses(&[signature], true)
+                .ok()
+                .and_then(|statuses| statuses.value.into_iter().next().flatten());
+
+            if let Some(status) = status {
+                if let Some(error) = status.err {
+                    return Err(X402ErrorResponse::new(
+                        X402ErrorCode::SettlementFailed,
+                        self.resource,
+                        &format!("The payment transaction failed. Error: {error}"),
+                    ));
+                }
+
+                if matches!(
+                    status.confirmation_status,
+                    Some(RpcCommitment::Confirmed | RpcCommitment::Finalized)
+                ) {
+                    return Ok(Some(X402PaymentResponse {
+                        success: true,
+                        transaction: signature.to_string(),
+                        network: self.chain.x402_id().to_string(),
+                        payer: payment.payer.clone(),
+                    }));
+                }
+            }
+        }
+
+        Ok(Option::None)
+    }
+
+    /// Checks the signatures of the transaction except the fee payer's when the facilitator
+    /// co-signs it afterwards
+    fn verify_signatures(&self, transaction: &Transaction) -> Result<(), X402ErrorResponse> {
+        let co_signs = self.fee_payer.is_some_and(|fee_payer| {
+            transaction.message.account_keys.first() == Some(&fee_payer.pubkey())
+        });
+
+        let signed = transaction.signatures.len()
+            == usize::from(transaction.message.header.num_required_signatures)
+            && transaction
+                .verify_with_results()
+                .iter()
+                .enumerate()
+                .all(|(index, verified)| *verified || (index == 0 && co_signs));
+
+        if !signed {
+            return Err(
+                self.invalid_payment("The payment transaction is not signed by all of its signers")
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Signs as the fee payer when it is the facilitator. Returns whether the transaction was signed
+    fn co_sign(&self, transaction: &mut Transaction) -> Result<bool, X402ErrorResponse> {
+        let Some(fee_payer) = self.fee_payer else {
+            return Ok(false);
+        };
+        let fee_payer_pubkey = fee_payer.pubkey();
+
+        if transaction.message.account_keys.first() != Some(&fee_payer_pubkey) {
+            return Ok(false);
+        }
+
+        // The facilitator only pays the fees, it must not be an account of any instruction
+        let used_by_instruction = transaction.message.instructions.iter().any(|instruction| {
+            instruction.program_id_index == 0 || instruction.accounts.contains(&0)
+        });
+
+        if used_by_instruction {
+            return Err(X402ErrorResponse::new(
+                X402ErrorCode::PaymentRejected,
+                self.resource,
+                "The fee payer of the facilitator cannot be an account of the payment instructions",
+            ));
+        }
+
+        self.check_sponsored(transaction)?;
+
+        let recent_blockhash = transaction.message.recent_blockhash;
+        transaction
+            .try_partial_sign(&[fee_payer], recent_blockhash)
+            .or(Err(self.internal_error(
+                "Unable to co-sign the payment transaction",
+            )))?;
+
+        Ok(true)
+    }
+
+    /// Checks that a transaction paid by the facilitator only has one transfer of the payment,
+    /// the idempotent creation of the token account of `pay_to` and a capped compute budget
+    fn check_sponsored(&self, transaction: &Transaction) -> Result<(), X402ErrorResponse> {
+        /// The limit of an instruction when the transaction does not set one
+        const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+        const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+        const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+        const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+        const CREATE_IDEMPOTENT: u8 = 1;
+
+        let rejected = |error: &str| {
+            X402ErrorResponse::new(X402ErrorCode::PaymentRejected, self.resource, error)
+        };
+        let (asset, pay_to) = self.asset_and_pay_to()?;
+
+        let account_keys = &transaction.message.account_keys;
+        let mut unit_limit = Option::<u32>::None;
+        let mut unit_price = Option::<u64>::None;
+        let mut creates_account = false;
+        let mut transfers = 0usize;
+
+        for instruction in &transaction.message.instructions {
+            let program_id = account_keys
+                .get(instruction.program_id_index as usize)
+                .ok_or_else(|| rejected("An instruction of the payment has no program"))?;
+            let accounts = instruction
+                .accounts
+                .iter()
+                .filter_map(|index| account_keys.get(*index as usize))
+                .collect::<Vec<&Pubkey>>();
+            let data = instruction.data.as_slice();
+
+            let allowed = if *program_id == solana_sdk_ids::compute_budget::ID {
+                match data {
+                    [SET_COMPUTE_UNIT_LIMIT, limit @ ..] if unit_limit.is_none() => {
+                        unit_limit = limit.try_into().ok().map(u32::from_le_bytes);
+                        unit_limit.is_some()
+                    }
+                    [SET_COMPUTE_UNIT_PRICE, price @ ..] if unit_price.is_none() => {
+                        unit_price = price.try_into().ok().map(u64::from_le_bytes);
+                        unit_price.is_some()
+                    }
+                    _ => false,
+                }
+            } else if *program_id == spl_associated_token_account::ID {
+                let creates_pay_to_account = match accounts.as_slice() {
+                    [_funder, account, wallet, mint, _system_program, token_program, ..] => {
+                        **wallet == pay_to
+                            && **mint == asset
+                            && **account
+                                == get_associated_token_address_with_program_id(
+                                    &pay_to,
+                                    &asset,
+                                    token_program,
+                                )
+                    }
+                    _ => false,
+                };
+
+                let allowed =
+                    !creates_account && data == [CREATE_IDEMPOTENT] && creates_pay_to_account;
+                creates_account = true;
+
+                allowed
+            } else {
+                transfers += 1;
+
+                self.transfer(program_id, &accounts, data, &asset, &pay_to)
+                    .is_some()
+            };
+
+            if !allowed {
+                return Err(rejected(
+                    "The facilitator only pays the fees of a payment with one transfer, \
+                    the creation of the token account of the recipient and a compute budget",
+                ));
+            }
+        }
+
+        if transfers != 1 {
+            return Err(rejected(
+                "The facilitator only pays the fees of a payment with one transfer",
+            ));
+        }
+
+        let instructions = transaction.message.instructions.len() as u32;
+        let budget_instructions = u32::from(unit_limit.is_some()) + u32::from(unit_price.is_some());
+        let unit_limit = unit_limit.unwrap_or(
+            (instructions - budget_instructions)
+                .saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT)
+                .min(MAX_COMPUTE_UNIT_LIMIT),
+        );
+
+        if unit_limit > self.fee_payer_limits.max_compute_unit_limit {
+            return Err(rejected(&format!(
+                "The compute unit limit `{unit_limit}` is above the `{}` the facilitator pays for",
+                self.fee_payer_limits.max_compute_unit_limit
+            )));
+        }
+
+        if unit_price.unwrap_or_default() > self.fee_payer_limits.max_compute_unit_price {
+            return Err(rejected(&format!(
+                "The compute unit price is above the `{}` micro-lamports the facilitator pays",
+                self.fee_payer_limits.max_compute_unit_price
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// The payer and the total amount of the asset the transaction sends to `pay_to`
+    fn transferred(&self, transaction: &Transaction) -> Result<(Pubkey, u64), X402ErrorResponse> {
+        let (asset, pay_to) = self.asset_and_pay_to()?;
+
+        let account_keys = &transaction.message.account_keys;
+        let mut payer = Option::<Pubkey>::None;
+        let mut total = 0u64;
+
+        for instruction in &transaction.message.instructions {
+            let Some(program_id) = account_keys.get(instruction.program_id_index as usize) else {
+                continue;
+            };
+            let accounts = instruction
+                .accounts
+                .iter()
+                .filter_map(|index| account_keys.get(*index as usize))
+                .collect::<Vec<&Pubkey>>();
+
+            let transfer = self.transfer(program_id, &accounts, &instruction.data, &asset, &pay_to);
+
+            if let Some((source, amount)) = transfer {
+                payer.get_or_insert(source);
+                total = total.saturating_add(amount);
+            }
+        }
+
+        payer.map(|payer| (payer, total)).ok_or_else(|| {
+            X402ErrorResponse::new(
+                X402ErrorCode::PaymentRejected,
+                self.resource,
+                "The payment transaction does not transfer the asset to the resource server",
+            )
+        })
+    }
+
+    fn asset_and_pay_to(&self) -> Result<(Pubkey, Pubkey), X402ErrorResponse> {
+        let asset = Pubkey::from_str(self.asset).or(Err(X402ErrorResponse::new(
+            X402ErrorCode::InvalidRequest,
+            self.resource,
+            "The asset is not a base58 address",
+        )))?;
+        let pay_to = Pubkey::from_str(self.pay_to).or(Err(X402ErrorResponse::new(
+            X402ErrorCode::InvalidRequest,
+            self.resource,
+            "The recipient is not a base58 address",
+        )))?;
+
+        Ok((asset, pay_to))
+    }
+
+    /// The source and the amount of an instruction that transfers the asset to `pay_to`
+    fn transfer(
+        &self,
+        program_id: &Pubkey,
+        accounts: &[&Pubkey],
+        data: &[u8],
+        asset: &Pubkey,
+        pay_to: &Pubkey,
+    ) -> Option<(Pubkey, u64)> {
+        if *asset == solana_system_interface::program::ID {
+            Self::sol_transfer(program_id, accounts, data, pay_to)
+        } else {
+            self.token_transfer(program_id, accounts, data, asset, pay_to)
+        }
+    }
+
+    /// A system program `Transfer` to `pay_to`, the data is the `u32` variant then the lamports
+    fn sol_transfer(
+        program_id: &Pubkey,
+        accounts: &[&Pubkey],
+        data: &[u8],
+        pay_to: &Pubkey,
+    ) -> Option<(Pubkey, u64)> {
+        const TRANSFER_VARIANT: u32 = 2;
+
+        if *program_id != solana_system_interface::program::ID || data.len() != 12 {
+            return None;
+        }
+
+        let variant = u32::from_le_bytes(data[..4].try_into().ok()?);
+        let lamports = u64::from_le_bytes(data[4..].try_into().ok()?);
+
+        match accounts {
+            [from, to, ..] if variant == TRANSFER_VARIANT && *to == pay_to => {
+                Some((**from, lamports))
+            }
+            _ => None,
+        }
+    }
+
+    /// A `TransferChecked` of the asset to the associated token account of `pay_to`
+    fn token_transfer(
+        &self,
+        program_id: &Pubkey,
+        accounts: &[&Pubkey],
+        data: &[u8],
+        asset: &Pubkey,
+        pay_to: &Pubkey,
+    ) -> Option<(Pubkey, u64)> {
+        if *program_id != spl_token::ID && *program_id != spl_token_2022::ID {
+            return None;
+        }
+
+        let TokenInstruction::TransferChecked { amount, decimals } =
+            TokenInstruction::unpack(data).ok()?
+        else {
+            return None;
+        };
+
+        let [_source, mint, destination, authority, ..] = accounts else {
+            return None;
+        };
+        let pay_to_ata = get_associated_token_address_with_program_id(pay_to, asset, program_id);
+
+        let decimals_match = self.decimals.is_none_or(|expected| expected == decimals);
+
+        (*mint == asset && **destination == pay_to_ata && decimals_match)
+            .then_some((**authority, amount))
+    }
+
+    fn invalid_payment(&self, error: &str) -> X402ErrorResponse {
+        let mut response =
+            X402ErrorResponse::new(X402ErrorCode::InvalidPayment, self.resource, error);
+        response.set_header(rusty_x402::X_PAYMENT_HEADER_KEY);
+
+        response
+    }
+
+    fn internal_error(&self, error: &str) -> X402ErrorResponse {
+        X402ErrorResponse::new(X402ErrorCode::InternalError, self.resource, error)
+    }
+}
+
+/// A payment whose transaction pays the resource but is not settled yet
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct X402VerifiedPayment {
+    pub payer: String,
+    pub amount: u64,
+    /// The base58 signature of the fee payer which identifies the transaction
+    pub signature: String,
+    /// The base64 encoded transaction
+    pub transaction: String,
+}
+
+/// The compute budget of the transactions the facilitator pays the fees of
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FeePayerLimits {
+    pub max_compute_unit_limit: u32,
+    /// In micro-lamports per compute unit
+    pub max_compute_unit_price: u64,
+}
+
+impl Default for FeePayerLimits {
+    /// At most 0.0004 SOL of priority fees for a payment
+    fn default() -> Self {
+        Self {
+            max_compute_unit_limit: 400_000,
+            max_compute_unit_price: 1_000_000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod payment_verifier_sanity {
+    use std::cell::Cell;
+
+    use common::RpcClientResult;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+
+    use super::*;
+
+    const RESOURCE: &str = "https://lagoon.markets/latest_newsletter";
+
+    /// A stand-in RPC which answers status checks with `status` and counts the sent transactions
+    struct StatusRpc {
+        status: serde_json::Value,
+        sent: Cell<u32>,
+        lands_once_sent: bool,
+    }
+
+    impl StatusRpc {
+        fn new(status: serde_json::Value) -> SolanaRpcClient<Self> {
+            SolanaRpcClient::with_transport(Self {
+                status,
+                sent: Cell::new(0),
+                lands_once_sent: false,
+            })
+        }
+
+        fn pending() -> SolanaRpcClient<Self> {
+            Self::new(serde_json::Value::Null)
+        }
+
+        fn confirmed() -> SolanaRpcClient<Self> {
+            Self::new(Self::confirmed_status())
+        }
+
+        /// Unknown until the transaction is sent again
+        fn lands_once_sent() -> SolanaRpcClient<Self> {
+            SolanaRpcClient::with_transport(Self {
+                status: serde_json::Value::Null,
+                sent: Cell::new(0),
+                lands_once_sent: true,
+            })
+        }
+
+        fn confirmed_status() -> serde_json::Value {
+            serde_json::json!({
+                "slot": 72,
+                "confirmations": 1,
+                "err": null,
+                "confirmationStatus": "confirmed"
+            })
+        }
+
+        fn failed() -> SolanaRpcClient<Self> {
+            Self::new(serde_json::json!({
+                "slot": 72,
+                "confirmations": 1,
+                "err": { "InstructionError": [0, "Custom"] },
+                "confirmationStatus": "confirmed"
+            }))
+        }
+    }
+
+    impl RpcTransport for StatusRpc {
+        fn send(&self, body: String) -> RpcClientResult<String> {
+            let request = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+
+            let result = match request["method"].as_str() {
+                Some("sendTransaction") => {
+                    self.sent.set(self.sent.get() + 1);
+
+                    serde_json::json!("signature")
+                }
+                _ => {
+                    let status = if self.lands_once_sent && self.sent.get() > 0 {
+                        Self::confirmed_status()
+                    } else {
+                        self.status.clone()
+                    };
+
+                    serde_json::json!({ "context": { "slot": 82 }, "value": [status] })
+                }
+            };
+
+            Ok(
+                serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+                    .to_string(),
+            )
+        }
+    }
+
+    fn payment() -> X402VerifiedPayment {
+        X402VerifiedPayment {
+            payer: "payer".to_string(),
+            amount: 1000,
+            signature: "signature".to_string(),
+            transaction: "transaction".to_string(),
+        }
+    }
+
+    fn verifier(pay_to: &str) -> X402PaymentVerifier<'_> {
+        let mut verifier = X402PaymentVerifier::new(
+            RESOURCE,
+            SolanaChain::Devnet,
+            "11111111111111111111111111111111",
+            1000,
+            pay_to,
+        );
+        verifier.set_confirmation(2, Duration::ZERO);
+
+        verifier
+    }
+
+    fn sponsored<'a>(
+        verifier: &X402PaymentVerifier<'a>,
+        payer: &Keypair,
+        fee_payer: &'a Keypair,
+        instructions: &[Instruction],
+    ) -> Result<X402VerifiedPayment, X402ErrorResponse> {
+        let message = Message::new(instructions, Some(&fee_payer.pubkey()));
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction.partial_sign(&[payer], Hash::default());
+
+        let encoded = Base64::encode_string(&bincode::serialize(&transaction).unwrap());
+
+        let mut verifier = *verifier;
+        verifier
+            .set_fee_payer(fee_payer)
+            .verify_payload(&X402PaymentPayload::new(SolanaChain::Devnet, &encoded))
+    }
+
+    fn rejected(outcome: Result<X402VerifiedPayment, X402ErrorResponse>) -> bool {
+        outcome.is_err_and(|error| error.code == X402ErrorCode::PaymentRejected)
+    }
+
+    #[test]
+    fn test_sponsored_rejections() {
+        let (payer, fee_payer, pay_to) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let pay_to_address = pay_to.pubkey().to_string();
+        let verifier = verifier(&pay_to_address);
+
+        let transfer =
+            solana_system_interface::instruction::transfer(&payer.pubkey(), &pay_to.pubkey(), 1000);
+        let budget = |variant: u8, value: &[u8]| {
+            Instruction::new_with_bytes(
+                solana_sdk_ids::compute_budget::ID,
+                &[&[variant], value].concat(),
+                Vec::new(),
+            )
+        };
+
+        assert!(sponsored(&verifier, &payer, &fee_payer, &[transfer.clone()]).is_ok());
+
+        // The fee payer cannot be moved by the payment or be its program
+        let drains_fee_payer = solana_system_interface::instruction::transfer(
+            &fee_payer.pubkey(),
+            &pay_to.pubkey(),
+            1000,
+        );
+        assert!(rejected(sponsored(
+            &verifier,
+            &fee_payer,
+            &fee_payer,
+            &[drains_fee_payer]
+        )));
+        let fee_payer_program = Instruction::new_with_bytes(fee_payer.pubkey(), &[], Vec::new());
+        assert!(rejected(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[fee_payer_program, transfer.clone()]
+        )));
+
+        // Only the compute budget, the token account of `pay_to` and the transfer are paid for
+        let unknown_program =
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[1, 2, 3], Vec::new());
+        assert!(rejected(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[unknown_program, transfer.clone()]
+        )));
+        assert!(rejected(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[
+                budget(2, &1000u32.to_le_bytes()),
+                budget(2, &1000u32.to_le_bytes()),
+                transfer.clone()
+            ]
+        )));
+        assert!(rejected(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[budget(3, &[1, 0]), transfer.clone()]
+        )));
+        assert!(rejected(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[budget(1, &[]), transfer.clone()]
+        )));
+        assert!(rejected(sponsored(
+            &verifier,
+            &fee_payer,
+            &fee_payer,
+            &[budget(2, &1000u32.to_le_bytes())]
+        )));
+    }
+
+    #[test]
+    fn test_sponsored_token_account() {
+        let (payer, fee_payer, pay_to) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let pay_to_address = pay_to.pubkey().to_string();
+        let mint = Pubkey::new_unique();
+        let mint_address = mint.to_string();
+        let mut verifier = verifier(&pay_to_address);
+        verifier.asset = &mint_address;
+
+        let token_program = spl_token_2022::ID;
+        let create_account = |wallet: &Pubkey, data: u8| {
+            Instruction::new_with_bytes(
+                spl_associated_token_account::ID,
+                &[data],
+                vec![
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new(
+                        get_associated_token_address_with_program_id(wallet, &mint, &token_program),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(*wallet, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(solana_system_interface::program::ID, false),
+                    AccountMeta::new_readonly(token_program, false),
+                ],
+            )
+        };
+        let transfer = spl_token_2022::instruction::transfer_checked(
+            &token_program,
+            &get_associated_token_address_with_program_id(&payer.pubkey(), &mint, &token_program),
+            &mint,
+            &get_associated_token_address_with_program_id(&pay_to.pubkey(), &mint, &token_program),
+            &payer.pubkey(),
+            &[],
+            1000,
+            6,
+        )
+        .unwrap();
+
+        assert!(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[create_account(&pay_to.pubkey(), 1), transfer.clone()]
+        )
+        .is_ok());
+
+        // Only the idempotent creation of the token account of `pay_to`, once
+        assert!(rejected(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[create_account(&Pubkey::new_unique(), 1), transfer.clone()]
+        )));
+        assert!(rejected(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[create_account(&pay_to.pubkey(), 0), transfer.clone()]
+        )));
+        assert!(rejected(sponsored(
+            &verifier,
+            &payer,
+            &fee_payer,
+            &[
+                create_account(&pay_to.pubkey(), 1),
+                create_account(&pay_to.pubkey(), 1),
+                transfer
+            ]
+        )));
+    }
+
+    #[test]
+    fn test_settle_timeout() {
+        let pay_to = Keypair::new().pubkey().to_string();
+        let verifier = verifier(&pay_to);
+        let ledger = PaymentLedger::in_memory().unwrap();
+
+        let pending = StatusRpc::pending();
+        let timed_out = verifier.settle_with(&ledger, &pending, &payment());
+        assert_eq!(
+            timed_out.map_err(|error| error.code),
+            Err(X402ErrorCode::SettlementFailed)
+        );
+        assert_eq!(
+            ledger.reserve("signature"),
+            Ok(LedgerReservation::Unconfirmed)
+        );
+        ledger.unconfirmed("signature").unwrap();
+
+        // The retry finds the transaction that landed after the timeout without resending it
+        let landed = StatusRpc::confirmed();
+        let settled = verifier.settle_with(&ledger, &landed, &payment()).unwrap();
+        assert_eq!(settled.transaction, "signature");
+        assert_eq!(landed.transport().sent.get(), 0);
+        assert_eq!(
+            ledger
+                .resource_payments(&pay_to, RESOURCE, 10, 0)
+                .unwrap()
+                .total,
+            1
+        );
+
+        assert_eq!(
+            verifier
+                .settle_with(&ledger, &StatusRpc::confirmed(), &payment())
+                .map_err(|error| error.code),
+            Err(X402ErrorCode::PaymentAlreadyUsed)
+        );
+    }
+
+    #[test]
+    fn test_settle_release() {
+        let pay_to = Keypair::new().pubkey().to_string();
+        let verifier = verifier(&pay_to);
+        let ledger = PaymentLedger::in_memory().unwrap();
+
+        // A failed transaction releases its reservation so the payment can be retried
+        assert_eq!(
+            verifier
+                .settle_with(&ledger, &StatusRpc::failed(), &payment())
+                .map_err(|error| error.code),
+            Err(X402ErrorCode::SettlementFailed)
+        );
+        assert_eq!(ledger.reserve("signature"), Ok(LedgerReservation::New));
+        ledger.release("signature").unwrap();
+
+        // An unconfirmed transaction that failed afterwards is released by the retry
+        verifier
+            .settle_with(&ledger, &StatusRpc::pending(), &payment())
+            .unwrap_err();
+        let failed = StatusRpc::failed();
+        verifier
+            .settle_with(&ledger, &failed, &payment())
+            .unwrap_err();
+        assert_eq!(failed.transport().sent.get(), 0);
+        assert_eq!(ledger.reserve("signature"), Ok(LedgerReservation::New));
+        ledger.release("signature").unwrap();
+
+        // An unconfirmed transaction that is still unknown is sent again
+        verifier
+            .settle_with(&ledger, &StatusRpc::pending(), &payment())
+            .unwrap_err();
+        let resent = StatusRpc::lands_once_sent();
+        verifier.settle_with(&ledger, &resent, &payment()).unwrap();
+        assert_eq!(resent.transport().sent.get(), 1);
+    }
+}
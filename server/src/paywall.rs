@@ -0,0 +1,572 @@
+use std::marker::PhantomData;
+
+use common::{
+    CommonHeaders, SolanaChain, X402AccessToken, X402AccessTokenError, X402AccessTokenSigner,
+    X402ClientProof, X402ErrorCode, X402ErrorResponse, X402PaymentResponse,
+};
+use rocket::fairing::AdHoc;
+use rocket::http::{ContentType, Header, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder, Response};
+use rusty_x402::{
+    PaymentRequestExtras, PaymentRequirementsBuilder, PaymentRequirementsResponse,
+    X_PAYMENT_HEADER_KEY,
+};
+
+use crate::{
+    CatalogHandler, CatalogMetering, CatalogPaymentOption, CatalogResource, MeteredStream,
+    PaymentLedger, ResourceCatalog, StreamMeter, X402Client, X402Error, X402PaymentVerifier,
+    SERVER_CONFIG,
+};
+
+static ACCESS_TOKEN_SIGNER: once_cell::sync::Lazy<X402AccessTokenSigner> =
+    once_cell::sync::Lazy::new(|| X402AccessTokenSigner::new(SERVER_CONFIG.access_token_secret()));
+
+/// A resource of the catalog that a route requires payment for with [Paid]
+pub trait PaidResource: Send + Sync + 'static {
+    const HANDLER: CatalogHandler;
+}
+
+/// The `latest_newsletter` resource of the catalog
+pub enum LatestNewsletter {}
+
+impl PaidResource for LatestNewsletter {
+    const HANDLER: CatalogHandler = CatalogHandler::LatestNewsletter;
+}
+
+/// The `voting` live updates stream of the catalog
+pub enum Voting {}
+
+impl PaidResource for Voting {
+    const HANDLER: CatalogHandler = CatalogHandler::Voting;
+}
+
+/// Request guard of a route that requires a payment for `R`.
+///
+/// Requests without an `X-PAYMENT` header are answered with 402 and the payment requirements
+/// of `R` in the catalog. A payment is verified and settled before the route runs and
+/// [X402Paywall::fairing] sends its `X-PAYMENT-RESPONSE` header, with an `X402-Access-Token`
+/// when the resource has `access_seconds`. Requests with an unexpired access token for `R`
+/// in the header are not paid again, with the client proof of the payer the token was issued to.
+/// [PaidStream] also reads the token of the `access_token` query parameter without a client
+/// proof, for [Paid::QUERY_TOKEN_SECS] after its payment.
+pub struct Paid<R: PaidResource> {
+    /// The payment settled by this request, `None` when it was authorized by an access token
+    pub payment: Option<X402PaymentResponse>,
+    /// The access token the request was authorized with, or the one issued for its payment
+    pub access_token: Option<X402AccessToken>,
+    resource: PhantomData<R>,
+}
+
+impl<R: PaidResource> Paid<R> {
+    /// How long after its payment an access token of the query parameter is accepted, since
+    /// anyone who sees the URL can replay it
+    pub const QUERY_TOKEN_SECS: u64 = 60;
+
+    /// Also accepts the access token of the query parameter when `query_token` is set
+    fn guard(req: &Request<'_>, query_token: bool) -> Outcome<Self, X402PaywallError> {
+        let catalog = ResourceCatalog::current();
+        // The resource was removed from the catalog after the server started
+        let Some(resource) = catalog.get(R::HANDLER) else {
+            return Outcome::Forward(Status::NotFound);
+        };
+
+        match Self::from_headers(req, resource, query_token) {
+            Ok(paid) => {
+                // Only a settled payment issues an access token
+                if let Some(payment) = paid.payment.as_ref() {
+                    req.local_cache(|| Some(payment.clone()));
+                    req.local_cache(|| paid.access_token.clone());
+                }
+
+                Outcome::Success(paid)
+            }
+            Err(error) => {
+                // Cached for the catchers which only receive the status
+                let status = match &error {
+                    X402PaywallError::PaymentRequired(required) => {
+                        req.local_cache(|| Some(required.clone()));
+                        Status::PaymentRequired
+                    }
+                    X402PaywallError::Rejected(error) => {
+                        req.local_cache(|| Some(error.clone()));
+                        Status::from_code(error.status).unwrap_or(Status::InternalServerError)
+                    }
+                };
+
+                Outcome::Error((status, error))
+            }
+        }
+    }
+
+    fn from_headers(
+        req: &Request<'_>,
+        resource: &CatalogResource,
+        query_token: bool,
+    ) -> Result<Self, X402PaywallError> {
+        let x_payment_header = req.headers().get_one(X_PAYMENT_HEADER_KEY);
+
+        // A new payment takes precedence over an access token that may have expired
+        if let (None, Some((token, in_query))) =
+            (x_payment_header, Self::access_token(req, query_token))
+        {
+            let client = Self::token_client(req, &resource.resource, in_query)?;
+
+            return Self::from_access_token(
+                &ACCESS_TOKEN_SIGNER,
+                token,
+                client.as_deref(),
+                &resource.resource,
+                PaymentLedger::now(),
+            );
+        }
+
+        let client = X402Client::from_headers(req, &resource.resource)?;
+
+        let Some(chain) = req
+            .headers()
+            .get_one(CommonHeaders::X402_CHAIN_HEADER)
+            .and_then(|chain| chain.parse::<SolanaChain>().ok())
+        else {
+            return Err(X402ErrorResponse::missing_header(
+                &resource.resource,
+                CommonHeaders::X402_CHAIN_HEADER,
+                "the chain identification in x402 format",
+            )
+            .into());
+        };
+
+        let Some(payment_option) = resource.payment_option(chain) else {
+            return Err(X402ErrorResponse::new(
+                X402ErrorCode::UnsupportedNetwork,
+                &resource.resource,
+                &format!("The `{chain}` network is not supported"),
+            )
+            .into());
+        };
+
+        let Some(x_payment_header) = x_payment_header else {
+            let fee_payer = if SERVER_CONFIG.client_is_facilitator() {
+                client.address.as_str()
+            } else {
+                SERVER_CONFIG.facilitator_address().unwrap().as_str()
+            };
+
+            return Err(X402PaymentRequired::new(fee_payer, resource, payment_option)?.into());
+        };
+
+        let mut verifier = X402PaymentVerifier::new(
+            &resource.resource,
+            chain,
+            &payment_option.asset,
+            payment_option.max_amount_required,
+            resource.pay_to(),
+        );
+        verifier.set_decimals(payment_option.decimals);
+        if let Some(facilitator) = SERVER_CONFIG.facilitator_keypair() {
+            verifier
+                .set_fee_payer(facilitator)
+                .set_fee_payer_limits(SERVER_CONFIG.fee_payer_limits());
+        }
+
+        // Confirming the transaction polls the RPC for several seconds
+        let payment = verifier.verify(x_payment_header).and_then(|payment| {
+            rocket::tokio::task::block_in_place(|| verifier.settle(&payment))
+        })?;
+
+        let access_token = resource.access_seconds.map(|access_seconds| {
+            X402AccessToken::new(
+                &resource.resource,
+                &payment.payer,
+                &payment.transaction,
+                PaymentLedger::now(),
+                access_seconds,
+            )
+        });
+
+        Ok(Self {
+            payment: Some(payment),
+            access_token,
+            resource: PhantomData,
+        })
+    }
+
+    /// The access token of the header, or of the query parameter when `query_token` is set,
+    /// and whether it was read from the query. Only streams read it from the query, for
+    /// `EventSource` clients which cannot set headers, since URLs end up in logs.
+    fn access_token<'a>(req: &'a Request<'_>, query_token: bool) -> Option<(&'a str, bool)> {
+        if let Some(token) = req
+            .headers()
+            .get_one(CommonHeaders::X402_ACCESS_TOKEN_HEADER)
+        {
+            return Some((token, false));
+        }
+
+        query_token
+            .then(|| req.query_value::<&str>(X402AccessToken::QUERY_PARAM))
+            .flatten()
+            .and_then(|token| token.ok())
+            .map(|token| (token, true))
+    }
+
+    /// The address of the client proof an access token must have been issued to.
+    /// `EventSource` clients send the client proof of the query parameters, if any, with the
+    /// token of the query parameter.
+    fn token_client(
+        req: &Request<'_>,
+        resource: &str,
+        in_query: bool,
+    ) -> Result<Option<String>, X402ErrorResponse> {
+        match req
+            .headers()
+            .get_one(CommonHeaders::X402_CLIENT_PROOF_HEADER)
+        {
+            Some(_) => X402Client::from_headers(req, resource).map(|client| Some(client.address)),
+            None if in_query
+                && req
+                    .query_value::<&str>(X402ClientProof::QUERY_PARAM)
+                    .is_some() =>
+            {
+                X402Client::from_query(req, resource).map(|client| Some(client.address))
+            }
+            None if in_query => Ok(Option::None),
+            None => {
+                let mut response = X402ErrorResponse::new(
+                    X402ErrorCode::InvalidClientProof,
+                    resource,
+                    "An access token is only accepted with the client proof of its payer",
+                );
+                response.set_header(CommonHeaders::X402_CLIENT_PROOF_HEADER);
+
+                Err(response)
+            }
+        }
+    }
+
+    /// Verifies the token at `now`. It must have been issued to the `client`, or without one
+    /// be used within [Self::QUERY_TOKEN_SECS] of its payment.
+    fn from_access_token(
+        signer: &X402AccessTokenSigner,
+        token: &str,
+        client: Option<&str>,
+        resource: &str,
+        now: u64,
+    ) -> Result<Self, X402PaywallError> {
+        let rejected = |code: X402ErrorCode, error: &str| {
+            let mut response = X402ErrorResponse::new(code, resource, error);
+            response.set_header(CommonHeaders::X402_ACCESS_TOKEN_HEADER);
+
+            response
+        };
+
+        let access_token = signer.verify(token, resource, now).map_err(|error| {
+            let code = match error {
+                X402AccessTokenError::Expired => X402ErrorCode::PaymentExpired,
+                _ => X402ErrorCode::PaymentRejected,
+            };
+
+            rejected(code, &error.to_string())
+        })?;
+
+        match client {
+            Some(client) if client != access_token.payer => {
+                return Err(rejected(
+                    X402ErrorCode::PaymentRejected,
+                    "The access token was issued to another client",
+                )
+                .into());
+            }
+            None if now.saturating_sub(access_token.issued_at) > Self::QUERY_TOKEN_SECS => {
+                return Err(rejected(
+                    X402ErrorCode::PaymentExpired,
+                    &format!(
+                        "A token without a client proof expires `{}` seconds after its payment",
+                        Self::QUERY_TOKEN_SECS
+                    ),
+                )
+                .into());
+            }
+            _ => {}
+        }
+
+        Ok(Self {
+            payment: Option::None,
+            access_token: Some(access_token),
+            resource: PhantomData,
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, R: PaidResource> FromRequest<'r> for Paid<R> {
+    type Error = X402PaywallError;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Self::guard(req, false)
+    }
+}
+
+/// Request guard of a stream of `R`, paid up front with [Paid] or metered when `R` has
+/// `metering` in the catalog. A metered stream opens without payment for a client with a proof,
+/// of the headers or the query parameters of `EventSource` clients, and is paid for with top-ups
+/// while it streams. A client has at most [StreamMeter::MAX_STREAMS_PER_CLIENT] open streams.
+/// An access token of a metered resource credits a top-up of a stream that closed, so the
+/// stream it opens is [PaidStream::Paid] until the token expires.
+pub enum PaidStream<R: PaidResource> {
+    Paid(Paid<R>),
+    Metered {
+        stream: MeteredStream,
+        resource: CatalogResource,
+    },
+}
+
+#[rocket::async_trait]
+impl<'r, R: PaidResource> FromRequest<'r> for PaidStream<R> {
+    type Error = X402PaywallError;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let catalog = ResourceCatalog::current();
+        let Some(resource) = catalog.get(R::HANDLER) else {
+            return Outcome::Forward(Status::NotFound);
+        };
+
+        let metering = resource
+            .metering
+            .filter(|_| Paid::<R>::access_token(req, true).is_none());
+        let Some(metering) = metering else {
+            return Paid::<R>::guard(req, true).map(Self::Paid);
+        };
+
+        match Self::open_metered(req, resource, metering) {
+            Ok(stream) => Outcome::Success(Self::Metered {
+                stream,
+                resource: resource.clone(),
+            }),
+            Err(error) => {
+                // Cached for the catchers which only receive the status
+                req.local_cache(|| Some(error.clone()));
+                let status = Status::from_code(error.status).unwrap_or(Status::InternalServerError);
+
+                Outcome::Error((status, error.into()))
+            }
+        }
+    }
+}
+
+impl<R: PaidResource> PaidStream<R> {
+    fn open_metered(
+        req: &Request<'_>,
+        resource: &CatalogResource,
+        metering: CatalogMetering,
+    ) -> Result<MeteredStream, X402ErrorResponse> {
+        let client = match req
+            .headers()
+            .get_one(CommonHeaders::X402_CLIENT_PROOF_HEADER)
+        {
+            Some(_) => X402Client::from_headers(req, &resource.resource)?,
+            None => X402Client::from_query(req, &resource.resource)?,
+        };
+
+        StreamMeter::open(metering, &client.address).ok_or_else(|| {
+            X402ErrorResponse::new(
+                X402ErrorCode::InvalidRequest,
+                &resource.resource,
+                &format!(
+                    "A client can only have `{}` open metered streams",
+                    StreamMeter::MAX_STREAMS_PER_CLIENT
+                ),
+            )
+        })
+    }
+}
+
+/// Why a [Paid] guard did not let the request through
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum X402PaywallError {
+    /// The request has no payment, the client is sent the payment requirements
+    PaymentRequired(X402PaymentRequired),
+    /// The request or its payment is invalid
+    Rejected(X402ErrorResponse),
+}
+
+impl From<X402PaymentRequired> for X402PaywallError {
+    fn from(value: X402PaymentRequired) -> Self {
+        Self::PaymentRequired(value)
+    }
+}
+
+impl From<X402ErrorResponse> for X402PaywallError {
+    fn from(value: X402ErrorResponse) -> Self {
+        Self::Rejected(value)
+    }
+}
+
+/// The 402 response with the serialized [PaymentRequirementsResponse] of a resource
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct X402PaymentRequired(pub String);
+
+impl X402PaymentRequired {
+    pub fn new(
+        fee_payer: &str,
+        resource: &CatalogResource,
+        payment_option: &CatalogPaymentOption,
+    ) -> Result<Self, X402ErrorResponse> {
+        let mut extra = PaymentRequestExtras::new(fee_payer);
+        if !payment_option.is_sol() {
+            extra = extra.set_decimals(payment_option.decimals);
+        }
+        if !payment_option.is_sol() && !payment_option.token_2022 {
+            extra = extra.set_legacy_token_mint();
+        }
+
+        let mut requirement = PaymentRequirementsBuilder::new();
+        requirement
+            .set_amount(payment_option.max_amount_required)
+            .set_asset(&payment_option.asset)
+            .set_description(&payment_option.description)
+            .set_recipient(resource.pay_to())
+            .set_mime_as_json()
+            .set_max_timeout_seconds(resource.max_timeout())
+            .set_resource(&resource.resource)
+            .set_extra(extra);
+
+        let internal_error = |error: &str| {
+            X402ErrorResponse::new(X402ErrorCode::InternalError, &resource.resource, error)
+        };
+
+        let requirement = requirement.build().map_err(|error| {
+            internal_error(
+                &(String::from("Unable to build the payment requirements. Error: ")
+                    + error.to_string().as_str()),
+            )
+        })?;
+
+        let mut body = PaymentRequirementsResponse::new();
+        body.add_payment_requirement(requirement);
+
+        body.to_json()
+            .map(|requirements| Self(requirements.to_string()))
+            .or(Err(internal_error(
+                "Unable to serialize the payment requirements",
+            )))
+    }
+}
+
+impl<'r> Responder<'r, 'static> for X402PaymentRequired {
+    fn respond_to(self, _req: &'r Request<'_>) -> response::Result<'static> {
+        Response::build()
+            .sized_body(self.0.len(), std::io::Cursor::new(self.0))
+            .header(ContentType::JSON)
+            .status(Status::PaymentRequired)
+            .ok()
+    }
+}
+
+/// The fairing and catcher that complete the responses of [Paid] routes
+pub struct X402Paywall;
+
+impl X402Paywall {
+    /// Adds the `X-PAYMENT-RESPONSE` header of a settled payment to the response,
+    /// and the `X402-Access-Token` issued for it
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_response("X-PAYMENT-RESPONSE header", |req, res| {
+            Box::pin(async move {
+                if let Some(payment) = req.local_cache(|| Option::<X402PaymentResponse>::None) {
+                    res.set_raw_header(
+                        CommonHeaders::X402_PAYMENT_RESPONSE_HEADER,
+                        payment.to_header(),
+                    );
+                }
+
+                if let Some(access_token) = req.local_cache(|| Option::<X402AccessToken>::None) {
+                    res.set_header(Self::access_token_header(access_token));
+                }
+            })
+        })
+    }
+
+    /// The `X402-Access-Token` header of a signed `access_token`
+    pub fn access_token_header(access_token: &X402AccessToken) -> Header<'static> {
+        Header::new(
+            CommonHeaders::X402_ACCESS_TOKEN_HEADER,
+            ACCESS_TOKEN_SIGNER.sign(access_token),
+        )
+    }
+}
+
+/// Renders the payment requirements of a [Paid] guard, or the error of a rejected payment
+#[catch(402)]
+pub fn x402_payment_required(req: &Request<'_>) -> Result<X402PaymentRequired, X402Error> {
+    match req.local_cache(|| Option::<X402PaymentRequired>::None) {
+        Some(required) => Ok(required.clone()),
+        None => Err(crate::cached_error(
+            req,
+            X402ErrorCode::PaymentRejected,
+            "Payment required",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod paywall_sanity {
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+
+    use super::*;
+
+    const NOW: u64 = 1_760_000_000;
+    const RESOURCE: &str = "https://lagoon.markets/latest_newsletter";
+
+    fn code(outcome: Result<Paid<LatestNewsletter>, X402PaywallError>) -> Option<X402ErrorCode> {
+        match outcome {
+            Err(X402PaywallError::Rejected(error)) => Some(error.code),
+            _ => Option::None,
+        }
+    }
+
+    #[test]
+    fn test_access_token_client() {
+        let signer = X402AccessTokenSigner::new(&[7u8; 32]);
+        let token = signer.sign(&X402AccessToken::new(RESOURCE, "payer", "tx", NOW, 3600));
+        let from_access_token = |client: Option<&str>, now: u64| {
+            Paid::<LatestNewsletter>::from_access_token(&signer, &token, client, RESOURCE, now)
+        };
+
+        assert!(from_access_token(Some("payer"), NOW + 600).is_ok());
+        assert_eq!(
+            code(from_access_token(Some("other"), NOW + 600)),
+            Some(X402ErrorCode::PaymentRejected)
+        );
+
+        // A token of the query parameter is only accepted shortly after its payment
+        let query_token_secs = Paid::<LatestNewsletter>::QUERY_TOKEN_SECS;
+        assert!(from_access_token(Option::None, NOW + query_token_secs).is_ok());
+        assert_eq!(
+            code(from_access_token(Option::None, NOW + query_token_secs + 1)),
+            Some(X402ErrorCode::PaymentExpired)
+        );
+    }
+
+    #[test]
+    fn test_missing_client_proof() {
+        let client = Client::untracked(rocket::build()).unwrap();
+        let request = client.get("/latest_newsletter").header(Header::new(
+            CommonHeaders::X402_ACCESS_TOKEN_HEADER,
+            "token",
+        ));
+
+        let missing_proof = Paid::<LatestNewsletter>::token_client(&request, RESOURCE, false);
+        assert_eq!(
+            missing_proof
+                .as_ref()
+                .map_err(|error| (error.code, error.status)),
+            Err((X402ErrorCode::InvalidClientProof, 401))
+        );
+
+        // `EventSource` clients send the token of the query parameter without a proof
+        assert_eq!(
+            Paid::<LatestNewsletter>::token_client(&request, RESOURCE, true),
+            Ok(Option::None)
+        );
+    }
+}
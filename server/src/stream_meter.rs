@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use common::{X402ErrorCode, X402ErrorResponse};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::tokio::sync::Notify;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+use crate::CatalogMetering;
+
+/// The open metered streams keyed by their id
+static STREAM_METERS: once_cell::sync::Lazy<Mutex<HashMap<String, Arc<StreamMeter>>>> =
+    once_cell::sync::Lazy::new(Mutex::default);
+
+/// The allowance of a metered stream that its top-ups pay for
+#[derive(Debug)]
+pub struct StreamMeter {
+    /// A random id, only the client of the stream can top it up
+    pub id: String,
+    /// The address of the client that opened the stream
+    pub owner: String,
+    pub metering: CatalogMetering,
+    allowance: Mutex<StreamAllowance>,
+    top_ups: Notify,
+    /// The top-ups whose payments are settling, see [TopUpStream]
+    pending_top_ups: AtomicUsize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+struct StreamAllowance {
+    events: u64,
+    /// Unix timestamp in seconds
+    paid_until: u64,
+    /// The transaction of the last top-up
+    transaction: Option<String>,
+}
+
+impl StreamMeter {
+    /// How many metered streams a client can have open at once
+    pub const MAX_STREAMS_PER_CLIENT: usize = 3;
+
+    /// Registers a stream of the `owner` client without allowance, closed when the
+    /// [MeteredStream] is dropped. `None` if the client has too many open streams.
+    pub fn open(metering: CatalogMetering, owner: &str) -> Option<MeteredStream> {
+        let mut meters = Self::meters();
+
+        let open_streams = meters.values().filter(|meter| meter.owner == owner).count();
+        if open_streams >= Self::MAX_STREAMS_PER_CLIENT {
+            return None;
+        }
+
+        let meter = Arc::new(Self::new(
+            &Keypair::new().pubkey().to_string(),
+            owner,
+            metering,
+        ));
+        meters.insert(meter.id.clone(), meter.clone());
+
+        Some(MeteredStream(meter))
+    }
+
+    /// The open stream with `id`
+    pub fn get(id: &str) -> Option<Arc<Self>> {
+        Self::meters().get(id).cloned()
+    }
+
+    fn new(id: &str, owner: &str, metering: CatalogMetering) -> Self {
+        Self {
+            id: id.to_string(),
+            owner: owner.to_string(),
+            metering,
+            allowance: Mutex::default(),
+            top_ups: Notify::new(),
+            pending_top_ups: AtomicUsize::new(0),
+        }
+    }
+
+    fn meters() -> MutexGuard<'static, HashMap<String, Arc<StreamMeter>>> {
+        STREAM_METERS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn allowance(&self) -> MutexGuard<'_, StreamAllowance> {
+        self.allowance
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Whether the stream can send an event at `now`
+    pub fn has_allowance(&self, now: u64) -> bool {
+        let allowance = self.allowance();
+
+        self.metering.events.is_none_or(|_| allowance.events > 0)
+            && self
+                .metering
+                .seconds
+                .is_none_or(|_| now < allowance.paid_until)
+    }
+
+    /// Whether a top-up started and its payment is still settling
+    pub fn has_pending_top_up(&self) -> bool {
+        self.pending_top_ups.load(Ordering::SeqCst) > 0
+    }
+
+    /// Counts a sent event against the allowance
+    pub fn consume_event(&self) {
+        if self.metering.events.is_some() {
+            let mut allowance = self.allowance();
+            allowance.events = allowance.events.saturating_sub(1);
+        }
+    }
+
+    /// Adds the allowance of a settled payment and resumes the stream
+    pub fn top_up(&self, transaction: &str, now: u64) {
+        {
+            let mut allowance = self.allowance();
+
+            if let Some(events) = self.metering.events {
+                allowance.events = allowance.events.saturating_add(events);
+            }
+
+            if let Some(seconds) = self.metering.seconds {
+                allowance.paid_until = allowance.paid_until.max(now).saturating_add(seconds);
+            }
+
+            allowance.transaction = Some(transaction.to_string());
+        }
+
+        self.top_ups.notify_waiters();
+    }
+
+    /// Waits until a top-up gives allowance at the time returned by `now`,
+    /// and returns the transaction of the top-up
+    pub async fn wait_for_top_up(&self, now: impl Fn() -> u64) -> Option<String> {
+        loop {
+            // Created before checking so a top-up in between is not missed
+            let top_up = self.top_ups.notified();
+
+            if self.has_allowance(now()) {
+                return self.allowance().transaction.clone();
+            }
+
+            top_up.await;
+        }
+    }
+}
+
+/// An open metered stream, closed when dropped with the stream
+#[derive(Debug)]
+pub struct MeteredStream(Arc<StreamMeter>);
+
+impl std::ops::Deref for MeteredStream {
+    type Target = StreamMeter;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for MeteredStream {
+    fn drop(&mut self) {
+        StreamMeter::meters().remove(&self.0.id);
+    }
+}
+
+/// Request guard of a top-up, the open metered stream of the `stream` query parameter.
+/// It goes before the [crate::Paid] guard so that a closed stream is not paid for.
+/// The top-up is pending until the guard is dropped, so the stream waits for its payment
+/// to settle.
+pub struct TopUpStream(pub Arc<StreamMeter>);
+
+impl TopUpStream {
+    pub const QUERY_PARAM: &str = "stream";
+
+    fn new(meter: Arc<StreamMeter>) -> Self {
+        meter.pending_top_ups.fetch_add(1, Ordering::SeqCst);
+
+        Self(meter)
+    }
+}
+
+impl Drop for TopUpStream {
+    fn drop(&mut self) {
+        self.0.pending_top_ups.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TopUpStream {
+    type Error = X402ErrorResponse;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let meter = req
+            .query_value::<&str>(Self::QUERY_PARAM)
+            .and_then(|id| id.ok())
+            .and_then(StreamMeter::get);
+
+        match meter {
+            Some(meter) => Outcome::Success(Self::new(meter)),
+            None => {
+                let error = X402ErrorResponse::new(
+                    X402ErrorCode::InvalidRequest,
+                    &req.uri().to_string(),
+                    "The `stream` query parameter is not an open metered stream",
+                );
+                // Cached for the catchers which only receive the status
+                req.local_cache(|| Some(error.clone()));
+
+                Outcome::Error((Status::BadRequest, error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod stream_meter_sanity {
+    use super::*;
+
+    #[test]
+    fn test_allowance() {
+        let by_minute = StreamMeter::new(
+            "minute",
+            "client",
+            CatalogMetering {
+                events: None,
+                seconds: Some(60),
+            },
+        );
+        assert!(!by_minute.has_allowance(100));
+
+        by_minute.top_up("first", 100);
+        by_minute.consume_event();
+        assert!(by_minute.has_allowance(159));
+        assert!(!by_minute.has_allowance(160));
+
+        // A top-up before the allowance runs out extends it
+        by_minute.top_up("second", 150);
+        assert!(by_minute.has_allowance(219));
+        assert!(!by_minute.has_allowance(220));
+
+        let by_event_or_minute = StreamMeter::new(
+            "event",
+            "client",
+            CatalogMetering {
+                events: Some(2),
+                seconds: Some(60),
+            },
+        );
+        by_event_or_minute.top_up("first", 100);
+        by_event_or_minute.consume_event();
+        assert!(by_event_or_minute.has_allowance(101));
+        by_event_or_minute.consume_event();
+        assert!(!by_event_or_minute.has_allowance(101));
+    }
+
+    #[test]
+    fn test_registry() {
+        let metering = CatalogMetering {
+            events: Some(10),
+            seconds: None,
+        };
+        let stream = StreamMeter::open(metering, "registry").unwrap();
+        let id = stream.id.clone();
+
+        StreamMeter::get(&id).unwrap().top_up("first", 0);
+        assert!(stream.has_allowance(0));
+
+        drop(stream);
+        assert!(StreamMeter::get(&id).is_none());
+
+        // A client can only open a few streams at once
+        let streams = (0..StreamMeter::MAX_STREAMS_PER_CLIENT)
+            .map(|_| StreamMeter::open(metering, "capped").unwrap())
+            .collect::<Vec<MeteredStream>>();
+        assert!(StreamMeter::open(metering, "capped").is_none());
+        assert!(StreamMeter::open(metering, "other").is_some());
+
+        drop(streams);
+        assert!(StreamMeter::open(metering, "capped").is_some());
+    }
+
+    #[test]
+    fn test_pending_top_up() {
+        let metering = CatalogMetering {
+            events: None,
+            seconds: Some(60),
+        };
+        let stream = StreamMeter::open(metering, "pending").unwrap();
+        assert!(!stream.has_pending_top_up());
+
+        let top_up = TopUpStream::new(StreamMeter::get(&stream.id).unwrap());
+        let concurrent = TopUpStream::new(StreamMeter::get(&stream.id).unwrap());
+        assert!(stream.has_pending_top_up());
+
+        drop(top_up);
+        assert!(stream.has_pending_top_up());
+        drop(concurrent);
+        assert!(!stream.has_pending_top_up());
+    }
+}
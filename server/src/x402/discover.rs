@@ -1,75 +1,93 @@
-use std::{borrow::Cow, time::Duration};
+use std::borrow::Cow;
+use std::collections::HashMap;
 
+use common::{X402DiscoveryPagination, X402DiscoveryQuery, X402ErrorCode, X402ErrorResponse};
 use rocket::serde::json::Json;
 use rusty_x402::{
     DiscoveryPayload, PayloadPagination, PaymentRequestExtras, PaymentRequirementsBuilder,
     ResourceInfo, X402Version,
 };
 
-use crate::{AllowedAssets, SERVER_CONFIG};
+use crate::{CatalogResource, ResourceCatalog, X402Error};
 
-#[get("/discover")]
-pub fn x402_discover<'x>() -> Result<Json<DiscoveryPayload<'x>>, String> {
-    let mut items = Vec::<ResourceInfo>::default();
+/// The paid resources of the catalog. Filters by `type`, `asset`, `minAmount`, `maxAmount` and
+/// a text `query`, paginated by `limit` and `offset`.
+#[get("/discover?<params..>")]
+pub fn x402_discover(
+    params: HashMap<String, String>,
+) -> Result<Json<serde_json::Value>, X402Error> {
+    let discover_uri = "/x402/discover";
+    let query = X402DiscoveryQuery::from_params(
+        params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    )
+    .map_err(|error| {
+        X402ErrorResponse::new(
+            X402ErrorCode::InvalidRequest,
+            discover_uri,
+            &error.to_string(),
+        )
+    })?;
 
-    let resource1_url = crate::NEWSLETTER_URI;
-    let extras = PaymentRequestExtras::new("");
-    let mut resource1_requirements = PaymentRequirementsBuilder::new();
-    resource1_requirements
-        .set_amount(500_000)
-        .set_asset(AllowedAssets::SOL.address)
-        .set_description("Pay using USDC to access the eBook")
-        .set_max_timeout_seconds(Duration::from_secs(60 * 5))
-        .set_recipient(SERVER_CONFIG.resource_server_address())
-        .set_resource(resource1_url)
-        .set_extra(extras)
-        .set_mime_as_json();
+    // The payload borrows from the catalog so it is serialized before the catalog is dropped
+    let catalog = ResourceCatalog::current();
+    let matching = catalog
+        .resources()
+        .iter()
+        .filter(|resource| matches_query(resource, &query))
+        .collect::<Vec<&CatalogResource>>();
+    let pagination = X402DiscoveryPagination::new(&query, matching.len());
 
-    let resource1 = ResourceInfo {
-        resource: resource1_url,
-        r#type: Option::Some("http"),
-        x402_version: X402Version::V1 as u8,
-        accepts: Cow::Owned(vec![resource1_requirements.build().map_err(|error| {
-            String::from("Error buildng resource") + error.to_string().as_str()
-        })?]),
-        header_image: Some("https://lagoon.markets/typewriter.jpg".into()),
-        title: Some("Conqueror of Blockchains; Taker of Markets".into()),
-        description: Some(
-            "Toly the Great provides insights on how the Solana blockchain is transforming financial markets at the speed of light.".into(),
-        ),
-        last_updated: u64::default(),
-        metadata: Option::default(),
-    };
+    let mut items = Vec::<ResourceInfo>::default();
 
-    let resource2_url = crate::VOTING;
-    let extras2 = PaymentRequestExtras::new("");
-    let mut resource2_requirements = PaymentRequirementsBuilder::new();
-    resource2_requirements
-        .set_amount(500_000)
-        .set_asset(AllowedAssets::SOL.address)
-        .set_description("View timeline live updates")
-        .set_max_timeout_seconds(Duration::from_secs(60 * 5))
-        .set_recipient(SERVER_CONFIG.resource_server_address())
-        .set_resource(resource2_url)
-        .set_extra(extras2)
-        .set_mime_as_json();
+    for resource in &matching[pagination.range(matching.len())] {
+        let accepts = resource
+            .accepts
+            .iter()
+            .filter(|option| query.matches_price(&option.asset, option.max_amount_required))
+            .map(|option| {
+                let mut extras = PaymentRequestExtras::new("");
+                if !option.is_sol() {
+                    extras = extras.set_decimals(option.decimals);
+                }
+                if !option.is_sol() && !option.token_2022 {
+                    extras = extras.set_legacy_token_mint();
+                }
 
-    let resource2 = ResourceInfo {
-        resource: resource2_url,
-        r#type: Option::Some("a2a"),
-        x402_version: X402Version::V1 as u8,
-        accepts: Cow::Owned(vec![resource2_requirements.build().map_err(|error| {
-            String::from("Error buildng resource") + error.to_string().as_str()
-        })?]),
-        header_image: Some("https://lagoon.markets/typewriter.jpg".into()),
-        title: Some("Live Updates on the timeline for new eBook release".into()),
-        description: Some("Get timelines on Toly the Great upcoming book `Building tokenized trading solutions` from our AI agent".into()),
-        last_updated: u64::default(),
-        metadata: Option::default(),
-    };
+                let mut requirements = PaymentRequirementsBuilder::new();
+                requirements
+                    .set_amount(option.max_amount_required)
+                    .set_asset(&option.asset)
+                    .set_description(&option.description)
+                    .set_max_timeout_seconds(resource.max_timeout())
+                    .set_recipient(resource.pay_to())
+                    .set_resource(&resource.resource)
+                    .set_extra(extras)
+                    .set_mime_as_json();
+
+                requirements.build().map_err(|error| {
+                    X402ErrorResponse::new(
+                        X402ErrorCode::InternalError,
+                        &resource.resource,
+                        &(String::from("Error buildng resource") + error.to_string().as_str()),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, X402ErrorResponse>>()?;
 
-    items.push(resource1);
-    items.push(resource2);
+        items.push(ResourceInfo {
+            resource: &resource.resource,
+            r#type: Option::Some(resource.r#type.as_str()),
+            x402_version: X402Version::V1 as u8,
+            accepts: Cow::Owned(accepts),
+            header_image: resource.header_image.as_deref().map(Into::into),
+            title: resource.title.as_deref().map(Into::into),
+            description: resource.description.as_deref().map(Into::into),
+            last_updated: catalog.loaded_at(),
+            metadata: Option::default(),
+        });
+    }
 
     let payload = DiscoveryPayload {
         x402_version: X402Version::V1 as u8,
@@ -77,5 +95,29 @@ pub fn x402_discover<'x>() -> Result<Json<DiscoveryPayload<'x>>, String> {
         pagination: PayloadPagination::default(),
     };
 
+    let mut payload = serde_json::to_value(&payload).map_err(|error| {
+        X402ErrorResponse::new(
+            X402ErrorCode::InternalError,
+            discover_uri,
+            &(String::from("Unable to serialize the discovery payload. Error: ")
+                + error.to_string().as_str()),
+        )
+    })?;
+    // `PayloadPagination` has no constructor for the page so it is written after serializing
+    payload["pagination"] = serde_json::json!(pagination);
+
     Ok(Json(payload))
 }
+
+fn matches_query(resource: &CatalogResource, query: &X402DiscoveryQuery) -> bool {
+    query.matches_type(resource.r#type.as_str())
+        && resource
+            .accepts
+            .iter()
+            .any(|option| query.matches_price(&option.asset, option.max_amount_required))
+        && query.matches_text(&[
+            &resource.resource,
+            resource.title.as_deref().unwrap_or_default(),
+            resource.description.as_deref().unwrap_or_default(),
+        ])
+}
@@ -0,0 +1,512 @@
+use common::{
+    RpcTransport, SolanaChain, SolanaRpcClient, X402ErrorCode, X402ErrorResponse,
+    X402FacilitatorRequest, X402PaymentPayload, X402SettleResponse, X402SupportedKind,
+    X402SupportedResponse, X402VerifyResponse,
+};
+use rocket::serde::json::Json;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+use crate::{FeePayerLimits, PaymentLedger, X402PaymentVerifier, SERVER_CONFIG};
+
+#[get("/facilitator/supported")]
+pub fn facilitator_supported() -> Json<X402SupportedResponse> {
+    Json(X402Facilitator::current().supported())
+}
+
+#[post("/facilitator/verify", format = "json", data = "<body>")]
+pub fn facilitator_verify(body: Json<X402FacilitatorRequest>) -> Json<X402VerifyResponse> {
+    Json(X402Facilitator::current().verify(&body))
+}
+
+#[post("/facilitator/settle", format = "json", data = "<body>")]
+pub async fn facilitator_settle(body: Json<X402FacilitatorRequest>) -> Json<X402SettleResponse> {
+    let response = blocking::unblock(move || match body.payment_requirements.chain() {
+        Ok(chain) => X402Facilitator::current().settle(
+            &body,
+            PaymentLedger::current(),
+            &SolanaRpcClient::new(SERVER_CONFIG.rpc_endpoint(chain)),
+        ),
+        Err(error) => X402SettleResponse::failed(&body.payment_requirements.network, error),
+    })
+    .await;
+
+    Json(response)
+}
+
+/// The facilitator role of the x402 spec for the Solana `exact` scheme
+#[derive(Debug, Clone, Copy)]
+pub struct X402Facilitator<'a> {
+    /// The recipients of the payments it verifies and settles
+    pay_to: &'a [String],
+    /// The chains it advertises as supported
+    chains: &'a [SolanaChain],
+    fee_payer: Option<&'a Keypair>,
+    fee_payer_limits: FeePayerLimits,
+}
+
+impl<'a> X402Facilitator<'a> {
+    pub fn new(pay_to: &'a [String]) -> Self {
+        Self {
+            pay_to,
+            chains: &[],
+            fee_payer: Option::None,
+            fee_payer_limits: FeePayerLimits::default(),
+        }
+    }
+
+    /// The facilitator of the server config
+    pub fn current() -> X402Facilitator<'static> {
+        let mut facilitator = X402Facilitator::new(SERVER_CONFIG.facilitator_pay_to());
+        facilitator
+            .set_chains(SERVER_CONFIG.configured_chains())
+            .set_fee_payer_limits(SERVER_CONFIG.fee_payer_limits());
+
+        if let Some(fee_payer) = SERVER_CONFIG.facilitator_keypair() {
+            facilitator.set_fee_payer(fee_payer);
+        }
+
+        facilitator
+    }
+
+    /// The chains with an RPC endpoint to settle the payments
+    pub fn set_chains(&mut self, chains: &'a [SolanaChain]) -> &mut Self {
+        self.chains = chains;
+
+        self
+    }
+
+    /// Co-signs the payments that name `fee_payer` as the fee payer
+    pub fn set_fee_payer(&mut self, fee_payer: &'a Keypair) -> &mut Self {
+        self.fee_payer.replace(fee_payer);
+
+        self
+    }
+
+    /// The compute budget of the payments co-signed as the fee payer
+    pub fn set_fee_payer_limits(&mut self, limits: FeePayerLimits) -> &mut Self {
+        self.fee_payer_limits = limits;
+
+        self
+    }
+
+    /// The chains it can settle on with the facilitator as the fee payer when its keypair
+    /// is configured. Nothing is supported without a recipient to pay to.
+    pub fn supported(&self) -> X402SupportedResponse {
+        let fee_payer = self
+            .fee_payer
+            .map(|fee_payer| fee_payer.pubkey().to_string());
+        let chains: &[SolanaChain] = if self.pay_to.is_empty() {
+            &[]
+        } else {
+            self.chains
+        };
+
+        X402SupportedResponse {
+            kinds: chains
+                .iter()
+                .map(|chain| X402SupportedKind::exact(*chain, fee_payer.as_deref()))
+                .collect(),
+        }
+    }
+
+    pub fn verify(&self, request: &X402FacilitatorRequest) -> X402VerifyResponse {
+        match self
+            .verifier(request)
+            .and_then(|verifier| verifier.verify_payload(&request.payment_payload))
+        {
+            Ok(payment) => X402VerifyResponse::valid(&payment.payer),
+            Err(error) => X402VerifyResponse::invalid(error),
+        }
+    }
+
+    /// Verifies the payment again before sending it so `/settle` can be called without `/verify`.
+    /// A payment is settled once, the `ledger` rejects its transaction afterwards.
+    pub fn settle<T: RpcTransport>(
+        &self,
+        request: &X402FacilitatorRequest,
+        ledger: &PaymentLedger,
+        client: &SolanaRpcClient<T>,
+    ) -> X402SettleResponse {
+        let network = request.payment_requirements.network.as_str();
+
+        let settled = self.verifier(request).and_then(|verifier| {
+            verifier
+                .verify_payload(&request.payment_payload)
+                .and_then(|payment| verifier.settle_with(ledger, client, &payment))
+        });
+
+        match settled {
+            Ok(settled) => X402SettleResponse {
+                success: true,
+                error_reason: Option::None,
+                error: Option::None,
+                transaction: settled.transaction,
+                network: settled.network,
+                payer: Some(settled.payer),
+            },
+            Err(error) => X402SettleResponse::failed(network, error),
+        }
+    }
+
+    fn verifier<'b>(
+        &'b self,
+        request: &'b X402FacilitatorRequest,
+    ) -> Result<X402PaymentVerifier<'b>, X402ErrorResponse> {
+        let requirements = &request.payment_requirements;
+        let invalid_request = |error: &str| {
+            X402ErrorResponse::new(X402ErrorCode::InvalidRequest, &requirements.resource, error)
+        };
+
+        if request.x402_version != X402PaymentPayload::X402_VERSION {
+            return Err(invalid_request("Only version 1 of x402 is supported"));
+        }
+
+        if requirements.scheme != X402PaymentPayload::EXACT_SCHEME {
+            return Err(invalid_request("Only the `exact` scheme is supported"));
+        }
+
+        if request.payment_payload.network != requirements.network {
+            return Err(invalid_request(
+                "The network of the payment does not match the payment requirements",
+            ));
+        }
+
+        // Only payments to the configured recipients, so the facilitator cannot be used by anyone
+        if !self.pay_to.contains(&requirements.pay_to) {
+            return Err(invalid_request(
+                "The facilitator does not settle payments to this recipient",
+            ));
+        }
+
+        let mut verifier = X402PaymentVerifier::new(
+            &requirements.resource,
+            requirements.chain()?,
+            &requirements.asset,
+            requirements.max_amount_required()?,
+            &requirements.pay_to,
+        );
+        verifier.set_fee_payer_limits(self.fee_payer_limits);
+
+        if let Some(decimals) = requirements.extra.as_ref().and_then(|extra| extra.decimals) {
+            verifier.set_decimals(decimals);
+        }
+
+        // Only sign for payments that name this facilitator as the fee payer
+        if let Some(fee_payer) = self.fee_payer.filter(|fee_payer| {
+            requirements.fee_payer() == Some(fee_payer.pubkey().to_string().as_str())
+        }) {
+            verifier.set_fee_payer(fee_payer);
+        }
+
+        Ok(verifier)
+    }
+}
+
+#[cfg(test)]
+mod x402_facilitator_sanity {
+    use base64ct::{Base64, Encoding};
+    use common::{RpcClientResult, X402PaymentRequirements, X402SvmExtra};
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_transaction::Transaction;
+
+    use super::*;
+
+    /// A stand-in RPC that accepts every transaction and confirms it immediately
+    struct LocalRpc;
+
+    impl RpcTransport for LocalRpc {
+        fn send(&self, body: String) -> RpcClientResult<String> {
+            let request = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+
+            let result = match request["method"].as_str() {
+                Some("sendTransaction") => {
+                    let transaction = Base64::decode_vec(request["params"][0].as_str().unwrap())
+                        .ok()
+                        .and_then(|bytes| bincode::deserialize::<Transaction>(&bytes).ok())
+                        .unwrap();
+                    assert!(transaction.verify().is_ok());
+
+                    serde_json::json!(transaction.signatures[0].to_string())
+                }
+                _ => serde_json::json!({
+                    "context": { "slot": 82 },
+                    "value": [{
+                        "slot": 72,
+                        "confirmations": 1,
+                        "err": null,
+                        "status": { "Ok": null },
+                        "confirmationStatus": "confirmed"
+                    }]
+                }),
+            };
+
+            Ok(
+                serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+                    .to_string(),
+            )
+        }
+    }
+
+    fn request(
+        payer: &Keypair,
+        fee_payer: &Keypair,
+        pay_to: &Keypair,
+        lamports: u64,
+    ) -> X402FacilitatorRequest {
+        let transfer = solana_system_interface::instruction::transfer(
+            &payer.pubkey(),
+            &pay_to.pubkey(),
+            lamports,
+        );
+
+        request_with(payer, fee_payer, pay_to, &[transfer])
+    }
+
+    fn request_with(
+        payer: &Keypair,
+        fee_payer: &Keypair,
+        pay_to: &Keypair,
+        instructions: &[Instruction],
+    ) -> X402FacilitatorRequest {
+        let message = Message::new(instructions, Some(&fee_payer.pubkey()));
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction.partial_sign(&[payer], Hash::default());
+
+        let encoded = Base64::encode_string(&bincode::serialize(&transaction).unwrap());
+
+        X402FacilitatorRequest {
+            x402_version: 1,
+            payment_payload: X402PaymentPayload::new(SolanaChain::Devnet, &encoded),
+            payment_requirements: X402PaymentRequirements {
+                scheme: "exact".to_string(),
+                network: "solana-devnet".to_string(),
+                max_amount_required: "1000".to_string(),
+                resource: "https://lagoon.markets/latest_newsletter".to_string(),
+                description: String::default(),
+                mime_type: "application/json".to_string(),
+                pay_to: pay_to.pubkey().to_string(),
+                max_timeout_seconds: 100,
+                asset: solana_system_interface::program::ID.to_string(),
+                extra: Some(X402SvmExtra {
+                    fee_payer: Some(fee_payer.pubkey().to_string()),
+                    decimals: Option::None,
+                }),
+            },
+        }
+    }
+
+    fn compute_budget(variant: u8, value: &[u8]) -> Instruction {
+        Instruction::new_with_bytes(
+            solana_sdk_ids::compute_budget::ID,
+            &[&[variant], value].concat(),
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let (payer, fee_payer, pay_to) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let recipients = [pay_to.pubkey().to_string()];
+        let without_fee_payer = X402Facilitator::new(&recipients);
+        let mut facilitator = X402Facilitator::new(&recipients);
+        facilitator.set_fee_payer(&fee_payer);
+
+        let paid_by_client = request(&payer, &payer, &pay_to, 1000);
+        assert_eq!(
+            without_fee_payer.verify(&paid_by_client),
+            X402VerifyResponse::valid(&payer.pubkey().to_string())
+        );
+
+        let underpaid = request(&payer, &payer, &pay_to, 999);
+        assert_eq!(
+            without_fee_payer.verify(&underpaid).invalid_reason,
+            Some(X402ErrorCode::PaymentRejected)
+        );
+
+        let paid_by_facilitator = request(&payer, &fee_payer, &pay_to, 1000);
+        assert_eq!(
+            without_fee_payer
+                .verify(&paid_by_facilitator)
+                .invalid_reason,
+            Some(X402ErrorCode::InvalidPayment)
+        );
+        assert!(facilitator.verify(&paid_by_facilitator).is_valid);
+
+        // The facilitator cannot be the source of the transfer
+        let drains_facilitator = request(&fee_payer, &fee_payer, &pay_to, 1000);
+        assert_eq!(
+            facilitator.verify(&drains_facilitator).invalid_reason,
+            Some(X402ErrorCode::PaymentRejected)
+        );
+
+        // Only payments to the configured recipients are verified
+        assert_eq!(
+            X402Facilitator::new(&[])
+                .verify(&paid_by_client)
+                .invalid_reason,
+            Some(X402ErrorCode::InvalidRequest)
+        );
+    }
+
+    #[test]
+    fn test_sponsored_instructions() {
+        let (payer, fee_payer, pay_to) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let recipients = [pay_to.pubkey().to_string()];
+        let mut facilitator = X402Facilitator::new(&recipients);
+        facilitator.set_fee_payer(&fee_payer);
+
+        let transfer =
+            solana_system_interface::instruction::transfer(&payer.pubkey(), &pay_to.pubkey(), 1000);
+        let limits = FeePayerLimits::default();
+
+        let budgeted = request_with(
+            &payer,
+            &fee_payer,
+            &pay_to,
+            &[
+                compute_budget(2, &limits.max_compute_unit_limit.to_le_bytes()),
+                compute_budget(3, &limits.max_compute_unit_price.to_le_bytes()),
+                transfer.clone(),
+            ],
+        );
+        assert!(facilitator.verify(&budgeted).is_valid);
+
+        let over_priced = request_with(
+            &payer,
+            &fee_payer,
+            &pay_to,
+            &[
+                compute_budget(2, &limits.max_compute_unit_limit.to_le_bytes()),
+                compute_budget(3, &(limits.max_compute_unit_price + 1).to_le_bytes()),
+                transfer.clone(),
+            ],
+        );
+        assert_eq!(
+            facilitator.verify(&over_priced).invalid_reason,
+            Some(X402ErrorCode::PaymentRejected)
+        );
+
+        let over_limit = request_with(
+            &payer,
+            &fee_payer,
+            &pay_to,
+            &[
+                compute_budget(2, &(limits.max_compute_unit_limit + 1).to_le_bytes()),
+                transfer.clone(),
+            ],
+        );
+        assert_eq!(
+            facilitator.verify(&over_limit).invalid_reason,
+            Some(X402ErrorCode::PaymentRejected)
+        );
+
+        let extra_transfer = request_with(
+            &payer,
+            &fee_payer,
+            &pay_to,
+            &[
+                transfer.clone(),
+                solana_system_interface::instruction::transfer(
+                    &payer.pubkey(),
+                    &Keypair::new().pubkey(),
+                    1,
+                ),
+            ],
+        );
+        assert_eq!(
+            facilitator.verify(&extra_transfer).invalid_reason,
+            Some(X402ErrorCode::PaymentRejected)
+        );
+
+        let split_payment =
+            request_with(&payer, &fee_payer, &pay_to, &[transfer.clone(), transfer]);
+        assert_eq!(
+            facilitator.verify(&split_payment).invalid_reason,
+            Some(X402ErrorCode::PaymentRejected)
+        );
+    }
+
+    #[test]
+    fn test_settle() {
+        let (payer, fee_payer, pay_to) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let recipients = [pay_to.pubkey().to_string()];
+        let mut facilitator = X402Facilitator::new(&recipients);
+        facilitator.set_fee_payer(&fee_payer);
+        let client = SolanaRpcClient::with_transport(LocalRpc);
+        let ledger = PaymentLedger::in_memory().unwrap();
+
+        let payment = request(&payer, &fee_payer, &pay_to, 1000);
+        let settled = facilitator.settle(&payment, &ledger, &client);
+        assert!(settled.success);
+        assert_eq!(settled.network, "solana-devnet");
+        assert_eq!(settled.payer, Some(payer.pubkey().to_string()));
+        assert!(!settled.transaction.is_empty());
+
+        // The same transaction cannot pay twice
+        assert_eq!(
+            facilitator.settle(&payment, &ledger, &client).error_reason,
+            Some(X402ErrorCode::PaymentAlreadyUsed)
+        );
+
+        let recorded = ledger
+            .resource_payments(
+                &pay_to.pubkey().to_string(),
+                &payment.payment_requirements.resource,
+                10,
+                0,
+            )
+            .unwrap();
+        assert_eq!(recorded.total, 1);
+        assert_eq!(recorded.payments[0].signature, settled.transaction);
+
+        let mut wrong_network = request(&payer, &payer, &pay_to, 1000);
+        wrong_network.payment_payload.network = "solana".to_string();
+        assert_eq!(
+            X402Facilitator::new(&recipients)
+                .settle(&wrong_network, &ledger, &client)
+                .error_reason,
+            Some(X402ErrorCode::InvalidRequest)
+        );
+    }
+
+    #[test]
+    fn test_supported() {
+        let fee_payer = Keypair::new();
+        let recipients = [Keypair::new().pubkey().to_string()];
+        let chains = [SolanaChain::MainnetBeta, SolanaChain::Devnet];
+        let supported = X402Facilitator::new(&recipients)
+            .set_chains(&chains)
+            .set_fee_payer(&fee_payer)
+            .supported();
+
+        assert_eq!(
+            supported
+                .kinds
+                .iter()
+                .map(|kind| kind.network.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["solana", "solana-devnet"]
+        );
+        assert_eq!(
+            supported.kinds[0]
+                .extra
+                .as_ref()
+                .and_then(|extra| extra.fee_payer.clone()),
+            Some(fee_payer.pubkey().to_string())
+        );
+
+        // Only the chains with an RPC endpoint are supported, and only with a recipient
+        assert!(X402Facilitator::new(&recipients)
+            .supported()
+            .kinds
+            .is_empty());
+        assert!(X402Facilitator::new(&[])
+            .set_chains(&chains)
+            .supported()
+            .kinds
+            .is_empty());
+    }
+}
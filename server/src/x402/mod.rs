@@ -3,3 +3,9 @@ pub use discover::*;
 
 mod optimize_tx_handler;
 pub use optimize_tx_handler::*;
+
+mod facilitator;
+pub use facilitator::*;
+
+mod payments;
+pub use payments::*;
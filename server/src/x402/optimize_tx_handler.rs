@@ -1,10 +1,14 @@
 use base64ct::{Base64, Encoding};
-use common::{SanctumBuilderResponse, SanctumRpcResponse, TxBase64Encoded};
+use common::{
+    SanctumBuilderResponse, SanctumRpcResponse, SolanaRpcClient, TipInstructionsRequest,
+    TipResponse, TxBase64Encoded,
+};
 use rocket::{http::Status, serde::json::Json};
 
+use solana_pubkey::Pubkey;
 use solana_transaction::Transaction;
 
-use crate::SERVER_CONFIG;
+use crate::{X402Client, SERVER_CONFIG};
 
 #[post("/optimize-tx", format = "json", data = "<body>")]
 pub async fn optimize_tx(
@@ -17,40 +21,29 @@ pub async fn optimize_tx(
     bincode::deserialize::<Transaction>(&decode_base64_tx)
         .or(Err((Status::BadRequest, "Invalid transaction".to_string())))?;
 
-    let builder_body = jzon::object! {
-      "id": "1",
-      "jsonrpc": "2.0",
-      "method": "buildGatewayTransaction",
-      "params": [
-        body.data.as_str(),
-        {
-            deliveryMethodType: "sanctum-sender", //| "jito" | "sanctum-sender" | "helius-sender", defaults to project parameters
-        }
-      ]
-    };
-
-    let response = blocking::unblock(move || {
-        minreq::post(SERVER_CONFIG.sanctum_uri())
-            .with_header("Content-Type", "application/json")
-            .with_body(builder_body.to_string())
-            .send()
-            .map_err(|error| (Status::InternalServerError, error.to_string()))
+    let decoded_sanctum_tx = blocking::unblock(move || {
+        SolanaRpcClient::new(SERVER_CONFIG.sanctum_uri())
+            .request::<_, SanctumBuilderResponse>(
+                "buildGatewayTransaction",
+                (
+                    body.data.as_str(),
+                    serde_json::json!({
+                        "deliveryMethodType": "sanctum-sender", //| "jito" | "sanctum-sender" | "helius-sender", defaults to project parameters
+                    }),
+                ),
+            )
+            .map_err(|error| {
+                (
+                    Status::InternalServerError,
+                    String::from("Unable to build the transaction with Sanctum gateway. Maybe you didn't follow the instructions on creating a Sanctum gateway account and a delivery method. Error: ")
+                        + error.to_string().as_str(),
+                )
+            })
     })
     .await?;
 
-    let decoded_sanctum_tx = serde_json::from_str::<SanctumRpcResponse<SanctumBuilderResponse>>(
-        response.as_str().or(Err((
-            Status::InternalServerError,
-            "Unable to decode sanctum body as a string to parse as JSON".to_string(),
-        )))?,
-    )
-    .or(Err((
-        Status::InternalServerError,
-        "Unable to parse sanctum body as JSON. Maybe you didn't follow the instructions on creating a Samctum gateway account and a delivery method".to_string(),
-    )))?;
-
     Ok(Json(TxBase64Encoded {
-        data: decoded_sanctum_tx.result.transaction,
+        data: decoded_sanctum_tx.transaction,
     }))
 }
 
@@ -65,33 +58,60 @@ pub async fn send_optimized_tx(
     bincode::deserialize::<Transaction>(&decode_base64_tx)
         .or(Err((Status::BadRequest, "Invalid transaction".to_string())))?;
 
-    let builder_body = jzon::object! {
-      "id": "1",
-      "jsonrpc": "2.0",
-      "method": "sendTransaction",
-      "params": [
-        body.data.as_str(),
-        // {
-        //     commitment: "confirmed",
-        //     encoding: "base64"
-        // }
-      ]
-    };
+    let decode_sent_optimized_tx = blocking::unblock(move || {
+        SolanaRpcClient::new(SERVER_CONFIG.sanctum_uri())
+            .call::<_, String>("sendTransaction", [body.data.as_str()])
+            .map_err(|error| {
+                (
+                    Status::InternalServerError,
+                    String::from("Encountered error sending transaction with `sendTransaction` method to Sanctum gateway! Error: ")
+                        + error.to_string().as_str(),
+                )
+            })
+    })
+    .await?;
+
+    let id = decode_sent_optimized_tx.id.to_string();
+    let jsonrpc = decode_sent_optimized_tx.jsonrpc.clone();
+    let signature = decode_sent_optimized_tx.into_result().map_err(|error| {
+        (
+            Status::InternalServerError,
+            String::from("The transaction probably did not succeed! Error: ")
+                + error.to_string().as_str(),
+        )
+    })?;
 
-    let response = blocking::unblock(move || {minreq::post(SERVER_CONFIG.sanctum_uri())
-        .with_header("Content-Type", "application/json")
-        .with_body(builder_body.to_string())
-        .send()
-        .or(
-            Err((Status::InternalServerError, "Encountered error sending transaction with `sendTransaction` method to Sanctum gateway!".to_string()))
-        )}).await?;
+    Ok(Json(SanctumRpcResponse {
+        id,
+        jsonrpc,
+        result: signature,
+    }))
+}
 
-    let decode_sent_optimized_tx =
-        serde_json::from_str::<SanctumRpcResponse<String>>(response.as_str().or(
-            Err((Status::InternalServerError, "The body returned by Sanctum gateway for `sendTransaction` is not a JSON string!".to_string()))
-        )?).or(
-            Err((Status::InternalServerError, "Unable to deserialize the sanctum response. If your Sanctum gateway config is correct then the transaction probably did not succeed!".to_string()))
-        )?;
+/// Proxies to the Sanctum gateway with the server API key, so only authenticated clients
+/// can use it
+#[post("/tip-instructions", format = "json", data = "<body>")]
+pub async fn tip_instructions(
+    _client: X402Client,
+    body: Json<TipInstructionsRequest>,
+) -> Result<Json<Vec<TipResponse>>, (Status, String)> {
+    body.fee_payer.parse::<Pubkey>().or(Err((
+        Status::BadRequest,
+        "Invalid Base58 fee payer address".to_string(),
+    )))?;
+
+    let tips = blocking::unblock(move || {
+        body.send(&SolanaRpcClient::new(SERVER_CONFIG.sanctum_uri()))
+            .map_err(|error| {
+                (
+                    Status::InternalServerError,
+                    String::from(
+                        "Unable to get the tip instructions from Sanctum gateway. Error: ",
+                    ) + error.to_string().as_str(),
+                )
+            })
+    })
+    .await?;
 
-    Ok(Json(decode_sent_optimized_tx))
+    Ok(Json(tips))
 }
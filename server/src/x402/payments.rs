@@ -0,0 +1,29 @@
+use common::{X402ErrorCode, X402ErrorResponse};
+use rocket::serde::json::Json;
+
+use crate::{LedgerPage, PaymentLedger, X402Client, X402Error};
+
+/// The payments of `resource` to the authenticated client address, newest first.
+/// Publishers sign the request with the `pay_to` address of their resource.
+#[get("/payments?<resource>&<limit>&<offset>")]
+pub fn resource_payments(
+    client: X402Client,
+    resource: &str,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Json<LedgerPage>, X402Error> {
+    PaymentLedger::current()
+        .resource_payments(
+            &client.address,
+            resource,
+            limit
+                .unwrap_or(PaymentLedger::DEFAULT_LIMIT)
+                .clamp(1, PaymentLedger::MAX_LIMIT),
+            offset.unwrap_or_default(),
+        )
+        .map(Json)
+        .map_err(|error| {
+            X402ErrorResponse::new(X402ErrorCode::InternalError, resource, &error.to_string())
+                .into()
+        })
+}
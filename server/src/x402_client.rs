@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use common::{
+    CommonHeaders, X402ClientProof, X402ClientProofError, X402ErrorCode, X402ErrorResponse,
+};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::SERVER_CONFIG;
+
+/// Nonces of verified proofs keyed by the client address and the nonce,
+/// with the proof timestamp so they can be pruned once they expire
+static SEEN_NONCES: once_cell::sync::Lazy<Mutex<HashMap<(String, String), u64>>> =
+    once_cell::sync::Lazy::new(Mutex::default);
+
+/// The `X402-Client-Address` of a request authenticated by its `X402-Client-Proof`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct X402Client {
+    pub address: String,
+    pub proof: X402ClientProof,
+}
+
+impl X402Client {
+    /// Verifies the client headers of `req`, `resource` is reported in the error
+    pub fn from_headers(req: &Request<'_>, resource: &str) -> Result<Self, X402ErrorResponse> {
+        let Some(address) = req.headers().get_one(CommonHeaders::X402_ADDRESS_HEADER) else {
+            return Err(X402ErrorResponse::missing_header(
+                resource,
+                CommonHeaders::X402_ADDRESS_HEADER,
+                "a base58 encoded Ed25519 public address",
+            ));
+        };
+
+        let Some(proof) = req
+            .headers()
+            .get_one(CommonHeaders::X402_CLIENT_PROOF_HEADER)
+            .and_then(|proof| X402ClientProof::from_header(proof).ok())
+        else {
+            return Err(X402ErrorResponse::missing_header(
+                resource,
+                CommonHeaders::X402_CLIENT_PROOF_HEADER,
+                "a base64url encoded client proof signed by the client address",
+            ));
+        };
+
+        Self::verify(req, resource, address, proof, &req.uri().to_string())
+    }
+
+    /// Verifies the client of the address and proof query parameters of `req`,
+    /// for `EventSource` clients which cannot set headers
+    pub fn from_query(req: &Request<'_>, resource: &str) -> Result<Self, X402ErrorResponse> {
+        let query_value = |name: &str| req.query_value::<&str>(name).and_then(|value| value.ok());
+
+        let (Some(address), Some(proof)) = (
+            query_value(X402ClientProof::ADDRESS_QUERY_PARAM),
+            query_value(X402ClientProof::QUERY_PARAM),
+        ) else {
+            let mut response = X402ErrorResponse::new(
+                X402ErrorCode::InvalidClientProof,
+                resource,
+                "The request has neither the client proof headers nor query parameters",
+            );
+            response.set_header(CommonHeaders::X402_CLIENT_PROOF_HEADER);
+
+            return Err(response);
+        };
+        let proof = X402ClientProof::from_header(proof)
+            .map_err(|error| Self::invalid_proof(resource, error))?;
+
+        let path = X402ClientProof::signed_path(&req.uri().to_string());
+
+        Self::verify(req, resource, address, proof, &path)
+    }
+
+    /// Checks that `proof` signs the request to `path` and was not used before
+    fn verify(
+        req: &Request<'_>,
+        resource: &str,
+        address: &str,
+        proof: X402ClientProof,
+        path: &str,
+    ) -> Result<Self, X402ErrorResponse> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        proof
+            .verify(
+                address,
+                req.method().as_str(),
+                path,
+                SERVER_CONFIG.siws_domain(),
+                now,
+            )
+            .and_then(|_| Self::check_nonce(address, &proof, now))
+            .map_err(|error| Self::invalid_proof(resource, error))?;
+
+        Ok(Self {
+            address: address.to_string(),
+            proof,
+        })
+    }
+
+    /// Rejects a nonce already used by the address and records it otherwise
+    fn check_nonce(
+        address: &str,
+        proof: &X402ClientProof,
+        now: u64,
+    ) -> Result<(), X402ClientProofError> {
+        let mut seen = SEEN_NONCES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // A proof is valid for the clock skew on either side of its timestamp
+        let oldest = now.saturating_sub(X402ClientProof::MAX_CLOCK_SKEW_SECS * 2);
+        seen.retain(|_, timestamp| *timestamp >= oldest);
+
+        match seen.insert((address.to_string(), proof.nonce.clone()), proof.timestamp) {
+            Some(_) => Err(X402ClientProofError::ReplayedNonce),
+            None => Ok(()),
+        }
+    }
+
+    fn invalid_proof(resource: &str, error: X402ClientProofError) -> X402ErrorResponse {
+        let mut response = X402ErrorResponse::new(
+            X402ErrorCode::InvalidClientProof,
+            resource,
+            &error.to_string(),
+        );
+        response.set_header(CommonHeaders::X402_CLIENT_PROOF_HEADER);
+
+        response
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for X402Client {
+    type Error = X402ErrorResponse;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match Self::from_headers(req, &req.uri().to_string()) {
+            Ok(client) => Outcome::Success(client),
+            Err(error) => {
+                let status = Status::from_code(error.status).unwrap_or(Status::Unauthorized);
+                // Cached for the catchers which only receive the status
+                req.local_cache(|| Some(error.clone()));
+
+                Outcome::Error((status, error))
+            }
+        }
+    }
+}
+
+/// Renders the error of a [X402Client] or [crate::Paid] guard that rejected a missing header
+#[catch(400)]
+pub fn x402_bad_request(req: &Request<'_>) -> crate::X402Error {
+    cached_error(req, X402ErrorCode::InvalidRequest, "Bad request")
+}
+
+/// Renders the error of a [X402Client] or [crate::Paid] guard that rejected the proof
+#[catch(401)]
+pub fn x402_unauthorized(req: &Request<'_>) -> crate::X402Error {
+    cached_error(req, X402ErrorCode::InvalidClientProof, "Unauthorized")
+}
+
+/// Renders the error of a [crate::Paid] guard that rejected a payment that was already used
+#[catch(409)]
+pub fn x402_conflict(req: &Request<'_>) -> crate::X402Error {
+    cached_error(req, X402ErrorCode::PaymentAlreadyUsed, "Conflict")
+}
+
+/// Renders the error of a [crate::Paid] guard that failed to settle a payment
+#[catch(502)]
+pub fn x402_bad_gateway(req: &Request<'_>) -> crate::X402Error {
+    cached_error(req, X402ErrorCode::SettlementFailed, "Bad gateway")
+}
+
+/// Renders the error of a [crate::Paid] guard that failed, or any other internal error
+#[catch(500)]
+pub fn x402_internal_error(req: &Request<'_>) -> crate::X402Error {
+    cached_error(req, X402ErrorCode::InternalError, "Internal server error")
+}
+
+/// The error a guard cached for the request, or `error` with the `fallback` code
+pub(crate) fn cached_error(
+    req: &Request<'_>,
+    fallback: X402ErrorCode,
+    error: &str,
+) -> crate::X402Error {
+    req.local_cache(|| Option::<X402ErrorResponse>::None)
+        .clone()
+        .unwrap_or_else(|| X402ErrorResponse::new(fallback, &req.uri().to_string(), error))
+        .into()
+}
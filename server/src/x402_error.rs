@@ -0,0 +1,28 @@
+use common::X402ErrorResponse;
+use rocket::{
+    http::{ContentType, Status},
+    request::Request,
+    response::{self, Responder, Response},
+};
+
+/// Responds with a [X402ErrorResponse] as JSON and its status code
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct X402Error(pub X402ErrorResponse);
+
+impl From<X402ErrorResponse> for X402Error {
+    fn from(value: X402ErrorResponse) -> Self {
+        Self(value)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for X402Error {
+    fn respond_to(self, _req: &'r Request<'_>) -> response::Result<'static> {
+        let body = self.0.to_json();
+
+        Response::build()
+            .sized_body(body.len(), std::io::Cursor::new(body))
+            .header(ContentType::JSON)
+            .status(Status::from_code(self.0.status).unwrap_or(Status::InternalServerError))
+            .ok()
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{X402UriError, X402UriResult};
+
+/// The parts of the resource URI in a x402-URI,
+/// `<scheme>://[<userinfo>@]<host>[:<port>]<rest>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct X402UriAuthority<'a> {
+    scheme: &'a str,
+    userinfo: Option<&'a str>,
+    host: &'a str,
+    port: Option<u16>,
+    rest: &'a str,
+}
+
+impl<'a> X402UriAuthority<'a> {
+    /// Splits a decoded resource URI like `https://example.com:8443/x402?id=1` into its parts.
+    /// The host is returned as written and may be empty.
+    pub fn parse(uri: &'a str) -> X402UriResult<Self> {
+        let (scheme, after_scheme) = uri
+            .split_once("://")
+            .ok_or(X402UriError::UnsupportedUriScheme)?;
+
+        // Browsers treat `\` like `/` so it also ends the authority
+        let authority_end = after_scheme
+            .find(['/', '?', '#', '\\'])
+            .unwrap_or(after_scheme.len());
+        let (authority, rest) = after_scheme.split_at(authority_end);
+
+        // The last `@` separates the userinfo like browsers do,
+        // so `https://lagoon.markets@evil.com` points to `evil.com`
+        let (userinfo, host_and_port) = match authority.rsplit_once('@') {
+            Some((userinfo, host_and_port)) => (Some(userinfo), host_and_port),
+            None => (None, authority),
+        };
+
+        let (host, port) = if host_and_port.starts_with('[') {
+            match host_and_port.find(']') {
+                Some(index) => (
+                    &host_and_port[..=index],
+                    host_and_port[index + 1..].strip_prefix(':'),
+                ),
+                None => return Err(X402UriError::InvalidHost(host_and_port.to_string())),
+            }
+        } else {
+            match host_and_port.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_and_port, None),
+            }
+        };
+
+        let port = port
+            .filter(|port| !port.is_empty())
+            .map(|port| {
+                if port.bytes().all(|byte| byte.is_ascii_digit()) {
+                    port.parse::<u16>().or(Err(X402UriError::InvalidPort))
+                } else {
+                    Err(X402UriError::InvalidPort)
+                }
+            })
+            .transpose()?;
+
+        Ok(Self {
+            scheme,
+            userinfo,
+            host,
+            port,
+            rest,
+        })
+    }
+
+    /// The scheme without the `://`
+    pub fn scheme(&self) -> &'a str {
+        self.scheme
+    }
+
+    /// The `user:password` part before the `@` in the authority
+    pub fn userinfo(&self) -> Option<&'a str> {
+        self.userinfo
+    }
+
+    pub fn host(&self) -> &'a str {
+        self.host
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// The path, query and fragment after the authority
+    pub fn rest(&self) -> &'a str {
+        self.rest
+    }
+}
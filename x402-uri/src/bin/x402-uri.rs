@@ -0,0 +1,237 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use x402_uri::{
+    X402QrOptions, X402Uri, X402UriAction, X402UriBuf, X402UriError, X402UriLint, X402UriScheme,
+};
+
+/// Create, inspect and check x402-URIs
+#[derive(Debug, Parser)]
+#[command(name = "x402-uri", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the action, scheme and decoded resource URI of a x402-URI as JSON
+    Parse { x402_uri: String },
+    /// Build a percent-encoded x402-URI for a resource URI
+    Build {
+        #[arg(long, value_enum, default_value_t = Action::Discover)]
+        action: Action,
+        /// The resource URI like `https://example.com/x402/discover`
+        uri: String,
+    },
+    /// Write a x402-URI as a QR code to a `.svg` or `.png` file
+    Qr {
+        x402_uri: String,
+        #[arg(long, short)]
+        output: PathBuf,
+        /// Defaults to the extension of the output file
+        #[arg(long, value_enum)]
+        format: Option<QrFormat>,
+        #[arg(long, default_value_t = 8)]
+        module_size: u32,
+        /// Fraction of the QR code width kept free for a logo, at most 0.3
+        #[arg(long)]
+        logo_safe_zone: Option<f32>,
+        /// The logo image linked in the center of a SVG QR code
+        #[arg(long)]
+        logo_href: Option<String>,
+    },
+    /// Check a x402-URI against the specification
+    Lint {
+        x402_uri: String,
+        /// Exit with an error on warnings
+        #[arg(long)]
+        deny_warnings: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Action {
+    Discover,
+    Subscribe,
+    Unsubscribe,
+    Once,
+    Pay,
+}
+
+impl From<Action> for X402UriAction {
+    fn from(value: Action) -> Self {
+        match value {
+            Action::Discover => Self::Discover,
+            Action::Subscribe => Self::Subscribe,
+            Action::Unsubscribe => Self::Unsubscribe,
+            Action::Once => Self::Once,
+            Action::Pay => Self::Pay,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum QrFormat {
+    Svg,
+    Png,
+}
+
+fn main() -> ExitCode {
+    let outcome = match Cli::parse().command {
+        Command::Parse { x402_uri } => parse(&x402_uri),
+        Command::Build { action, uri } => build(action, &uri),
+        Command::Qr {
+            x402_uri,
+            output,
+            format,
+            module_size,
+            logo_safe_zone,
+            logo_href,
+        } => qr(
+            &x402_uri,
+            &output,
+            format,
+            module_size,
+            logo_safe_zone,
+            logo_href,
+        ),
+        Command::Lint {
+            x402_uri,
+            deny_warnings,
+        } => return lint(&x402_uri, deny_warnings),
+    };
+
+    match outcome {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse(x402_uri: &str) -> Result<(), String> {
+    let parsed = X402Uri::new(x402_uri).map_err(|error| error.to_string())?;
+
+    let json = serde_json::json!({
+        "action": parsed.action().as_str(),
+        "scheme": parsed.uri_scheme().as_str().trim_end_matches("://"),
+        "uri": parsed.uri(),
+        "percentEncoded": parsed.is_percent_encoded(),
+        "canonical": parsed.canonical().map_err(|error| error.to_string())?.to_string(),
+    });
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json).map_err(|error| error.to_string())?
+    );
+
+    Ok(())
+}
+
+fn build(action: Action, uri: &str) -> Result<(), String> {
+    let built = build_uri(action, uri).map_err(|error| error.to_string())?;
+
+    println!("{built}");
+
+    Ok(())
+}
+
+/// Builds the x402-URI and checks the payment parameters of a `pay` URI,
+/// so that only URIs the parser accepts are printed
+fn build_uri(action: Action, uri: &str) -> Result<X402UriBuf, X402UriError> {
+    let uri_scheme = X402UriScheme::try_from(uri).unwrap_or_default();
+
+    let built = X402UriBuf::builder()
+        .set_action(action.into())
+        .set_uri_scheme(uri_scheme)
+        .set_uri(uri)
+        .build()?;
+
+    if built.action() == X402UriAction::Pay {
+        built.pay_request()?;
+    }
+
+    Ok(built)
+}
+
+fn qr(
+    x402_uri: &str,
+    output: &PathBuf,
+    format: Option<QrFormat>,
+    module_size: u32,
+    logo_safe_zone: Option<f32>,
+    logo_href: Option<String>,
+) -> Result<(), String> {
+    let format = format
+        .or_else(|| {
+            output
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .and_then(|extension| QrFormat::from_str(extension, true).ok())
+        })
+        .ok_or("Use an output file ending with `.svg` or `.png` or set `--format`")?;
+
+    let mut options = X402QrOptions::new();
+    options.set_module_size(module_size);
+
+    if let Some(logo_safe_zone) = logo_safe_zone {
+        options.set_logo_safe_zone(logo_safe_zone);
+    }
+    if let Some(logo_href) = logo_href.as_ref() {
+        options.set_logo_href(logo_href);
+    }
+
+    let qr_code = X402UriBuf::new(x402_uri)
+        .and_then(|parsed| parsed.to_qr_code(&options))
+        .map_err(|error: X402UriError| error.to_string())?;
+
+    let bytes = match format {
+        QrFormat::Svg => qr_code.to_svg().into_bytes(),
+        QrFormat::Png => qr_code.to_png().map_err(|error| error.to_string())?,
+    };
+
+    std::fs::write(output, bytes).map_err(|error| error.to_string())
+}
+
+fn lint(x402_uri: &str, deny_warnings: bool) -> ExitCode {
+    let lints = X402UriLint::check(x402_uri);
+
+    lints.iter().for_each(|lint| eprintln!("{lint}"));
+
+    let failed = lints.iter().any(|lint| {
+        lint.is_error() || (deny_warnings && !matches!(lint, X402UriLint::NotCanonical { .. }))
+    });
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod x402_uri_cli_sanity {
+    use super::*;
+
+    #[test]
+    fn test_build_pay() {
+        let settle = "https://shop.example.com/x402/settle?recipient=";
+
+        assert!(build_uri(
+            Action::Pay,
+            &(String::from(settle) + "2wKupLR9q6wXYppw8Gr2NvWxKBUqm4PPJKkQfoxHDBg4")
+        )
+        .is_ok());
+        assert_eq!(
+            build_uri(Action::Pay, &(String::from(settle) + "not-an-address")),
+            Err(X402UriError::InvalidPayAddress("recipient".to_string()))
+        );
+        assert_eq!(
+            build_uri(Action::Pay, "https://shop.example.com/x402/settle"),
+            Err(X402UriError::MissingPayRecipient)
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use core::{fmt, str::FromStr};
+
+use crate::{X402Uri, X402UriAction, X402UriError, X402UriPercent, X402UriResult, X402UriScheme};
+
+/// An owned x402-URI that can be stored, built and printed.
+/// Its [Display](fmt::Display) output is the canonical percent-encoded form
+/// which parses back into an equal [X402UriBuf].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct X402UriBuf {
+    uri_scheme: X402UriScheme,
+    action: X402UriAction,
+    uri: String,
+}
+
+impl X402UriBuf {
+    pub fn new(x402_uri: &str) -> X402UriResult<Self> {
+        X402Uri::new(x402_uri).map(|parsed| parsed.to_buf())
+    }
+
+    pub fn builder() -> X402UriBuilder {
+        X402UriBuilder::new()
+    }
+
+    pub fn uri_scheme(&self) -> X402UriScheme {
+        self.uri_scheme
+    }
+
+    pub fn action(&self) -> X402UriAction {
+        self.action
+    }
+
+    /// The decoded resource URI after `x402://<action>/`
+    pub fn uri(&self) -> &str {
+        self.uri.as_str()
+    }
+
+    pub(crate) fn write_canonical(
+        f: &mut fmt::Formatter<'_>,
+        action: X402UriAction,
+        uri: &str,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "{}//{}/{}",
+            X402Uri::SCHEME,
+            action,
+            X402UriPercent::encode(uri)
+        )
+    }
+}
+
+impl fmt::Display for X402UriBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Self::write_canonical(f, self.action, &self.uri)
+    }
+}
+
+impl FromStr for X402UriBuf {
+    type Err = X402UriError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl From<&X402Uri<'_>> for X402UriBuf {
+    fn from(value: &X402Uri<'_>) -> Self {
+        Self {
+            uri_scheme: value.uri_scheme(),
+            action: value.action(),
+            uri: value.uri().to_string(),
+        }
+    }
+}
+
+impl From<X402Uri<'_>> for X402UriBuf {
+    fn from(value: X402Uri<'_>) -> Self {
+        (&value).into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for X402UriBuf {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for X402UriBuf {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+
+        Self::new(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Builds a [X402UriBuf] for a resource.
+/// The action defaults to [X402UriAction::Discover] and the scheme to [X402UriScheme::Https].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct X402UriBuilder {
+    uri_scheme: X402UriScheme,
+    action: X402UriAction,
+    uri: Option<String>,
+}
+
+impl X402UriBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_action(&mut self, action: X402UriAction) -> &mut Self {
+        self.action = action;
+
+        self
+    }
+
+    pub fn set_uri_scheme(&mut self, uri_scheme: X402UriScheme) -> &mut Self {
+        self.uri_scheme = uri_scheme;
+
+        self
+    }
+
+    /// The URI of the resource. If it has no scheme, the scheme set on the builder is prepended.
+    pub fn set_uri(&mut self, uri: &str) -> &mut Self {
+        self.uri.replace(uri.to_string());
+
+        self
+    }
+
+    pub fn build(&self) -> X402UriResult<X402UriBuf> {
+        let uri = self.uri.as_ref().ok_or(X402UriError::MissingResourceUri)?;
+
+        let uri = match X402UriScheme::try_from(uri.as_str()) {
+            Ok(uri_scheme) if uri_scheme == self.uri_scheme => uri.clone(),
+            Ok(_) => return Err(X402UriError::UriSchemeMismatch),
+            Err(_) if uri.contains("://") => return Err(X402UriError::UnsupportedUriScheme),
+            Err(_) => self.uri_scheme.as_str().to_string() + uri.as_str(),
+        };
+
+        Ok(X402UriBuf {
+            uri_scheme: self.uri_scheme,
+            action: self.action,
+            uri,
+        })
+    }
+}
+
+#[cfg(test)]
+mod x402_uri_buf_sanity {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_build_and_display() {
+        let built = X402UriBuf::builder()
+            .set_action(X402UriAction::Discover)
+            .set_uri_scheme(X402UriScheme::Https)
+            .set_uri("https://lagoon.markets/x402/discover")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            built.to_string(),
+            "x402://discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover"
+        );
+        assert_eq!(built.to_string().parse::<X402UriBuf>().unwrap(), built);
+
+        let built = X402UriBuf::builder()
+            .set_action(X402UriAction::Once)
+            .set_uri_scheme(X402UriScheme::A2a)
+            .set_uri("example.com/agent?task=summarize")
+            .build()
+            .unwrap();
+        assert_eq!(built.uri(), "a2a://example.com/agent?task=summarize");
+        assert_eq!(
+            built.to_string(),
+            "x402://once/a2a%3A%2F%2Fexample.com%2Fagent%3Ftask%3Dsummarize"
+        );
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert_eq!(
+            X402UriBuilder::new().build(),
+            Err(X402UriError::MissingResourceUri)
+        );
+        assert_eq!(
+            X402UriBuilder::new()
+                .set_uri_scheme(X402UriScheme::Mcp)
+                .set_uri("https://example.com")
+                .build(),
+            Err(X402UriError::UriSchemeMismatch)
+        );
+        assert_eq!(
+            X402UriBuilder::new().set_uri("ftp://example.com").build(),
+            Err(X402UriError::UnsupportedUriScheme)
+        );
+    }
+
+    #[test]
+    fn test_borrowed_to_owned() {
+        let x402_uri = "x402://subscribe/https://example.com/topic?author=Toly%20The%20Great";
+        let parsed = X402Uri::new(x402_uri).unwrap();
+        let owned = parsed.to_buf();
+
+        assert_eq!(owned.action(), X402UriAction::Subscribe);
+        assert_eq!(owned.uri(), parsed.uri());
+        assert_eq!(parsed.to_string(), owned.to_string());
+        assert_eq!(owned.to_string().parse::<X402UriBuf>().unwrap(), owned);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let owned = X402UriBuf::new("x402://once/mcp://example.com/book").unwrap();
+        let json = serde_json::to_string(&owned).unwrap();
+
+        assert_eq!(json, "\"x402://once/mcp%3A%2F%2Fexample.com%2Fbook\"");
+        assert_eq!(serde_json::from_str::<X402UriBuf>(&json).unwrap(), owned);
+        assert!(serde_json::from_str::<X402UriBuf>("\"x402://buy/https://example.com\"").is_err());
+    }
+
+    fn any_action() -> impl Strategy<Value = X402UriAction> {
+        prop_oneof![
+            Just(X402UriAction::Discover),
+            Just(X402UriAction::Subscribe),
+            Just(X402UriAction::Unsubscribe),
+            Just(X402UriAction::Once),
+            Just(X402UriAction::Pay),
+        ]
+    }
+
+    fn any_scheme() -> impl Strategy<Value = X402UriScheme> {
+        prop_oneof![
+            Just(X402UriScheme::Https),
+            Just(X402UriScheme::A2a),
+            Just(X402UriScheme::Mcp),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn parse_display_round_trip(
+            action in any_action(),
+            uri_scheme in any_scheme(),
+            rest in "\\PC*",
+        ) {
+            let built = X402UriBuf::builder()
+                .set_action(action)
+                .set_uri_scheme(uri_scheme)
+                .set_uri(&(uri_scheme.as_str().to_string() + rest.as_str()))
+                .build()
+                .unwrap();
+
+            let displayed = built.to_string();
+            let parsed = X402Uri::new(&displayed).unwrap();
+
+            prop_assert_eq!(parsed.to_buf(), built.clone());
+            prop_assert_eq!(displayed.parse::<X402UriBuf>().unwrap(), built);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{
+    X402Uri, X402UriAction, X402UriAuthority, X402UriBuf, X402UriBuilder, X402UriResult,
+    X402UriScheme,
+};
+
+/// The canonical form of a resource URI, used as the identity of a resource.
+/// Resource URIs that differ only in case of the scheme and host, a default port,
+/// escaping, dot segments, a trailing `/` or the order of query parameters
+/// have the same canonical form.
+pub struct X402UriCanonical;
+
+impl X402UriCanonical {
+    const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+    /// Canonicalizes a resource URI like `HTTPS://Example.COM:443/a/?b=2&a=1`
+    /// into `https://example.com/a?a=1&b=2`
+    pub fn resource_uri(uri: &str) -> X402UriResult<String> {
+        Self::canonicalize(uri, false)
+    }
+
+    /// Canonicalizes the resource URI of a x402-URI. The Subscription Data
+    /// of a `subscribe/` URI stays the last query parameter.
+    pub fn x402_resource_uri(action: X402UriAction, uri: &str) -> X402UriResult<String> {
+        Self::canonicalize(uri, action == X402UriAction::Subscribe)
+    }
+
+    fn canonicalize(uri: &str, keep_last_param: bool) -> X402UriResult<String> {
+        let authority = X402UriAuthority::parse(uri)?;
+        let scheme = authority.scheme().to_ascii_lowercase();
+        let default_port = X402UriScheme::try_from(uri)
+            .ok()
+            .and_then(|uri_scheme| uri_scheme.default_port());
+
+        let (rest, fragment) = match authority.rest().split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (authority.rest(), None),
+        };
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+
+        let mut canonical = scheme + "://";
+
+        if let Some(userinfo) = authority.userinfo() {
+            canonical.push_str(&Self::normalize_escapes(userinfo));
+            canonical.push('@');
+        }
+
+        canonical.push_str(&authority.host().trim_end_matches('.').to_ascii_lowercase());
+
+        if let Some(port) = authority.port().filter(|port| Some(*port) != default_port) {
+            canonical.push(':');
+            canonical.push_str(&port.to_string());
+        }
+
+        canonical.push_str(&Self::normalize_path(path));
+
+        if let Some(query) = query.map(|query| Self::sort_query(query, keep_last_param)) {
+            if !query.is_empty() {
+                canonical.push('?');
+                canonical.push_str(&query);
+            }
+        }
+
+        if let Some(fragment) = fragment.filter(|fragment| !fragment.is_empty()) {
+            canonical.push('#');
+            canonical.push_str(&Self::normalize_escapes(fragment));
+        }
+
+        Ok(canonical)
+    }
+
+    /// Removes dot segments and the trailing `/`. An empty path becomes `/`.
+    fn normalize_path(path: &str) -> String {
+        // Browsers treat `\` like `/`
+        let path = Self::normalize_escapes(&path.replace('\\', "/"));
+
+        let mut segments = Vec::<&str>::new();
+        path.split('/').skip(1).for_each(|segment| match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        });
+
+        while segments.last().is_some_and(|segment| segment.is_empty()) {
+            segments.pop();
+        }
+
+        String::from("/") + segments.join("/").as_str()
+    }
+
+    /// Sorts the query parameters by their name, parameters with the same name keep their order
+    fn sort_query(query: &str, keep_last_param: bool) -> String {
+        let mut params = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(Self::normalize_escapes)
+            .collect::<Vec<String>>();
+
+        let last_param = if keep_last_param { params.pop() } else { None };
+
+        params.sort_by(|a, b| {
+            let name = |param: &String| param.split('=').next().unwrap_or_default().to_string();
+
+            name(a).cmp(&name(b))
+        });
+        params.extend(last_param);
+
+        params.join("&")
+    }
+
+    /// Decodes escaped unreserved characters, uppercases the hex digits of other escapes
+    /// and escapes characters that are not allowed in a URI
+    fn normalize_escapes(component: &str) -> String {
+        let bytes = component.as_bytes();
+        let mut normalized = String::with_capacity(component.len());
+        let mut index = 0usize;
+
+        while index < bytes.len() {
+            let byte = bytes[index];
+
+            let escaped = (byte == b'%')
+                .then(|| bytes.get(index + 1..index + 3))
+                .flatten()
+                .and_then(|hex| {
+                    let high = (hex[0] as char).to_digit(16)?;
+                    let low = (hex[1] as char).to_digit(16)?;
+
+                    Some((high * 16 + low) as u8)
+                });
+
+            match escaped {
+                Some(decoded) if Self::is_unreserved(decoded) => {
+                    normalized.push(decoded as char);
+                    index += 3;
+                }
+                Some(decoded) => {
+                    Self::push_escaped(&mut normalized, decoded);
+                    index += 3;
+                }
+                None if byte != b'%' && (Self::is_unreserved(byte) || Self::is_reserved(byte)) => {
+                    normalized.push(byte as char);
+                    index += 1;
+                }
+                None => {
+                    Self::push_escaped(&mut normalized, byte);
+                    index += 1;
+                }
+            }
+        }
+
+        normalized
+    }
+
+    fn push_escaped(output: &mut String, byte: u8) {
+        output.push('%');
+        output.push(Self::HEX_DIGITS[(byte >> 4) as usize] as char);
+        output.push(Self::HEX_DIGITS[(byte & 0x0F) as usize] as char);
+    }
+
+    fn is_unreserved(byte: u8) -> bool {
+        byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+    }
+
+    fn is_reserved(byte: u8) -> bool {
+        matches!(
+            byte,
+            b':' | b'/'
+                | b'?'
+                | b'#'
+                | b'['
+                | b']'
+                | b'@'
+                | b'!'
+                | b'$'
+                | b'&'
+                | b'\''
+                | b'('
+                | b')'
+                | b'*'
+                | b'+'
+                | b','
+                | b';'
+                | b'='
+        )
+    }
+}
+
+impl X402Uri<'_> {
+    /// The x402-URI with its resource URI in canonical form
+    pub fn canonical(&self) -> X402UriResult<X402UriBuf> {
+        self.to_buf().canonical()
+    }
+}
+
+impl X402UriBuf {
+    /// The x402-URI with its resource URI in canonical form
+    pub fn canonical(&self) -> X402UriResult<X402UriBuf> {
+        let uri = X402UriCanonical::x402_resource_uri(self.action(), self.uri())?;
+
+        X402UriBuilder::new()
+            .set_action(self.action())
+            .set_uri_scheme(self.uri_scheme())
+            .set_uri(&uri)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod x402_uri_canonical_sanity {
+    use super::*;
+
+    fn canonical(uri: &str) -> String {
+        X402UriCanonical::resource_uri(uri).unwrap()
+    }
+
+    #[test]
+    fn test_same_resource() {
+        let expected = "https://example.com/a";
+
+        [
+            "https://example.com/a",
+            "HTTPS://Example.COM/a",
+            "https://example.com/a/",
+            "https://example.com:443/a",
+            "https://example.com./%61",
+            "https://example.com/b/../a",
+            "https://example.com/./a",
+            "https://example.com\\a",
+        ]
+        .iter()
+        .for_each(|uri| assert_eq!(canonical(uri), expected, "{uri}"));
+
+        assert_eq!(canonical("https://example.com"), "https://example.com/");
+        assert_eq!(canonical("https://example.com/"), "https://example.com/");
+        assert_eq!(canonical("https://example.com?"), "https://example.com/");
+        assert_eq!(
+            canonical("https://example.com:8443/a"),
+            "https://example.com:8443/a"
+        );
+        assert_eq!(
+            canonical("mcp://Example.com:443/tools"),
+            "mcp://example.com:443/tools"
+        );
+    }
+
+    #[test]
+    fn test_escapes_and_query() {
+        assert_eq!(
+            canonical("https://example.com/caf%c3%a9 book?q=a%2fb"),
+            "https://example.com/caf%C3%A9%20book?q=a%2Fb"
+        );
+        assert_eq!(
+            canonical("https://example.com/café"),
+            "https://example.com/caf%C3%A9"
+        );
+        assert_eq!(
+            canonical("https://example.com/100%"),
+            "https://example.com/100%25"
+        );
+        assert_eq!(
+            canonical("https://example.com/x402?version=1&author=Toly&&tag=b&tag=a#Top"),
+            "https://example.com/x402?author=Toly&tag=b&tag=a&version=1#Top"
+        );
+        assert_eq!(
+            canonical(&canonical("https://EXAMPLE.com/x/./y/%7euser/?b=%2F&a=1")),
+            canonical("https://EXAMPLE.com/x/./y/%7euser/?b=%2F&a=1")
+        );
+    }
+
+    #[test]
+    fn test_x402_uri() {
+        let a =
+            X402Uri::new("x402://discover/HTTPS%3A%2F%2FLagoon.Markets%3A443%2Fx402%2Fdiscover%2F")
+                .unwrap()
+                .canonical()
+                .unwrap();
+        let b = X402Uri::new("x402://Discover/https://lagoon.markets/x402/discover")
+            .unwrap()
+            .canonical()
+            .unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(a.uri(), "https://lagoon.markets/x402/discover");
+
+        let subscribe = X402UriBuf::new("x402://subscribe/https://example.com/topic?z=1&a=2&e30=")
+            .unwrap()
+            .canonical()
+            .unwrap();
+        assert_eq!(subscribe.uri(), "https://example.com/topic?a=2&z=1&e30=");
+    }
+}
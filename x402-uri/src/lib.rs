@@ -1,43 +1,93 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+use std::borrow::Cow;
+
+mod percent;
+pub use percent::*;
+
+mod buf;
+pub use buf::*;
+
+mod authority;
+pub use authority::*;
+
+mod policy;
+pub use policy::*;
+
+mod canonical;
+pub use canonical::*;
+
+mod pay;
+pub use pay::*;
+
+mod solana_pay;
+pub use solana_pay::*;
+
+mod ndef;
+pub use ndef::*;
+
+mod lint;
+pub use lint::*;
+
+#[cfg(feature = "subscription")]
+mod subscription;
+#[cfg(feature = "subscription")]
+pub use subscription::*;
+
+#[cfg(feature = "qr")]
+mod qr;
+#[cfg(feature = "qr")]
+pub use qr::*;
+
+#[cfg(feature = "signature")]
+mod signature;
+#[cfg(feature = "signature")]
+pub use signature::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct X402Uri<'x> {
     uri_scheme: X402UriScheme,
     action: X402UriAction,
-    uri: &'x str,
+    raw: &'x str,
+    raw_uri: &'x str,
+    uri: Cow<'x, str>,
 }
 
 impl<'x> X402Uri<'x> {
-    pub fn new(x402_uri: &'x str) -> X402UriResult<Self> {
-        let scheme = "x402://";
-
-        if !x402_uri.starts_with(scheme) {
-            return Err(X402UriError::UriSchemeStartsWithX402Scheme);
-        }
+    pub const SCHEME: &'static str = "x402:";
 
-        let trim_scheme = x402_uri.trim_start_matches(scheme);
+    pub fn new(x402_uri: &'x str) -> X402UriResult<Self> {
+        let trim_scheme = x402_uri
+            .get(..Self::SCHEME.len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case(Self::SCHEME))
+            .map(|_| &x402_uri[Self::SCHEME.len()..])
+            .ok_or(X402UriError::UriSchemeStartsWithX402Scheme)?;
+        // Browsers and QR scanners sometimes drop the `//` or collapse it into a single `/`
+        let trim_scheme = trim_scheme
+            .strip_prefix("//")
+            .or(trim_scheme.strip_prefix('/'))
+            .unwrap_or(trim_scheme);
 
-        if let Some((action_raw, rest)) = trim_scheme.split_once('/') {
-            let action: X402UriAction = action_raw.try_into()?;
+        let (action_raw, rest) = trim_scheme
+            .split_once('/')
+            .ok_or(X402UriError::ExpectedAnActionInUri)?;
+        let action: X402UriAction = action_raw.try_into()?;
 
-            let mut outcome = Self {
-                uri_scheme: X402UriScheme::Https,
-                action,
-                uri: rest,
-            };
+        // Unencoded inner URIs are borrowed as is, otherwise the inner URI is decoded once
+        let uri = if X402UriScheme::try_from(rest).is_ok() {
+            Cow::Borrowed(rest)
+        } else {
+            X402UriPercent::decode(rest)?
+        };
 
-            if rest.starts_with(X402UriScheme::HTTPS_SCHEME) {
-                outcome.uri_scheme = X402UriScheme::Https;
-            } else if rest.starts_with(X402UriScheme::A2A_SCHEME) {
-                outcome.uri_scheme = X402UriScheme::A2a;
-            } else if rest.starts_with(X402UriScheme::MCP_SCHEME) {
-                outcome.uri_scheme = X402UriScheme::Mcp;
-            } else {
-                return Err(X402UriError::UnsupportedUriScheme);
-            }
+        let uri_scheme =
+            X402UriScheme::try_from(uri.as_ref()).or(Err(X402UriError::UnsupportedUriScheme))?;
 
-            Ok(outcome)
-        } else {
-            Err(X402UriError::ExpectedAnActionInUri)
-        }
+        Ok(Self {
+            uri_scheme,
+            action,
+            raw: x402_uri,
+            raw_uri: rest,
+            uri,
+        })
     }
 
     pub fn uri_scheme(&self) -> X402UriScheme {
@@ -48,8 +98,36 @@ impl<'x> X402Uri<'x> {
         self.action
     }
 
+    /// The decoded resource URI after `x402://<action>/`
     pub fn uri(&self) -> &str {
-        self.uri
+        self.uri.as_ref()
+    }
+
+    /// The resource URI exactly as it appeared in the x402-URI, before any percent-decoding
+    pub fn raw_uri(&self) -> &'x str {
+        self.raw_uri
+    }
+
+    /// The whole x402-URI exactly as it was parsed
+    pub fn raw(&self) -> &'x str {
+        self.raw
+    }
+
+    /// Returns `true` if the resource URI was percent-encoded in the x402-URI
+    pub fn is_percent_encoded(&self) -> bool {
+        matches!(self.uri, Cow::Owned(_))
+    }
+
+    /// Copies the parsed x402-URI into an owned [X402UriBuf]
+    pub fn to_buf(&self) -> X402UriBuf {
+        self.into()
+    }
+}
+
+/// Writes the canonical form `x402://<action>/<percent-encoded URI>`
+impl core::fmt::Display for X402Uri<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        X402UriBuf::write_canonical(f, self.action, self.uri())
     }
 }
 
@@ -69,6 +147,148 @@ pub enum X402UriError {
     InvalidX402UriAction,
     #[error("The URI after the action `x402://<action>/<this URI>` is not supported. Only `https://`, `a2a://` and `mcp://` are supported.")]
     UnsupportedUriScheme,
+    /// A `%` in the URI is not followed by two hexadecimal digits
+    #[error("Malformed percent-encoding at byte `{0}` of the URI after the action. A `%` must be followed by two hexadecimal digits")]
+    MalformedPercentEncoding(usize),
+    /// The percent-decoded URI is not valid UTF-8
+    #[error("The percent-decoded URI after the action is not valid UTF-8")]
+    PercentDecodedUriIsNotUtf8,
+    /// The [X402UriBuilder] was not given the URI of the resource
+    #[error("The URI of the resource is required to build a x402Uri")]
+    MissingResourceUri,
+    /// The URI given to the [X402UriBuilder] has a different scheme from the one set on the builder
+    #[error(
+        "The URI of the resource has a different scheme from the scheme set on the x402Uri builder"
+    )]
+    UriSchemeMismatch,
+    /// Only `x402://subscribe/` URIs carry Subscription Data
+    #[error("Subscription Data can only be decoded from a `x402://subscribe/` URI")]
+    SubscriptionDataRequiresSubscribeAction,
+    /// The subscribe URI has no base64 encoded Subscription Data in its query
+    #[error("The subscribe URI does not contain the base64 encoded Subscription Data")]
+    MissingSubscriptionData,
+    /// The Subscription Data is not valid base64
+    #[error("The Subscription Data in the subscribe URI is not valid base64")]
+    InvalidSubscriptionDataBase64,
+    /// The Subscription Data does not match the Subscription Data JSON schema
+    #[error(
+        "The Subscription Data is not valid JSON for the Subscription Data schema. Error: `{0}`"
+    )]
+    InvalidSubscriptionDataJson(String),
+    /// The `unsubscribe` field of the Subscription Data is not a valid x402Uri
+    #[error("The `unsubscribe` URI in the Subscription Data is not a valid x402Uri. Error: `{0}`")]
+    InvalidUnsubscribeUri(Box<X402UriError>),
+    /// The `unsubscribe` field of the Subscription Data does not use the `unsubscribe` action
+    #[error(
+        "The `unsubscribe` URI in the Subscription Data must start with `x402://unsubscribe/`"
+    )]
+    UnsubscribeUriActionMismatch,
+    /// The `maxAmountRequired` of the `x402Payload` is not an unsigned 64 bit integer
+    #[error("The `maxAmountRequired` of the `x402Payload` in the Subscription Data is not an unsigned 64 bit integer")]
+    InvalidSubscriptionAmount,
+    /// A reserved x402 query parameter of the resource URI of a subscribe x402Uri appears more than once
+    #[error("The `{0}` query parameter of the subscribed resource URI must only appear once")]
+    DuplicateQueryParameter(String),
+    /// The x402Uri is too long to fit in the largest QR code
+    #[error("The x402Uri is too long to be encoded into a QR code")]
+    QrDataTooLong,
+    /// The QR code encoder rejected the x402Uri
+    #[error("Unable to encode the x402Uri into a QR code. Error: `{0}`")]
+    QrEncoding(String),
+    /// The logo safe zone is not a fraction in the range `(0, 0.3]` of the QR code width
+    #[error("The logo safe zone of a QR code must be greater than 0 and at most 0.3 of the QR code width")]
+    InvalidQrLogoSafeZone,
+    /// Writing the PNG image of the QR code failed
+    #[error("Unable to render the QR code as a PNG image. Error: `{0}`")]
+    QrPngEncoding(String),
+    /// The x402Uri is longer than the [ValidationPolicy] allows
+    #[error("The x402Uri is {length} bytes long which exceeds the maximum of {max} bytes")]
+    UriTooLong { length: usize, max: usize },
+    /// The resource URI has no host
+    #[error("The resource URI of the x402Uri has no host")]
+    MissingHost,
+    /// The resource URI contains a `user:password@` part which can hide the real host
+    #[error("The resource URI of the x402Uri must not contain credentials")]
+    CredentialsInUri,
+    /// The host is not a valid domain name
+    #[error("The host `{0}` is not a valid domain name")]
+    InvalidHost(String),
+    /// The port is not a number in the range `0..=65535`
+    #[error("The port of the resource URI is invalid")]
+    InvalidPort,
+    /// The host is an IP address and the [ValidationPolicy] does not allow IP literals
+    #[error("The host `{0}` is an IP address which is not allowed")]
+    IpLiteralHost(String),
+    /// The host is a local host name and the [ValidationPolicy] does not allow it
+    #[error("The host `{0}` is a local host which is not allowed")]
+    LocalhostNotAllowed(String),
+    /// The host is an internationalized domain name and the [ValidationPolicy] does not allow it
+    #[error("The host `{0}` is an internationalized domain name which is not allowed")]
+    IdnHostNotAllowed(String),
+    /// The host mixes scripts or uses lookalike letters to imitate a Latin domain name
+    #[error("The host `{0}` looks like a homograph of another domain name")]
+    HomographHost(String),
+    /// The host is in the deny list of the [ValidationPolicy]
+    #[error("The host `{0}` is denied")]
+    HostDenied(String),
+    /// The host is not in the allow list of the [ValidationPolicy]
+    #[error("The host `{0}` is not in the list of allowed hosts")]
+    HostNotAllowed(String),
+    /// Payment parameters were requested from a x402-URI whose action is not `pay`
+    #[error("Only `x402://pay/` URIs contain payment parameters")]
+    PayRequestRequiresPayAction,
+    /// The `recipient` of a `x402://pay/` or Solana Pay URI is missing
+    #[error("The `recipient` of the payment request is missing")]
+    MissingPayRecipient,
+    /// The named parameter of a `x402://pay/` or Solana Pay URI is not a base58 Solana address
+    #[error("The `{0}` of the payment request is not a valid base58 address")]
+    InvalidPayAddress(String),
+    /// The `amount` is not a non-negative decimal number that fits into a u64
+    #[error(
+        "The `amount` of the payment request must be a non-negative decimal number like `1.5`"
+    )]
+    InvalidPayAmount,
+    /// The `amount` has more decimals than the asset supports
+    #[error("The `amount` of the payment request has more decimals than the asset")]
+    PayAmountExceedsAssetDecimals,
+    /// A query parameter of a `x402://pay/` or Solana Pay URI appears more than once
+    #[error("The `{0}` of the payment request must only appear once")]
+    DuplicatePayParameter(String),
+    /// The bytes are not a valid ed25519 signing key
+    #[error("The bytes are not a valid ed25519 keypair")]
+    InvalidSigningKey,
+    /// Only one of the `pk` and `sig` query parameters is present
+    #[error("The signed x402Uri is missing the `{0}` query parameter")]
+    MissingSignatureParameter(String),
+    /// The `pk` or `sig` query parameter appears more than once
+    #[error("The `{0}` query parameter of a signed x402Uri must only appear once")]
+    DuplicateSignatureParameter(String),
+    /// The `pk` query parameter is not a base58 encoded ed25519 public key
+    #[error("The `pk` query parameter is not a valid base58 encoded ed25519 public key")]
+    InvalidSignaturePublicKey,
+    /// The `sig` query parameter is not a base58 encoded ed25519 signature
+    #[error("The `sig` query parameter is not a valid base58 encoded ed25519 signature")]
+    InvalidSignatureEncoding,
+    /// The signature does not match the x402Uri, it was changed after it was signed
+    #[error("The signature does not match the x402Uri. It may have been tampered with")]
+    SignatureMismatch,
+    /// The URI does not start with the `solana:` scheme
+    #[error("The URI is not a Solana Pay URI, it must start with `solana:`")]
+    InvalidSolanaPayUri,
+    /// Only `x402://once/` URIs can be converted into Solana Pay transaction requests
+    #[error(
+        "Only `x402://once/https://` URIs can be converted into Solana Pay transaction requests"
+    )]
+    SolanaPayRequiresOnceAction,
+    /// The NDEF message ends before the end of a record or TLV block
+    #[error("The NDEF message is truncated")]
+    TruncatedNdefMessage,
+    /// The NDEF message is chunked or has a record that is not valid
+    #[error("The NDEF message has an invalid or chunked record")]
+    InvalidNdefRecord,
+    /// The NDEF message has no URI record with a valid x402Uri
+    #[error("The NDEF message does not contain a URI record with a valid x402Uri")]
+    MissingNdefX402UriRecord,
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
@@ -78,6 +298,7 @@ pub enum X402UriAction {
     Subscribe,
     Unsubscribe,
     Once,
+    Pay,
 }
 
 impl X402UriAction {
@@ -85,6 +306,23 @@ impl X402UriAction {
     pub const SUBSCRIBE: &str = "subscribe";
     pub const UNSUBSCRIBE: &str = "unsubscribe";
     pub const ONCE: &str = "once";
+    pub const PAY: &str = "pay";
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Discover => Self::DISCOVER,
+            Self::Subscribe => Self::SUBSCRIBE,
+            Self::Unsubscribe => Self::UNSUBSCRIBE,
+            Self::Once => Self::ONCE,
+            Self::Pay => Self::PAY,
+        }
+    }
+}
+
+impl core::fmt::Display for X402UriAction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl TryFrom<&str> for X402UriAction {
@@ -92,10 +330,11 @@ impl TryFrom<&str> for X402UriAction {
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let parsed = match value {
-            Self::DISCOVER => Self::Discover,
-            Self::SUBSCRIBE => Self::Subscribe,
-            Self::UNSUBSCRIBE => Self::Unsubscribe,
-            Self::ONCE => Self::Once,
+            _m if value.eq_ignore_ascii_case(Self::DISCOVER) => Self::Discover,
+            _m if value.eq_ignore_ascii_case(Self::SUBSCRIBE) => Self::Subscribe,
+            _m if value.eq_ignore_ascii_case(Self::UNSUBSCRIBE) => Self::Unsubscribe,
+            _m if value.eq_ignore_ascii_case(Self::ONCE) => Self::Once,
+            _m if value.eq_ignore_ascii_case(Self::PAY) => Self::Pay,
             _ => return Err(X402UriError::InvalidX402UriAction),
         };
 
@@ -115,16 +354,44 @@ impl X402UriScheme {
     pub const HTTPS_SCHEME: &str = "https://";
     pub const A2A_SCHEME: &str = "a2a://";
     pub const MCP_SCHEME: &str = "mcp://";
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Https => Self::HTTPS_SCHEME,
+            Self::A2a => Self::A2A_SCHEME,
+            Self::Mcp => Self::MCP_SCHEME,
+        }
+    }
+
+    /// The port implied when a resource URI with this scheme has no port
+    pub fn default_port(&self) -> Option<u16> {
+        match self {
+            Self::Https => Some(443),
+            Self::A2a | Self::Mcp => None,
+        }
+    }
+}
+
+impl core::fmt::Display for X402UriScheme {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl TryFrom<&str> for X402UriScheme {
     type Error = X402UriError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let starts_with = |scheme: &str| {
+            value
+                .get(..scheme.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+        };
+
         let parsed = match value {
-            _m if value.starts_with(Self::HTTPS_SCHEME) => Self::Https,
-            _m if value.starts_with(Self::A2A_SCHEME) => Self::A2a,
-            _m if value.starts_with(Self::MCP_SCHEME) => Self::Mcp,
+            _m if starts_with(Self::HTTPS_SCHEME) => Self::Https,
+            _m if starts_with(Self::A2A_SCHEME) => Self::A2a,
+            _m if starts_with(Self::MCP_SCHEME) => Self::Mcp,
             _ => return Err(X402UriError::InvalidX402UriScheme),
         };
 
@@ -182,4 +449,95 @@ mod x402_uri_sanity {
         let x402_uri = "x402://unsubscribe/foo://example.com/apiv1/So11111111111111111111111111111111111111112";
         assert!(X402Uri::new(x402_uri).is_err());
     }
+
+    #[test]
+    fn test_percent_encoded() {
+        let x402_uri = "x402://discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover";
+        let parsed = X402Uri::new(x402_uri).unwrap();
+        assert_eq!(parsed.action(), X402UriAction::Discover);
+        assert_eq!(parsed.uri_scheme(), X402UriScheme::Https);
+        assert_eq!(parsed.uri(), "https://lagoon.markets/x402/discover");
+        assert_eq!(
+            parsed.raw_uri(),
+            "https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover"
+        );
+        assert_eq!(parsed.raw(), x402_uri);
+        assert!(parsed.is_percent_encoded());
+
+        let x402_uri = "x402://subscribe/a2a%3a%2f%2fexample.com%2Fapiv1";
+        let parsed = X402Uri::new(x402_uri).unwrap();
+        assert_eq!(parsed.uri_scheme(), X402UriScheme::A2a);
+        assert_eq!(parsed.uri(), "a2a://example.com/apiv1");
+
+        let x402_uri = "x402://once/mcp%3A%2F%2Fexample.com%2Fbook%3Fauthor%3DToly%20The%20Great";
+        let parsed = X402Uri::new(x402_uri).unwrap();
+        assert_eq!(parsed.uri_scheme(), X402UriScheme::Mcp);
+        assert_eq!(parsed.uri(), "mcp://example.com/book?author=Toly The Great");
+    }
+
+    #[test]
+    fn test_unencoded_is_borrowed() {
+        // Escapes inside an unencoded URI belong to that URI and are not decoded
+        let x402_uri = "x402://once/https://example.com/book?author=Toly%20The%20Great";
+        let parsed = X402Uri::new(x402_uri).unwrap();
+        assert!(!parsed.is_percent_encoded());
+        assert_eq!(
+            parsed.uri(),
+            "https://example.com/book?author=Toly%20The%20Great"
+        );
+        assert_eq!(parsed.uri(), parsed.raw_uri());
+    }
+
+    #[test]
+    fn test_browser_mangled() {
+        let expected = "https://lagoon.markets/x402/discover";
+
+        [
+            "x402:discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover",
+            "x402:/discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover",
+            "X402://discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover",
+            "x402://DISCOVER/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover",
+            "x402://Discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover",
+            "x402:discover/https://lagoon.markets/x402/discover",
+            "x402://discover/HTTPS%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover",
+        ]
+        .iter()
+        .for_each(|x402_uri| {
+            let parsed = X402Uri::new(x402_uri).unwrap();
+            assert_eq!(parsed.action(), X402UriAction::Discover);
+            assert_eq!(parsed.uri_scheme(), X402UriScheme::Https);
+            assert!(parsed.uri().eq_ignore_ascii_case(expected));
+        });
+
+        let parsed = X402Uri::new("x402:UNSUBSCRIBE/mcp://example.com").unwrap();
+        assert_eq!(parsed.action(), X402UriAction::Unsubscribe);
+    }
+
+    #[test]
+    fn test_percent_encoding_errors() {
+        assert_eq!(
+            X402Uri::new("x402://discover/https%3A%2F%2Fexample.com%2"),
+            Err(X402UriError::MalformedPercentEncoding(25))
+        );
+        assert_eq!(
+            X402Uri::new("x402://discover/https%3A%2F%2Fexample.com%zz"),
+            Err(X402UriError::MalformedPercentEncoding(25))
+        );
+        assert_eq!(
+            X402Uri::new("x402://discover/https%3A%2F%2Fexample.com%FF"),
+            Err(X402UriError::PercentDecodedUriIsNotUtf8)
+        );
+        assert_eq!(
+            X402Uri::new("x402://discover/foo%3A%2F%2Fexample.com"),
+            Err(X402UriError::UnsupportedUriScheme)
+        );
+        assert_eq!(
+            X402Uri::new("x402://buy/https%3A%2F%2Fexample.com"),
+            Err(X402UriError::InvalidX402UriAction)
+        );
+        assert_eq!(
+            X402Uri::new("x403://discover/https%3A%2F%2Fexample.com"),
+            Err(X402UriError::UriSchemeStartsWithX402Scheme)
+        );
+    }
 }
@@ -0,0 +1,177 @@
+use core::fmt;
+
+use crate::{ValidationPolicy, X402Uri, X402UriAction, X402UriError};
+
+/// A problem found by [X402UriLint::check]
+#[derive(Debug, PartialEq, Eq)]
+pub enum X402UriLint {
+    /// The app rejects the x402-URI with this error
+    Error(X402UriError),
+    /// The x402-URI parses but the strict [ValidationPolicy] rejects it
+    PolicyViolation(X402UriError),
+    /// A character that is not allowed in URIs and may be changed by browsers and QR scanners
+    UnencodedCharacter { character: char, index: usize },
+    /// The resource URI is not percent-encoded
+    NotPercentEncoded,
+    /// The `x402:` scheme is not lowercase or is missing the `//`
+    NonCanonicalScheme,
+    /// The action is not lowercase
+    NonCanonicalAction,
+    /// The x402-URI differs from its canonical form
+    NotCanonical { canonical: String },
+}
+
+impl X402UriLint {
+    /// Checks a x402-URI against the specification the same way the app parses it
+    pub fn check(x402_uri: &str) -> Vec<Self> {
+        let mut lints = Vec::<Self>::new();
+
+        x402_uri
+            .char_indices()
+            .filter(|(_, character)| !Self::is_uri_character(*character))
+            .for_each(|(index, character)| {
+                lints.push(Self::UnencodedCharacter { character, index })
+            });
+
+        let parsed = match X402Uri::new(x402_uri) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                lints.push(Self::Error(error));
+
+                return lints;
+            }
+        };
+
+        if let Err(error) = Self::check_action(&parsed) {
+            lints.push(Self::Error(error));
+        }
+
+        if let Err(error) = ValidationPolicy::default().validate(&parsed) {
+            lints.push(Self::PolicyViolation(error));
+        }
+
+        if !x402_uri.starts_with("x402://") {
+            lints.push(Self::NonCanonicalScheme);
+        }
+
+        if !x402_uri
+            .split_once(':')
+            .map(|(_, rest)| rest.trim_start_matches('/'))
+            .is_some_and(|rest| rest.starts_with(parsed.action().as_str()))
+        {
+            lints.push(Self::NonCanonicalAction);
+        }
+
+        if !parsed.is_percent_encoded() {
+            lints.push(Self::NotPercentEncoded);
+        }
+
+        match parsed.canonical() {
+            Ok(canonical) if canonical.to_string() != x402_uri => lints.push(Self::NotCanonical {
+                canonical: canonical.to_string(),
+            }),
+            Ok(_) => {}
+            Err(error) => lints.push(Self::Error(error)),
+        }
+
+        lints
+    }
+
+    /// Whether the app would reject the x402-URI
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error(_))
+    }
+
+    /// Checks the data that the action requires
+    fn check_action(parsed: &X402Uri<'_>) -> Result<(), X402UriError> {
+        match parsed.action() {
+            X402UriAction::Pay => parsed.pay_request().map(|_| ())?,
+            #[cfg(feature = "subscription")]
+            X402UriAction::Subscribe => parsed.subscription_data().map(|_| ())?,
+            _ => {}
+        }
+
+        #[cfg(feature = "signature")]
+        parsed.verify_signature()?;
+
+        Ok(())
+    }
+
+    /// Unreserved, reserved and `%` characters from RFC 3986
+    fn is_uri_character(character: char) -> bool {
+        character.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=%".contains(character)
+    }
+}
+
+impl fmt::Display for X402UriLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error(error) => write!(f, "error: {error}"),
+            Self::PolicyViolation(error) => {
+                write!(f, "warning: rejected by the strict validation policy: {error}")
+            }
+            Self::UnencodedCharacter { character, index } => write!(
+                f,
+                "warning: unencoded character `{}` at byte {index}, browsers and QR scanners may change it",
+                character.escape_default()
+            ),
+            Self::NotPercentEncoded => write!(
+                f,
+                "warning: the resource URI is not percent-encoded, all URLs in a x402-URI must be URL encoded"
+            ),
+            Self::NonCanonicalScheme => write!(f, "warning: use the `x402://` scheme"),
+            Self::NonCanonicalAction => write!(f, "warning: the action should be lowercase"),
+            Self::NotCanonical { canonical } => {
+                write!(f, "note: the canonical form is `{canonical}`")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod x402_uri_lint_sanity {
+    use super::*;
+
+    #[test]
+    fn test_canonical_is_clean() {
+        assert_eq!(
+            X402UriLint::check("x402://discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover"),
+            Vec::default()
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let lints = X402UriLint::check("X402:DISCOVER/https://lagoon.markets/x402 discover");
+
+        assert!(lints.contains(&X402UriLint::UnencodedCharacter {
+            character: ' ',
+            index: 41
+        }));
+        assert!(lints.contains(&X402UriLint::NotPercentEncoded));
+        assert!(lints.contains(&X402UriLint::NonCanonicalScheme));
+        assert!(lints.contains(&X402UriLint::NonCanonicalAction));
+        assert!(lints.contains(&X402UriLint::NotCanonical {
+            canonical: "x402://discover/https%3A%2F%2Flagoon.markets%2Fx402%2520discover"
+                .to_string()
+        }));
+        assert!(!lints.iter().any(X402UriLint::is_error));
+
+        assert_eq!(
+            X402UriLint::check("x402://once/https%3A%2F%2F127.0.0.1%2Fbook"),
+            vec![X402UriLint::PolicyViolation(X402UriError::IpLiteralHost(
+                "127.0.0.1".to_string()
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            X402UriLint::check("x402://once/ftp://example.com"),
+            vec![X402UriLint::Error(X402UriError::UnsupportedUriScheme)]
+        );
+        assert!(X402UriLint::check("x402://pay/https%3A%2F%2Fexample.com")
+            .contains(&X402UriLint::Error(X402UriError::MissingPayRecipient)));
+    }
+}
@@ -0,0 +1,427 @@
+use crate::{X402Uri, X402UriBuf, X402UriError, X402UriResult};
+
+/// A NDEF message for NFC tags with a URI record for a x402-URI and an optional
+/// Android Application Record that launches an app when the tag is tapped.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct X402NdefMessage {
+    x402_uri: X402UriBuf,
+    android_package: Option<String>,
+}
+
+impl X402NdefMessage {
+    /// The package of the Lagoon Explorer Android app
+    pub const EXPLORER_PACKAGE: &str = "lagoon.markets.explorer";
+
+    pub fn new(x402_uri: X402UriBuf) -> Self {
+        Self {
+            x402_uri,
+            android_package: Option::None,
+        }
+    }
+
+    /// Adds an Android Application Record which makes Android open `package`
+    /// instead of asking which app should handle the x402-URI
+    pub fn set_android_package(&mut self, package: &str) -> &mut Self {
+        self.android_package.replace(package.to_string());
+
+        self
+    }
+
+    /// Adds an Android Application Record for [Self::EXPLORER_PACKAGE]
+    pub fn set_explorer_package(&mut self) -> &mut Self {
+        self.set_android_package(Self::EXPLORER_PACKAGE)
+    }
+
+    pub fn x402_uri(&self) -> &X402UriBuf {
+        &self.x402_uri
+    }
+
+    pub fn android_package(&self) -> Option<&str> {
+        self.android_package.as_deref()
+    }
+
+    /// Encodes the NDEF message, the URI record first and then the Android Application Record
+    pub fn encode(&self) -> Vec<u8> {
+        let uri_record = X402NdefRecord::uri(&self.x402_uri.to_string());
+        let records = match self.android_package.as_ref() {
+            Some(package) => vec![uri_record, X402NdefRecord::android_application(package)],
+            None => vec![uri_record],
+        };
+
+        let last = records.len() - 1;
+        records
+            .iter()
+            .enumerate()
+            .fold(Vec::new(), |mut outcome, (index, record)| {
+                record.encode(index == 0, index == last, &mut outcome);
+
+                outcome
+            })
+    }
+
+    /// Decodes a NDEF message like the bytes of Android's `NdefMessage.toByteArray()`.
+    /// The first URI record with a x402-URI is used, other records are ignored.
+    pub fn decode(bytes: &[u8]) -> X402UriResult<Self> {
+        let records = X402NdefRecord::decode_all(bytes)?;
+
+        let x402_uri = records
+            .iter()
+            .filter_map(|record| record.to_uri())
+            .find_map(|uri| X402Uri::new(&uri).ok().map(|parsed| parsed.to_buf()))
+            .ok_or(X402UriError::MissingNdefX402UriRecord)?;
+
+        let android_package = records
+            .iter()
+            .find(|record| {
+                record.tnf == X402NdefRecord::TNF_EXTERNAL
+                    && record.record_type == X402NdefRecord::ANDROID_APPLICATION_TYPE
+            })
+            .map(|record| {
+                String::from_utf8(record.payload.clone()).or(Err(X402UriError::InvalidNdefRecord))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            x402_uri,
+            android_package,
+        })
+    }
+
+    /// Decodes the raw payload read from a tag. This can be a NDEF message or the memory of
+    /// a NFC Forum Type 2 tag where the NDEF message is wrapped in a TLV block.
+    pub fn decode_tag(payload: &[u8]) -> X402UriResult<Self> {
+        match payload.first() {
+            Some(header) if header & X402NdefRecord::MESSAGE_BEGIN != 0 => Self::decode(payload),
+            _ => Self::decode(Self::find_ndef_tlv(payload)?),
+        }
+    }
+
+    fn find_ndef_tlv(payload: &[u8]) -> X402UriResult<&[u8]> {
+        const NULL_TLV: u8 = 0x00;
+        const NDEF_MESSAGE_TLV: u8 = 0x03;
+        const TERMINATOR_TLV: u8 = 0xFE;
+
+        let mut index = 0usize;
+
+        while let Some(&tag) = payload.get(index) {
+            match tag {
+                NULL_TLV => {
+                    index += 1;
+                    continue;
+                }
+                TERMINATOR_TLV => break,
+                _ => {}
+            }
+
+            let (length, value_start) = match payload.get(index + 1) {
+                Some(0xFF) => {
+                    let length = payload
+                        .get(index + 2..index + 4)
+                        .ok_or(X402UriError::TruncatedNdefMessage)?;
+
+                    (
+                        u16::from_be_bytes([length[0], length[1]]) as usize,
+                        index + 4,
+                    )
+                }
+                Some(length) => (*length as usize, index + 2),
+                None => return Err(X402UriError::TruncatedNdefMessage),
+            };
+
+            let value = payload
+                .get(value_start..value_start + length)
+                .ok_or(X402UriError::TruncatedNdefMessage)?;
+
+            if tag == NDEF_MESSAGE_TLV {
+                return Ok(value);
+            }
+
+            index = value_start + length;
+        }
+
+        Err(X402UriError::MissingNdefX402UriRecord)
+    }
+}
+
+/// A single NDEF record
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct X402NdefRecord {
+    tnf: u8,
+    record_type: Vec<u8>,
+    payload: Vec<u8>,
+}
+
+impl X402NdefRecord {
+    const MESSAGE_BEGIN: u8 = 0x80;
+    const MESSAGE_END: u8 = 0x40;
+    const CHUNKED: u8 = 0x20;
+    const SHORT_RECORD: u8 = 0x10;
+    const ID_LENGTH: u8 = 0x08;
+    const TNF_MASK: u8 = 0x07;
+
+    const TNF_WELL_KNOWN: u8 = 0x01;
+    const TNF_EXTERNAL: u8 = 0x04;
+    const URI_TYPE: &[u8] = b"U";
+    const ANDROID_APPLICATION_TYPE: &[u8] = b"android.com:pkg";
+
+    /// The prefixes abbreviated by the first byte of a URI record payload
+    const URI_PREFIXES: [&str; 36] = [
+        "",
+        "http://www.",
+        "https://www.",
+        "http://",
+        "https://",
+        "tel:",
+        "mailto:",
+        "ftp://anonymous:anonymous@",
+        "ftp://ftp.",
+        "ftps://",
+        "sftp://",
+        "smb://",
+        "nfs://",
+        "ftp://",
+        "dav://",
+        "news:",
+        "telnet://",
+        "imap:",
+        "rtsp://",
+        "urn:",
+        "pop:",
+        "sip:",
+        "sips:",
+        "tftp:",
+        "btspp://",
+        "btl2cap://",
+        "btgoep://",
+        "tcpobex://",
+        "irdaobex://",
+        "file://",
+        "urn:epc:id:",
+        "urn:epc:tag:",
+        "urn:epc:pat:",
+        "urn:epc:raw:",
+        "urn:epc:",
+        "urn:nfc:",
+    ];
+
+    /// A URI record, `x402:` has no abbreviation so the full URI is written
+    fn uri(uri: &str) -> Self {
+        let mut payload = vec![0u8];
+        payload.extend_from_slice(uri.as_bytes());
+
+        Self {
+            tnf: Self::TNF_WELL_KNOWN,
+            record_type: Self::URI_TYPE.to_vec(),
+            payload,
+        }
+    }
+
+    fn android_application(package: &str) -> Self {
+        Self {
+            tnf: Self::TNF_EXTERNAL,
+            record_type: Self::ANDROID_APPLICATION_TYPE.to_vec(),
+            payload: package.as_bytes().to_vec(),
+        }
+    }
+
+    fn to_uri(&self) -> Option<String> {
+        if self.tnf != Self::TNF_WELL_KNOWN || self.record_type != Self::URI_TYPE {
+            return None;
+        }
+
+        let (prefix, rest) = self.payload.split_first()?;
+        let prefix = Self::URI_PREFIXES.get(*prefix as usize)?;
+
+        core::str::from_utf8(rest)
+            .ok()
+            .map(|rest| String::from(*prefix) + rest)
+    }
+
+    fn encode(&self, message_begin: bool, message_end: bool, output: &mut Vec<u8>) {
+        let mut header = self.tnf;
+
+        if message_begin {
+            header |= Self::MESSAGE_BEGIN;
+        }
+        if message_end {
+            header |= Self::MESSAGE_END;
+        }
+
+        let short_record = self.payload.len() <= u8::MAX as usize;
+        if short_record {
+            header |= Self::SHORT_RECORD;
+        }
+
+        output.push(header);
+        output.push(self.record_type.len() as u8);
+
+        if short_record {
+            output.push(self.payload.len() as u8);
+        } else {
+            output.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        }
+
+        output.extend_from_slice(&self.record_type);
+        output.extend_from_slice(&self.payload);
+    }
+
+    fn decode_all(bytes: &[u8]) -> X402UriResult<Vec<Self>> {
+        let mut records = Vec::<Self>::new();
+        let mut index = 0usize;
+
+        loop {
+            let mut take = |length: usize| {
+                let taken = bytes
+                    .get(index..index + length)
+                    .ok_or(X402UriError::TruncatedNdefMessage)?;
+                index += length;
+
+                Ok::<_, X402UriError>(taken)
+            };
+
+            let header = take(1)?[0];
+
+            if header & Self::CHUNKED != 0 {
+                return Err(X402UriError::InvalidNdefRecord);
+            }
+
+            let type_length = take(1)?[0] as usize;
+            let payload_length = if header & Self::SHORT_RECORD != 0 {
+                take(1)?[0] as usize
+            } else {
+                let length = take(4)?;
+
+                u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize
+            };
+            let id_length = if header & Self::ID_LENGTH != 0 {
+                take(1)?[0] as usize
+            } else {
+                0
+            };
+
+            let record_type = take(type_length)?.to_vec();
+            take(id_length)?;
+            let payload = take(payload_length)?.to_vec();
+
+            records.push(Self {
+                tnf: header & Self::TNF_MASK,
+                record_type,
+                payload,
+            });
+
+            if header & Self::MESSAGE_END != 0 {
+                return Ok(records);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod x402_ndef_sanity {
+    use super::*;
+
+    fn x402_uri() -> X402UriBuf {
+        X402UriBuf::new("x402://discover/https://lagoon.markets/x402/discover").unwrap()
+    }
+
+    #[test]
+    fn test_uri_record() {
+        let encoded = X402NdefMessage::new(x402_uri()).encode();
+        let displayed = x402_uri().to_string();
+
+        assert_eq!(encoded[0], 0xD1);
+        assert_eq!(encoded[1], 1);
+        assert_eq!(encoded[2] as usize, displayed.len() + 1);
+        assert_eq!(encoded[3], b'U');
+        assert_eq!(encoded[4], 0);
+        assert_eq!(&encoded[5..], displayed.as_bytes());
+
+        assert_eq!(
+            X402NdefMessage::decode(&encoded).unwrap(),
+            X402NdefMessage::new(x402_uri())
+        );
+    }
+
+    #[test]
+    fn test_android_application_record() {
+        let mut message = X402NdefMessage::new(x402_uri());
+        message.set_explorer_package();
+        let encoded = message.encode();
+
+        // The URI record begins the message and the Android Application Record ends it
+        assert_eq!(encoded[0], 0x91);
+        let aar = &encoded[encoded.len() - 2 - 15 - 1 - X402NdefMessage::EXPLORER_PACKAGE.len()..];
+        assert_eq!(aar[0], 0x54);
+        assert_eq!(&aar[3..18], b"android.com:pkg");
+        assert_eq!(&aar[18..], X402NdefMessage::EXPLORER_PACKAGE.as_bytes());
+
+        let decoded = X402NdefMessage::decode(&encoded).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(
+            decoded.android_package(),
+            Some(X402NdefMessage::EXPLORER_PACKAGE)
+        );
+    }
+
+    #[test]
+    fn test_long_record_and_tag_memory() {
+        let long = X402UriBuf::new(
+            &(String::from("x402://once/https://example.com/") + "a".repeat(300).as_str()),
+        )
+        .unwrap();
+        let encoded = X402NdefMessage::new(long.clone()).encode();
+        assert_eq!(encoded[0] & X402NdefRecord::SHORT_RECORD, 0);
+        assert_eq!(X402NdefMessage::decode(&encoded).unwrap().x402_uri(), &long);
+
+        // Type 2 tag memory with a lock control TLV, the NDEF message TLV and a terminator
+        let short = X402NdefMessage::new(x402_uri()).encode();
+        let mut memory = vec![0x01, 0x03, 0xA0, 0x0C, 0x34, 0x00, 0x03, short.len() as u8];
+        memory.extend_from_slice(&short);
+        memory.push(0xFE);
+        assert_eq!(
+            X402NdefMessage::decode_tag(&memory).unwrap().x402_uri(),
+            &x402_uri()
+        );
+
+        let mut memory = vec![0x03, 0xFF];
+        memory.extend_from_slice(&(encoded.len() as u16).to_be_bytes());
+        memory.extend_from_slice(&encoded);
+        assert_eq!(
+            X402NdefMessage::decode_tag(&memory).unwrap().x402_uri(),
+            &long
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let encoded = X402NdefMessage::new(x402_uri()).encode();
+        assert_eq!(
+            X402NdefMessage::decode(&encoded[..encoded.len() - 1]),
+            Err(X402UriError::TruncatedNdefMessage)
+        );
+        assert_eq!(
+            X402NdefMessage::decode(&[]),
+            Err(X402UriError::TruncatedNdefMessage)
+        );
+
+        // A https URI record abbreviated with the `https://` prefix code
+        let https = [
+            0xD1, 0x01, 0x0C, b'U', 0x04, b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c',
+            b'o', b'm',
+        ];
+        assert_eq!(
+            X402NdefMessage::decode(&https),
+            Err(X402UriError::MissingNdefX402UriRecord)
+        );
+
+        let chunked = [0xB1, 0x01, 0x01, b'U', 0x00];
+        assert_eq!(
+            X402NdefMessage::decode(&chunked),
+            Err(X402UriError::InvalidNdefRecord)
+        );
+        assert_eq!(
+            X402NdefMessage::decode_tag(&[0x00, 0x00, 0xFE]),
+            Err(X402UriError::MissingNdefX402UriRecord)
+        );
+    }
+}
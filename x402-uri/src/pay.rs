@@ -0,0 +1,452 @@
+use core::fmt;
+
+use crate::{
+    X402Uri, X402UriAction, X402UriBuf, X402UriBuilder, X402UriError, X402UriPercent, X402UriResult,
+};
+
+/// The payment parameters of a `x402://pay/` URI, modelled after Solana Pay transfer requests.
+/// They are query parameters of the resource URI that settles the payment,
+/// `x402://pay/<URI>?recipient=<address>&amount=<amount>&asset=<mint>&label=<label>&message=<message>&memo=<memo>`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct X402PayRequest {
+    /// The base58 address of the account receiving the payment
+    pub recipient: String,
+    /// The amount in user units of the asset like `1.5` USDC. If missing the user enters the amount.
+    pub amount: Option<X402PayAmount>,
+    /// The base58 mint address of the token. If missing the payment is in SOL.
+    pub asset: Option<String>,
+    /// Who is requesting the payment, like the name of a shop
+    pub label: Option<String>,
+    /// What the payment is for, like the item being bought
+    pub message: Option<String>,
+    /// Included in the memo instruction of the payment transaction
+    pub memo: Option<String>,
+}
+
+impl X402PayRequest {
+    pub const RECIPIENT: &str = "recipient";
+    pub const AMOUNT: &str = "amount";
+    pub const ASSET: &str = "asset";
+    pub const LABEL: &str = "label";
+    pub const MESSAGE: &str = "message";
+    pub const MEMO: &str = "memo";
+
+    pub fn new(recipient: &str) -> Self {
+        Self {
+            recipient: recipient.to_string(),
+            amount: Option::None,
+            asset: Option::None,
+            label: Option::None,
+            message: Option::None,
+            memo: Option::None,
+        }
+    }
+
+    pub fn set_amount(&mut self, amount: X402PayAmount) -> &mut Self {
+        self.amount.replace(amount);
+
+        self
+    }
+
+    pub fn set_asset(&mut self, asset: &str) -> &mut Self {
+        self.asset.replace(asset.to_string());
+
+        self
+    }
+
+    pub fn set_label(&mut self, label: &str) -> &mut Self {
+        self.label.replace(label.to_string());
+
+        self
+    }
+
+    pub fn set_message(&mut self, message: &str) -> &mut Self {
+        self.message.replace(message.to_string());
+
+        self
+    }
+
+    pub fn set_memo(&mut self, memo: &str) -> &mut Self {
+        self.memo.replace(memo.to_string());
+
+        self
+    }
+
+    /// Parses the payment parameters from the resource URI of a `x402://pay/` URI.
+    /// Other query parameters belong to the server and are ignored.
+    pub fn from_uri(action: X402UriAction, uri: &str) -> X402UriResult<Self> {
+        if action != X402UriAction::Pay {
+            return Err(X402UriError::PayRequestRequiresPayAction);
+        }
+
+        let query = uri
+            .split_once('?')
+            .map(|(_, query)| query.split_once('#').map_or(query, |(query, _)| query))
+            .unwrap_or_default();
+
+        let (recipient, mut outcome) = Self::parse_query(query, Self::ASSET, |_, _| Ok(()))?;
+
+        outcome.recipient = recipient.ok_or(X402UriError::MissingPayRecipient)?;
+        outcome.validate()?;
+
+        Ok(outcome)
+    }
+
+    /// Parses the payment parameters of a query string and returns the `recipient` parameter
+    /// separately. `asset_param` is the name of the asset parameter and `other` receives
+    /// the parameters that are not payment parameters.
+    pub(crate) fn parse_query<'q>(
+        query: &'q str,
+        asset_param: &str,
+        mut other: impl FnMut(&'q str, &'q str) -> X402UriResult<()>,
+    ) -> X402UriResult<(Option<String>, Self)> {
+        let mut recipient = Option::<String>::None;
+        let mut outcome = Self::new("");
+
+        query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .try_for_each(|param| {
+                let (name, value) = param.split_once('=').unwrap_or((param, ""));
+
+                let field = match name {
+                    Self::RECIPIENT => &mut recipient,
+                    Self::LABEL => &mut outcome.label,
+                    Self::MESSAGE => &mut outcome.message,
+                    Self::MEMO => &mut outcome.memo,
+                    _ if name == asset_param => &mut outcome.asset,
+                    Self::AMOUNT => {
+                        if outcome.amount.is_some() {
+                            return Err(X402UriError::DuplicatePayParameter(name.to_string()));
+                        }
+                        outcome.amount.replace(value.parse()?);
+
+                        return Ok(());
+                    }
+                    _ => return other(name, value),
+                };
+
+                if field.is_some() {
+                    return Err(X402UriError::DuplicatePayParameter(name.to_string()));
+                }
+                field.replace(X402UriPercent::decode(value)?.into_owned());
+
+                Ok(())
+            })?;
+
+        Ok((recipient, outcome))
+    }
+
+    /// Checks that the recipient and asset are base58 encoded Solana addresses
+    pub fn validate(&self) -> X402UriResult<()> {
+        if !Self::is_address(&self.recipient) {
+            return Err(X402UriError::InvalidPayAddress(Self::RECIPIENT.to_string()));
+        }
+
+        if self
+            .asset
+            .as_ref()
+            .is_some_and(|asset| !Self::is_address(asset))
+        {
+            return Err(X402UriError::InvalidPayAddress(Self::ASSET.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// The payment parameters as a percent-encoded query string without the leading `?`
+    pub fn to_query(&self) -> String {
+        let amount = self.amount.as_ref().map(|amount| amount.to_string());
+
+        [
+            (Self::RECIPIENT, Some(&self.recipient)),
+            (Self::AMOUNT, amount.as_ref()),
+            (Self::ASSET, self.asset.as_ref()),
+            (Self::LABEL, self.label.as_ref()),
+            (Self::MESSAGE, self.message.as_ref()),
+            (Self::MEMO, self.memo.as_ref()),
+        ]
+        .iter()
+        .filter_map(|(name, value)| {
+            value.map(|value| String::from(*name) + "=" + X402UriPercent::encode(value).as_str())
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+    }
+
+    pub(crate) fn is_address(value: &str) -> bool {
+        const BASE58_ALPHABET: &[u8] =
+            b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+        (32..=44).contains(&value.len())
+            && value.bytes().all(|byte| BASE58_ALPHABET.contains(&byte))
+    }
+}
+
+/// A non-negative decimal amount in user units like `0.25`, stored without loss of precision.
+/// Scientific notation, signs and a leading or trailing `.` are rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct X402PayAmount {
+    value: u64,
+    decimals: u8,
+}
+
+impl X402PayAmount {
+    /// An amount of `value / 10^decimals` user units
+    pub fn new(value: u64, decimals: u8) -> Self {
+        Self { value, decimals }
+    }
+
+    /// The amount without the decimal point, `1.50` has the value `150`
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The number of digits after the decimal point, `1.50` has `2` decimals
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Converts the amount to the smallest unit of an asset with `asset_decimals`,
+    /// like lamports for SOL with 9 decimals
+    pub fn to_base_units(&self, asset_decimals: u8) -> X402UriResult<u64> {
+        let extra_decimals = asset_decimals
+            .checked_sub(self.decimals)
+            .ok_or(X402UriError::PayAmountExceedsAssetDecimals)?;
+
+        10u64
+            .checked_pow(extra_decimals as u32)
+            .and_then(|multiplier| self.value.checked_mul(multiplier))
+            .ok_or(X402UriError::InvalidPayAmount)
+    }
+}
+
+impl core::str::FromStr for X402PayAmount {
+    type Err = X402UriError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+
+        if integer.is_empty()
+            || (s.contains('.') && fraction.is_empty())
+            || !integer
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|byte| byte.is_ascii_digit())
+        {
+            return Err(X402UriError::InvalidPayAmount);
+        }
+
+        let value = (String::from(integer) + fraction)
+            .parse::<u64>()
+            .or(Err(X402UriError::InvalidPayAmount))?;
+        let decimals = u8::try_from(fraction.len()).or(Err(X402UriError::InvalidPayAmount))?;
+
+        Ok(Self { value, decimals })
+    }
+}
+
+impl fmt::Display for X402PayAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = format!(
+            "{:0>width$}",
+            self.value,
+            width = self.decimals as usize + 1
+        );
+        let (integer, fraction) = digits.split_at(digits.len() - self.decimals as usize);
+
+        if fraction.is_empty() {
+            f.write_str(integer)
+        } else {
+            write!(f, "{integer}.{fraction}")
+        }
+    }
+}
+
+impl X402Uri<'_> {
+    /// Parses the payment parameters of a `x402://pay/` URI
+    pub fn pay_request(&self) -> X402UriResult<X402PayRequest> {
+        X402PayRequest::from_uri(self.action(), self.uri())
+    }
+}
+
+impl X402UriBuf {
+    /// Parses the payment parameters of a `x402://pay/` URI
+    pub fn pay_request(&self) -> X402UriResult<X402PayRequest> {
+        X402PayRequest::from_uri(self.action(), self.uri())
+    }
+}
+
+impl X402UriBuilder {
+    /// Builds a `x402://pay/` URI with the payment parameters appended to the URI of the resource
+    pub fn build_pay(&self, pay_request: &X402PayRequest) -> X402UriResult<X402UriBuf> {
+        pay_request.validate()?;
+
+        let mut builder = self.clone();
+        let built = builder.set_action(X402UriAction::Pay).build()?;
+        let separator = if built.uri().contains('?') { '&' } else { '?' };
+
+        let mut uri = built.uri().to_string();
+        uri.push(separator);
+        uri.push_str(&pay_request.to_query());
+
+        let built = builder.set_uri(&uri).build()?;
+        built.pay_request()?;
+
+        Ok(built)
+    }
+}
+
+#[cfg(test)]
+mod x402_pay_sanity {
+    use super::*;
+
+    const RECIPIENT: &str = "2wKupLR9q6wXYppw8Gr2NvWxKBUqm4PPJKkQfoxHDBg4";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    #[test]
+    fn test_parse() {
+        let x402_uri =
+            String::from("x402://pay/https://shop.example.com/x402/settle?order=42&recipient=")
+                + RECIPIENT
+                + "&amount=1.50&asset="
+                + USDC
+                + "&label=Lagoon%20Coffee&message=Flat%20white&memo=order%2342";
+        let parsed = X402Uri::new(&x402_uri).unwrap();
+        assert_eq!(parsed.action(), X402UriAction::Pay);
+
+        let pay_request = parsed.pay_request().unwrap();
+        assert_eq!(pay_request.recipient, RECIPIENT);
+        assert_eq!(pay_request.amount, Some(X402PayAmount::new(150, 2)));
+        assert_eq!(pay_request.asset.as_deref(), Some(USDC));
+        assert_eq!(pay_request.label.as_deref(), Some("Lagoon Coffee"));
+        assert_eq!(pay_request.message.as_deref(), Some("Flat white"));
+        assert_eq!(pay_request.memo.as_deref(), Some("order#42"));
+
+        let minimal = X402Uri::new(
+            &(String::from("x402://PAY/https://shop.example.com/settle?recipient=") + RECIPIENT),
+        )
+        .unwrap()
+        .pay_request()
+        .unwrap();
+        assert_eq!(minimal, X402PayRequest::new(RECIPIENT));
+    }
+
+    #[test]
+    fn test_build_round_trip() {
+        let mut pay_request = X402PayRequest::new(RECIPIENT);
+        pay_request
+            .set_amount("0.001".parse().unwrap())
+            .set_asset(USDC)
+            .set_label("Toly's Books & Co")
+            .set_message("Conqueror of Blockchains")
+            .set_memo("ünïcødé 🚀");
+
+        let built = X402UriBuf::builder()
+            .set_uri("https://shop.example.com/x402/settle?order=42")
+            .build_pay(&pay_request)
+            .unwrap();
+        assert_eq!(built.action(), X402UriAction::Pay);
+        assert!(built
+            .uri()
+            .starts_with("https://shop.example.com/x402/settle?order=42&recipient="));
+
+        let displayed = built.to_string();
+        assert!(displayed.starts_with("x402://pay/https%3A%2F%2Fshop.example.com"));
+        assert_eq!(
+            X402Uri::new(&displayed).unwrap().pay_request().unwrap(),
+            pay_request
+        );
+    }
+
+    #[test]
+    fn test_amount() {
+        [
+            ("0", 0, 0),
+            ("1", 1, 0),
+            ("1.50", 150, 2),
+            ("0.000000001", 1, 9),
+            ("18446744073709551615", u64::MAX, 0),
+        ]
+        .iter()
+        .for_each(|(amount, value, decimals)| {
+            let parsed = amount.parse::<X402PayAmount>().unwrap();
+            assert_eq!(parsed, X402PayAmount::new(*value, *decimals));
+            assert_eq!(parsed.to_string(), *amount);
+        });
+
+        [
+            "",
+            ".5",
+            "1.",
+            "-1",
+            "+1",
+            "1e3",
+            "1.2.3",
+            "1,5",
+            "18446744073709551616",
+        ]
+        .iter()
+        .for_each(|amount| {
+            assert_eq!(
+                amount.parse::<X402PayAmount>(),
+                Err(X402UriError::InvalidPayAmount),
+                "{amount}"
+            )
+        });
+
+        let amount = "1.5".parse::<X402PayAmount>().unwrap();
+        assert_eq!(amount.to_base_units(6), Ok(1_500_000));
+        assert_eq!(amount.to_base_units(1), Ok(15));
+        assert_eq!(
+            "0.001".parse::<X402PayAmount>().unwrap().to_base_units(2),
+            Err(X402UriError::PayAmountExceedsAssetDecimals)
+        );
+        assert_eq!(
+            amount.to_base_units(20),
+            Err(X402UriError::InvalidPayAmount)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let pay = |query: &str| {
+            X402PayRequest::from_uri(
+                X402UriAction::Pay,
+                &(String::from("https://shop.example.com/settle?") + query),
+            )
+        };
+
+        assert_eq!(
+            X402PayRequest::from_uri(X402UriAction::Once, "https://example.com?recipient=x"),
+            Err(X402UriError::PayRequestRequiresPayAction)
+        );
+        assert_eq!(pay("amount=1"), Err(X402UriError::MissingPayRecipient));
+        assert_eq!(
+            pay("recipient=0OIl"),
+            Err(X402UriError::InvalidPayAddress("recipient".to_string()))
+        );
+        assert_eq!(
+            pay(&(String::from("recipient=") + RECIPIENT + "&asset=USDC")),
+            Err(X402UriError::InvalidPayAddress("asset".to_string()))
+        );
+        assert_eq!(
+            pay(&(String::from("recipient=") + RECIPIENT + "&amount=1&amount=2")),
+            Err(X402UriError::DuplicatePayParameter("amount".to_string()))
+        );
+        assert_eq!(
+            pay(&(String::from("recipient=") + RECIPIENT + "&recipient=" + RECIPIENT)),
+            Err(X402UriError::DuplicatePayParameter("recipient".to_string()))
+        );
+        assert_eq!(
+            pay(&(String::from("recipient=") + RECIPIENT + "&amount=1e9")),
+            Err(X402UriError::InvalidPayAmount)
+        );
+        assert_eq!(
+            X402UriBuf::builder()
+                .set_uri("https://shop.example.com/settle")
+                .build_pay(&X402PayRequest::new("not-an-address")),
+            Err(X402UriError::InvalidPayAddress("recipient".to_string()))
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::borrow::Cow;
+
+use crate::{X402UriError, X402UriResult};
+
+/// Strict percent-decoding for the resource URI embedded in a x402-URI.
+pub struct X402UriPercent;
+
+impl X402UriPercent {
+    /// Decodes every `%XX` escape in `value`.
+    /// The input is borrowed when it contains no escapes.
+    /// Unlike lenient decoders, a `%` that is not followed by two hexadecimal digits is an error
+    /// since it means the link was truncated or mangled.
+    pub fn decode(value: &str) -> X402UriResult<Cow<'_, str>> {
+        if !value.contains('%') {
+            return Ok(Cow::Borrowed(value));
+        }
+
+        let bytes = value.as_bytes();
+        let mut decoded = Vec::<u8>::with_capacity(bytes.len());
+        let mut index = 0usize;
+
+        while let Some(byte) = bytes.get(index) {
+            if *byte == b'%' {
+                let high = bytes
+                    .get(index + 1)
+                    .and_then(|value| Self::hex_value(*value));
+                let low = bytes
+                    .get(index + 2)
+                    .and_then(|value| Self::hex_value(*value));
+
+                match (high, low) {
+                    (Some(high), Some(low)) => decoded.push((high << 4) | low),
+                    _ => return Err(X402UriError::MalformedPercentEncoding(index)),
+                }
+
+                index += 3;
+            } else {
+                decoded.push(*byte);
+                index += 1;
+            }
+        }
+
+        String::from_utf8(decoded)
+            .map(Cow::Owned)
+            .or(Err(X402UriError::PercentDecodedUriIsNotUtf8))
+    }
+
+    /// Percent-encodes every byte of `value` except the RFC 3986 unreserved characters
+    /// `A-Z a-z 0-9 - . _ ~`, which is how the resource URI is written in a canonical x402-URI.
+    pub fn encode(value: &str) -> String {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+        let mut encoded = String::with_capacity(value.len());
+
+        value.bytes().for_each(|byte| {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                encoded.push(byte as char);
+            } else {
+                encoded.push('%');
+                encoded.push(HEX[(byte >> 4) as usize] as char);
+                encoded.push(HEX[(byte & 0x0F) as usize] as char);
+            }
+        });
+
+        encoded
+    }
+
+    fn hex_value(byte: u8) -> Option<u8> {
+        match byte {
+            b'0'..=b'9' => Some(byte - b'0'),
+            b'a'..=b'f' => Some(byte - b'a' + 10),
+            b'A'..=b'F' => Some(byte - b'A' + 10),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use core::fmt::Write;
+
+use qrcode::{types::QrError, Color, EcLevel, QrCode, Version};
+
+use crate::{X402Uri, X402UriBuf, X402UriError, X402UriResult};
+
+/// A QR code matrix for a x402-URI ready to be rendered for print or screens
+#[derive(Debug, Clone, PartialEq)]
+pub struct X402QrCode {
+    modules: Vec<bool>,
+    width: usize,
+    version: i16,
+    ec_level: X402QrEcLevel,
+    logo_safe_zone: Option<X402QrSafeZone>,
+    options: X402QrOptions,
+}
+
+impl X402QrCode {
+    /// Above this version the modules get too small to scan reliably from a poster at a distance,
+    /// so a lower error-correction level is preferred over a bigger symbol.
+    pub const PREFERRED_MAX_VERSION: i16 = 10;
+    /// The largest logo safe zone as a fraction of the symbol width.
+    /// The area it covers must stay well under the 30% that [X402QrEcLevel::High] can recover.
+    pub const MAX_LOGO_SAFE_ZONE: f32 = 0.3;
+
+    /// Encodes the canonical form of the x402-URI into a QR code
+    pub fn encode(x402_uri: &X402UriBuf, options: &X402QrOptions) -> X402UriResult<Self> {
+        Self::encode_str(&x402_uri.to_string(), options)
+    }
+
+    fn encode_str(data: &str, options: &X402QrOptions) -> X402UriResult<Self> {
+        if let Some(ratio) = options.logo_safe_zone {
+            if !(ratio > 0.0 && ratio <= Self::MAX_LOGO_SAFE_ZONE) {
+                return Err(X402UriError::InvalidQrLogoSafeZone);
+            }
+        }
+
+        let code = Self::pick_ec_level(data, options)?;
+        let width = code.width();
+        let version = match code.version() {
+            Version::Normal(version) => version,
+            Version::Micro(version) => version,
+        };
+
+        let logo_safe_zone = options.logo_safe_zone.map(|ratio| {
+            let mut size = (width as f32 * ratio).ceil() as usize;
+            // Keep the zone centered on a module
+            if size.is_multiple_of(2) {
+                size += 1;
+            }
+            let start = (width - size) / 2;
+
+            X402QrSafeZone {
+                x: start,
+                y: start,
+                size,
+            }
+        });
+
+        let modules = code
+            .to_colors()
+            .into_iter()
+            .enumerate()
+            .map(|(index, color)| {
+                let inside_safe_zone =
+                    logo_safe_zone.is_some_and(|zone| zone.contains(index % width, index / width));
+
+                color == Color::Dark && !inside_safe_zone
+            })
+            .collect::<Vec<bool>>();
+
+        Ok(Self {
+            modules,
+            width,
+            version,
+            ec_level: code.error_correction_level().into(),
+            logo_safe_zone,
+            options: options.clone(),
+        })
+    }
+
+    /// Picks the highest error-correction level that keeps the symbol at or below
+    /// [Self::PREFERRED_MAX_VERSION], otherwise the smallest symbol that fits the data.
+    /// A logo safe zone always uses [X402QrEcLevel::High].
+    fn pick_ec_level(data: &str, options: &X402QrOptions) -> X402UriResult<QrCode> {
+        if options.logo_safe_zone.is_some() {
+            return QrCode::with_error_correction_level(data, EcLevel::H).map_err(Into::into);
+        }
+
+        let mut smallest = Option::<QrCode>::None;
+
+        for ec_level in [EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L] {
+            let code = match QrCode::with_error_correction_level(data, ec_level) {
+                Ok(code) => code,
+                Err(QrError::DataTooLong) => continue,
+                Err(error) => return Err(error.into()),
+            };
+
+            if matches!(code.version(), Version::Normal(version) if version <= Self::PREFERRED_MAX_VERSION)
+            {
+                return Ok(code);
+            }
+
+            smallest.replace(code);
+        }
+
+        smallest.ok_or(X402UriError::QrDataTooLong)
+    }
+
+    /// The number of modules on each side of the symbol, excluding the quiet zone
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn version(&self) -> i16 {
+        self.version
+    }
+
+    pub fn ec_level(&self) -> X402QrEcLevel {
+        self.ec_level
+    }
+
+    /// The cleared area in the center of the symbol where a logo can be placed, in modules
+    pub fn logo_safe_zone(&self) -> Option<X402QrSafeZone> {
+        self.logo_safe_zone
+    }
+
+    /// Returns `true` if the module at column `x` and row `y` is dark
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.width && self.modules[y * self.width + x]
+    }
+
+    /// The width and height of the rendered image in pixels, including the quiet zone
+    pub fn image_size(&self) -> u32 {
+        (self.width + self.options.quiet_zone * 2) as u32 * self.options.module_size
+    }
+
+    /// Renders the QR code as a SVG document
+    pub fn to_svg(&self) -> String {
+        let size = self.width + self.options.quiet_zone * 2;
+        let quiet_zone = self.options.quiet_zone;
+        let mut svg = String::default();
+
+        // Writing to a `String` cannot fail
+        let _ = write!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{pixels}" height="{pixels}" viewBox="0 0 {size} {size}" shape-rendering="crispEdges"><rect width="{size}" height="{size}" fill="{light}"/><path fill="{dark}" d=""#,
+            pixels = self.image_size(),
+            light = Self::hex_color(self.options.light_color),
+            dark = Self::hex_color(self.options.dark_color),
+        );
+
+        (0..self.width).for_each(|y| {
+            (0..self.width).for_each(|x| {
+                if self.is_dark(x, y) {
+                    let _ = write!(svg, "M{},{}h1v1h-1z", x + quiet_zone, y + quiet_zone);
+                }
+            })
+        });
+        svg.push_str(r#""/>"#);
+
+        if let (Some(zone), Some(href)) = (self.logo_safe_zone, self.options.logo_href.as_ref()) {
+            let _ = write!(
+                svg,
+                r#"<image href="{}" x="{}" y="{}" width="{size}" height="{size}" preserveAspectRatio="xMidYMid meet"/>"#,
+                Self::escape_xml(href),
+                zone.x + quiet_zone,
+                zone.y + quiet_zone,
+                size = zone.size,
+            );
+        }
+
+        svg.push_str("</svg>");
+
+        svg
+    }
+
+    /// Renders the QR code as an 8-bit RGB PNG image
+    pub fn to_png(&self) -> X402UriResult<Vec<u8>> {
+        let pixels = self.image_size();
+        let module_size = self.options.module_size as usize;
+        let quiet_zone = self.options.quiet_zone;
+        let mut data = Vec::<u8>::with_capacity(pixels as usize * pixels as usize * 3);
+
+        (0..pixels as usize).for_each(|row| {
+            (0..pixels as usize).for_each(|column| {
+                let x = (column / module_size).checked_sub(quiet_zone);
+                let y = (row / module_size).checked_sub(quiet_zone);
+                let dark = x.zip(y).is_some_and(|(x, y)| self.is_dark(x, y));
+
+                data.extend_from_slice(if dark {
+                    &self.options.dark_color
+                } else {
+                    &self.options.light_color
+                });
+            })
+        });
+
+        let mut png_bytes = Vec::<u8>::default();
+        let mut encoder = png::Encoder::new(&mut png_bytes, pixels, pixels);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder
+            .write_header()
+            .map_err(|error| X402UriError::QrPngEncoding(error.to_string()))?;
+        writer
+            .write_image_data(&data)
+            .map_err(|error| X402UriError::QrPngEncoding(error.to_string()))?;
+        writer
+            .finish()
+            .map_err(|error| X402UriError::QrPngEncoding(error.to_string()))?;
+
+        Ok(png_bytes)
+    }
+
+    fn hex_color(color: [u8; 3]) -> String {
+        format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+    }
+
+    fn escape_xml(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('"', "&quot;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+}
+
+/// Options for encoding and rendering a [X402QrCode]
+#[derive(Debug, Clone, PartialEq)]
+pub struct X402QrOptions {
+    logo_safe_zone: Option<f32>,
+    logo_href: Option<String>,
+    quiet_zone: usize,
+    module_size: u32,
+    dark_color: [u8; 3],
+    light_color: [u8; 3],
+}
+
+impl Default for X402QrOptions {
+    fn default() -> Self {
+        Self {
+            logo_safe_zone: Option::None,
+            logo_href: Option::None,
+            quiet_zone: 4,
+            module_size: 8,
+            dark_color: [0, 0, 0],
+            light_color: [255, 255, 255],
+        }
+    }
+}
+
+impl X402QrOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears a centered square for a logo. `ratio` is the side of the square
+    /// as a fraction of the symbol width, at most [X402QrCode::MAX_LOGO_SAFE_ZONE].
+    pub fn set_logo_safe_zone(&mut self, ratio: f32) -> &mut Self {
+        self.logo_safe_zone.replace(ratio);
+
+        self
+    }
+
+    /// The logo embedded in the safe zone of the SVG output
+    pub fn set_logo_href(&mut self, href: &str) -> &mut Self {
+        self.logo_href.replace(href.to_string());
+
+        self
+    }
+
+    /// The number of light modules around the symbol. The QR specification requires at least 4.
+    pub fn set_quiet_zone(&mut self, modules: usize) -> &mut Self {
+        self.quiet_zone = modules;
+
+        self
+    }
+
+    /// The size of one module in pixels
+    pub fn set_module_size(&mut self, pixels: u32) -> &mut Self {
+        self.module_size = pixels.max(1);
+
+        self
+    }
+
+    pub fn set_colors(&mut self, dark: [u8; 3], light: [u8; 3]) -> &mut Self {
+        self.dark_color = dark;
+        self.light_color = light;
+
+        self
+    }
+}
+
+/// A square area of the symbol, in modules, excluding the quiet zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct X402QrSafeZone {
+    pub x: usize,
+    pub y: usize,
+    pub size: usize,
+}
+
+impl X402QrSafeZone {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.size).contains(&x) && (self.y..self.y + self.size).contains(&y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum X402QrEcLevel {
+    /// Recovers up to 7% of the symbol
+    Low,
+    /// Recovers up to 15% of the symbol
+    Medium,
+    /// Recovers up to 25% of the symbol
+    Quartile,
+    /// Recovers up to 30% of the symbol
+    High,
+}
+
+impl From<EcLevel> for X402QrEcLevel {
+    fn from(value: EcLevel) -> Self {
+        match value {
+            EcLevel::L => Self::Low,
+            EcLevel::M => Self::Medium,
+            EcLevel::Q => Self::Quartile,
+            EcLevel::H => Self::High,
+        }
+    }
+}
+
+impl From<QrError> for X402UriError {
+    fn from(value: QrError) -> Self {
+        match value {
+            QrError::DataTooLong => Self::QrDataTooLong,
+            _ => Self::QrEncoding(value.to_string()),
+        }
+    }
+}
+
+impl X402Uri<'_> {
+    /// Encodes the canonical form of the x402-URI into a QR code
+    pub fn to_qr_code(&self, options: &X402QrOptions) -> X402UriResult<X402QrCode> {
+        X402QrCode::encode_str(&self.to_string(), options)
+    }
+}
+
+impl X402UriBuf {
+    /// Encodes the canonical form of the x402-URI into a QR code
+    pub fn to_qr_code(&self, options: &X402QrOptions) -> X402UriResult<X402QrCode> {
+        X402QrCode::encode(self, options)
+    }
+}
+
+#[cfg(test)]
+mod x402_qr_sanity {
+    use super::*;
+
+    const DISCOVER: &str = "x402://discover/https%3A%2F%2Flagoon.markets%2Fx402%2Fdiscover";
+
+    #[test]
+    fn test_auto_ec_level() {
+        let parsed = X402Uri::new(DISCOVER).unwrap();
+        let code = parsed.to_qr_code(&X402QrOptions::default()).unwrap();
+
+        assert_eq!(code.ec_level(), X402QrEcLevel::High);
+        assert!(code.version() <= X402QrCode::PREFERRED_MAX_VERSION);
+        assert_eq!(code.width(), code.version() as usize * 4 + 17);
+
+        let long_uri = X402UriBuf::builder()
+            .set_uri(&(String::from("https://example.com/") + "a".repeat(400).as_str()))
+            .build()
+            .unwrap();
+        let code = long_uri.to_qr_code(&X402QrOptions::default()).unwrap();
+        assert!(code.ec_level() < X402QrEcLevel::High);
+
+        let too_long = X402UriBuf::builder()
+            .set_uri(&(String::from("https://example.com/") + "a".repeat(4000).as_str()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            too_long.to_qr_code(&X402QrOptions::default()),
+            Err(X402UriError::QrDataTooLong)
+        );
+    }
+
+    #[test]
+    fn test_logo_safe_zone() {
+        let parsed = X402Uri::new(DISCOVER).unwrap();
+        let code = parsed
+            .to_qr_code(
+                X402QrOptions::new()
+                    .set_logo_safe_zone(0.2)
+                    .set_logo_href("https://lagoon.markets/assets/Lagoon.Markets-Logo.svg"),
+            )
+            .unwrap();
+
+        assert_eq!(code.ec_level(), X402QrEcLevel::High);
+        let zone = code.logo_safe_zone().unwrap();
+        assert!(!zone.size.is_multiple_of(2));
+        assert_eq!(zone.x * 2 + zone.size, code.width());
+        (zone.y..zone.y + zone.size)
+            .for_each(|y| (zone.x..zone.x + zone.size).for_each(|x| assert!(!code.is_dark(x, y))));
+        assert!(code.to_svg().contains("<image href="));
+
+        assert_eq!(
+            parsed.to_qr_code(X402QrOptions::new().set_logo_safe_zone(0.5)),
+            Err(X402UriError::InvalidQrLogoSafeZone)
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let code = X402Uri::new(DISCOVER)
+            .unwrap()
+            .to_qr_code(X402QrOptions::new().set_module_size(4))
+            .unwrap();
+        let size = (code.width() + 8) as u32 * 4;
+        assert_eq!(code.image_size(), size);
+
+        let svg = code.to_svg();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains(&format!(r#"width="{size}" height="{size}""#)));
+        // The top left finder pattern starts right after the quiet zone
+        assert!(svg.contains("M4,4h1v1h-1z"));
+
+        let png_bytes = code.to_png().unwrap();
+        let mut reader = png::Decoder::new(png_bytes.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (size, size));
+        // Quiet zone is light and the first module of the finder pattern is dark
+        assert_eq!(&pixels[..3], &[255, 255, 255]);
+        let first_module = ((16 * size + 16) * 3) as usize;
+        assert_eq!(&pixels[first_module..first_module + 3], &[0, 0, 0]);
+    }
+}
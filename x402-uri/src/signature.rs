@@ -0,0 +1,337 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use crate::{X402Uri, X402UriAction, X402UriBuf, X402UriBuilder, X402UriError, X402UriResult};
+
+/// Signs x402-URIs with the ed25519 key of a publisher so that apps can detect
+/// x402-URIs that were changed, like a QR code sticker pasted over a billboard.
+/// The base58 encoded public key and signature are added as the `pk` and `sig` query
+/// parameters of the resource URI. The signed message is the canonical x402-URI
+/// without the `pk` and `sig` parameters, see [X402UriBuf::signed_message].
+pub struct X402UriSigner {
+    signing_key: SigningKey,
+}
+
+impl X402UriSigner {
+    pub const PUBLIC_KEY: &str = "pk";
+    pub const SIGNATURE: &str = "sig";
+
+    /// Creates a signer from a 32 byte ed25519 secret key
+    pub fn new(secret_key: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret_key),
+        }
+    }
+
+    /// Creates a signer from the 64 bytes of a Solana keypair, the secret key followed by the public key
+    pub fn from_keypair_bytes(keypair: &[u8; 64]) -> X402UriResult<Self> {
+        let signing_key =
+            SigningKey::from_keypair_bytes(keypair).or(Err(X402UriError::InvalidSigningKey))?;
+
+        Ok(Self { signing_key })
+    }
+
+    /// The base58 encoded public key of the publisher
+    pub fn public_key(&self) -> String {
+        bs58::encode(self.signing_key.verifying_key().as_bytes()).into_string()
+    }
+
+    /// Signs the canonical form of the x402-URI, replacing any previous signature
+    pub fn sign(&self, x402_uri: &X402UriBuf) -> X402UriResult<X402UriBuf> {
+        let unsigned = x402_uri.unsigned()?;
+        let signature = self.signing_key.sign(unsigned.to_string().as_bytes());
+
+        let params = [
+            String::from(Self::PUBLIC_KEY) + "=" + self.public_key().as_str(),
+            String::from(Self::SIGNATURE)
+                + "="
+                + bs58::encode(signature.to_bytes()).into_string().as_str(),
+        ];
+
+        let (resource, mut query, fragment) = split_query(unsigned.uri());
+
+        // The Subscription Data stays the last query parameter
+        let position = if unsigned.action() == X402UriAction::Subscribe {
+            query.len().saturating_sub(1)
+        } else {
+            query.len()
+        };
+        query.splice(position..position, params.iter().map(String::as_str));
+
+        rebuild(&unsigned, resource, &query, fragment)
+    }
+}
+
+/// The result of verifying the publisher signature of a x402-URI
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum X402UriSignature {
+    /// The x402-URI has no `pk` and `sig` parameters
+    Unsigned,
+    /// The x402-URI was signed by the base58 encoded publisher key. Apps should
+    /// check that this key belongs to the publisher they expect.
+    Verified { public_key: String },
+}
+
+impl X402UriBuf {
+    /// The canonical x402-URI without the `pk` and `sig` parameters
+    pub fn unsigned(&self) -> X402UriResult<X402UriBuf> {
+        let (resource, query, fragment) = split_query(self.uri());
+        let query = query
+            .into_iter()
+            .filter(|param| signature_param(param).is_none())
+            .collect::<Vec<&str>>();
+
+        rebuild(self, resource, &query, fragment)?.canonical()
+    }
+
+    /// The bytes signed by the publisher, the canonical x402-URI without the `pk` and `sig` parameters
+    pub fn signed_message(&self) -> X402UriResult<String> {
+        self.unsigned().map(|unsigned| unsigned.to_string())
+    }
+
+    /// Verifies the publisher signature. An x402-URI without `pk` and `sig` is [X402UriSignature::Unsigned],
+    /// an x402-URI with an invalid signature or only one of the parameters is an error.
+    pub fn verify_signature(&self) -> X402UriResult<X402UriSignature> {
+        let (_, query, _) = split_query(self.uri());
+
+        let mut public_key = Option::<&str>::None;
+        let mut signature = Option::<&str>::None;
+
+        query
+            .into_iter()
+            .filter_map(signature_param)
+            .try_for_each(|(name, value)| {
+                let field = if name == X402UriSigner::PUBLIC_KEY {
+                    &mut public_key
+                } else {
+                    &mut signature
+                };
+
+                if field.replace(value).is_some() {
+                    return Err(X402UriError::DuplicateSignatureParameter(name.to_string()));
+                }
+
+                Ok(())
+            })?;
+
+        let (public_key, signature) = match (public_key, signature) {
+            (None, None) => return Ok(X402UriSignature::Unsigned),
+            (Some(public_key), Some(signature)) => (public_key, signature),
+            (None, Some(_)) => {
+                return Err(X402UriError::MissingSignatureParameter(
+                    X402UriSigner::PUBLIC_KEY.to_string(),
+                ))
+            }
+            (Some(_), None) => {
+                return Err(X402UriError::MissingSignatureParameter(
+                    X402UriSigner::SIGNATURE.to_string(),
+                ))
+            }
+        };
+
+        let verifying_key = bs58::decode(public_key)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+            .ok_or(X402UriError::InvalidSignaturePublicKey)?;
+        let signature = bs58::decode(signature)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .map(|bytes| Signature::from_bytes(&bytes))
+            .ok_or(X402UriError::InvalidSignatureEncoding)?;
+
+        verifying_key
+            .verify_strict(self.signed_message()?.as_bytes(), &signature)
+            .or(Err(X402UriError::SignatureMismatch))?;
+
+        Ok(X402UriSignature::Verified {
+            public_key: public_key.to_string(),
+        })
+    }
+}
+
+impl X402Uri<'_> {
+    /// Verifies the publisher signature, see [X402UriBuf::verify_signature]
+    pub fn verify_signature(&self) -> X402UriResult<X402UriSignature> {
+        self.to_buf().verify_signature()
+    }
+}
+
+/// Splits a resource URI into the part before the query, the query parameters and the fragment
+fn split_query(uri: &str) -> (&str, Vec<&str>, Option<&str>) {
+    let (uri, fragment) = match uri.split_once('#') {
+        Some((uri, fragment)) => (uri, Some(fragment)),
+        None => (uri, None),
+    };
+
+    match uri.split_once('?') {
+        Some((resource, query)) => (
+            resource,
+            query.split('&').filter(|param| !param.is_empty()).collect(),
+            fragment,
+        ),
+        None => (uri, Vec::default(), fragment),
+    }
+}
+
+fn signature_param(param: &str) -> Option<(&str, &str)> {
+    param
+        .split_once('=')
+        .filter(|(name, _)| *name == X402UriSigner::PUBLIC_KEY || *name == X402UriSigner::SIGNATURE)
+}
+
+fn rebuild(
+    x402_uri: &X402UriBuf,
+    resource: &str,
+    query: &[&str],
+    fragment: Option<&str>,
+) -> X402UriResult<X402UriBuf> {
+    let mut uri = resource.to_string();
+
+    if !query.is_empty() {
+        uri.push('?');
+        uri.push_str(&query.join("&"));
+    }
+
+    if let Some(fragment) = fragment {
+        uri.push('#');
+        uri.push_str(fragment);
+    }
+
+    X402UriBuilder::new()
+        .set_action(x402_uri.action())
+        .set_uri_scheme(x402_uri.uri_scheme())
+        .set_uri(&uri)
+        .build()
+}
+
+#[cfg(test)]
+mod x402_uri_signature_sanity {
+    use super::*;
+
+    const SECRET_KEY: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = X402UriSigner::new(&SECRET_KEY);
+        let x402_uri = X402UriBuf::new(
+            "x402://once/https://Example.com/x402/publisher/?name=Conqueror&author=Toly",
+        )
+        .unwrap();
+
+        let signed = signer.sign(&x402_uri).unwrap();
+        assert_eq!(
+            signed.verify_signature(),
+            Ok(X402UriSignature::Verified {
+                public_key: signer.public_key()
+            })
+        );
+        assert_eq!(x402_uri.verify_signature(), Ok(X402UriSignature::Unsigned));
+
+        // Scanning the printed QR code and reordering the parameters keeps the signature valid
+        let displayed = signed.to_string();
+        let parsed = X402Uri::new(&displayed).unwrap();
+        assert!(matches!(
+            parsed.verify_signature(),
+            Ok(X402UriSignature::Verified { .. })
+        ));
+
+        let resigned = X402UriSigner::new(&[9u8; 32]).sign(&signed).unwrap();
+        assert_eq!(resigned.uri().matches("sig=").count(), 1);
+        assert_ne!(resigned.verify_signature(), signed.verify_signature());
+
+        let mut keypair = [0u8; 64];
+        keypair[..32].copy_from_slice(&SECRET_KEY);
+        keypair[32..].copy_from_slice(&bs58::decode(signer.public_key()).into_vec().unwrap());
+        assert_eq!(
+            X402UriSigner::from_keypair_bytes(&keypair)
+                .unwrap()
+                .public_key(),
+            signer.public_key()
+        );
+        assert!(matches!(
+            X402UriSigner::from_keypair_bytes(&[1u8; 64]),
+            Err(X402UriError::InvalidSigningKey)
+        ));
+    }
+
+    #[test]
+    fn test_tampered() {
+        let signer = X402UriSigner::new(&SECRET_KEY);
+        let pay = X402UriBuf::new(
+            "x402://pay/https://shop.example.com/settle?recipient=2wKupLR9q6wXYppw8Gr2NvWxKBUqm4PPJKkQfoxHDBg4&amount=1",
+        )
+        .unwrap();
+        let signed = signer.sign(&pay).unwrap();
+
+        let tampered = X402UriBuf::new(&signed.to_string().replace(
+            "2wKupLR9q6wXYppw8Gr2NvWxKBUqm4PPJKkQfoxHDBg4",
+            "EwWqGE4ZFKLofuestmU4LDdK7XM1N4ALgdZccwYugwGd",
+        ))
+        .unwrap();
+        assert_eq!(
+            tampered.verify_signature(),
+            Err(X402UriError::SignatureMismatch)
+        );
+
+        let other_action = X402UriBuf::builder()
+            .set_action(X402UriAction::Once)
+            .set_uri(signed.uri())
+            .build()
+            .unwrap();
+        assert_eq!(
+            other_action.verify_signature(),
+            Err(X402UriError::SignatureMismatch)
+        );
+    }
+
+    #[test]
+    fn test_subscription_data_stays_last() {
+        let signer = X402UriSigner::new(&SECRET_KEY);
+        let subscribe =
+            X402UriBuf::new("x402://subscribe/https://example.com/topic?b=1&a=2&e30=").unwrap();
+        let signed = signer.sign(&subscribe).unwrap();
+
+        assert!(signed
+            .uri()
+            .starts_with("https://example.com/topic?a=2&b=1&pk="));
+        assert!(signed.uri().ends_with("&e30="));
+        assert!(matches!(
+            signed.verify_signature(),
+            Ok(X402UriSignature::Verified { .. })
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        let verify = |query: &str| {
+            X402UriBuf::new(&(String::from("x402://discover/https://example.com/x402?") + query))
+                .unwrap()
+                .verify_signature()
+        };
+
+        assert_eq!(
+            verify("sig=abc"),
+            Err(X402UriError::MissingSignatureParameter("pk".to_string()))
+        );
+        assert_eq!(
+            verify("pk=abc"),
+            Err(X402UriError::MissingSignatureParameter("sig".to_string()))
+        );
+        assert_eq!(
+            verify("pk=a&pk=b&sig=c"),
+            Err(X402UriError::DuplicateSignatureParameter("pk".to_string()))
+        );
+        assert_eq!(
+            verify("pk=0OIl&sig=abc"),
+            Err(X402UriError::InvalidSignaturePublicKey)
+        );
+
+        let public_key = X402UriSigner::new(&SECRET_KEY).public_key();
+        assert_eq!(
+            verify(&(String::from("pk=") + public_key.as_str() + "&sig=abc")),
+            Err(X402UriError::InvalidSignatureEncoding)
+        );
+    }
+}
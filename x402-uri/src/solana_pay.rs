@@ -0,0 +1,297 @@
+use crate::{
+    X402PayRequest, X402UriAction, X402UriBuf, X402UriBuilder, X402UriError, X402UriPercent,
+    X402UriResult, X402UriScheme,
+};
+
+/// A Solana Pay `solana:` URI converted to its x402 equivalent
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum X402SolanaPay {
+    /// A transfer request `solana:<recipient>?amount=<amount>&spl-token=<mint>&reference=<reference>&label=<label>&message=<message>&memo=<memo>`
+    /// which has the same parameters as the [X402UriAction::Pay] action
+    Transfer(X402SolanaTransfer),
+    /// A transaction request `solana:<https link>` which is a [X402UriAction::Once] x402-URI
+    Transaction(X402UriBuf),
+}
+
+impl X402SolanaPay {
+    pub const SCHEME: &str = "solana:";
+    pub const SPL_TOKEN: &str = "spl-token";
+    pub const REFERENCE: &str = "reference";
+
+    /// Parses a Solana Pay transfer request or transaction request URI
+    pub fn parse(solana_uri: &str) -> X402UriResult<Self> {
+        let rest = solana_uri
+            .get(..Self::SCHEME.len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case(Self::SCHEME))
+            .map(|_| &solana_uri[Self::SCHEME.len()..])
+            .ok_or(X402UriError::InvalidSolanaPayUri)?;
+
+        if rest
+            .get(..X402UriScheme::HTTPS_SCHEME.len())
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case(X402UriScheme::HTTPS_SCHEME))
+        {
+            Self::transaction(rest)
+        } else if rest
+            .get(.."https%3a".len())
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https%3a"))
+        {
+            // The link must be percent-encoded when it has query parameters
+            Self::transaction(&X402UriPercent::decode(rest)?)
+        } else if rest.contains(':') {
+            Err(X402UriError::UnsupportedUriScheme)
+        } else {
+            X402SolanaTransfer::parse(rest).map(Self::Transfer)
+        }
+    }
+
+    /// The equivalent x402 action
+    pub fn action(&self) -> X402UriAction {
+        match self {
+            Self::Transfer(_) => X402UriAction::Pay,
+            Self::Transaction(_) => X402UriAction::Once,
+        }
+    }
+
+    fn transaction(link: &str) -> X402UriResult<Self> {
+        X402UriBuilder::new()
+            .set_action(X402UriAction::Once)
+            .set_uri(link)
+            .build()
+            .map(Self::Transaction)
+    }
+}
+
+/// The parameters of a Solana Pay transfer request
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct X402SolanaTransfer {
+    pub pay_request: X402PayRequest,
+    /// The base58 `reference` addresses added to the transfer to find it onchain
+    pub references: Vec<String>,
+}
+
+impl X402SolanaTransfer {
+    /// Parses `<recipient>?<query>` after the `solana:` scheme
+    fn parse(transfer: &str) -> X402UriResult<Self> {
+        let (recipient, query) = transfer.split_once('?').unwrap_or((transfer, ""));
+
+        let mut references = Vec::<String>::new();
+        let (_, mut pay_request) =
+            X402PayRequest::parse_query(query, X402SolanaPay::SPL_TOKEN, |name, value| {
+                if name == X402SolanaPay::REFERENCE {
+                    if !X402PayRequest::is_address(value) {
+                        return Err(X402UriError::InvalidPayAddress(name.to_string()));
+                    }
+                    references.push(value.to_string());
+                }
+
+                Ok(())
+            })?;
+
+        if recipient.is_empty() {
+            return Err(X402UriError::MissingPayRecipient);
+        }
+        pay_request.recipient = recipient.to_string();
+        pay_request.validate()?;
+
+        Ok(Self {
+            pay_request,
+            references,
+        })
+    }
+
+    /// Builds the `x402://pay/` URI that settles the transfer at `settle_uri`
+    pub fn to_x402_uri(&self, settle_uri: &str) -> X402UriResult<X402UriBuf> {
+        X402UriBuilder::new()
+            .set_uri(settle_uri)
+            .build_pay(&self.pay_request)
+    }
+}
+
+impl X402PayRequest {
+    /// The Solana Pay transfer request URI with the same parameters
+    pub fn to_solana_pay(&self) -> X402UriResult<String> {
+        self.validate()?;
+
+        let params = [
+            (
+                X402PayRequest::AMOUNT,
+                self.amount.map(|amount| amount.to_string()),
+            ),
+            (X402SolanaPay::SPL_TOKEN, self.asset.clone()),
+            (X402PayRequest::LABEL, self.label.clone()),
+            (X402PayRequest::MESSAGE, self.message.clone()),
+            (X402PayRequest::MEMO, self.memo.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            value.map(|value| String::from(name) + "=" + X402UriPercent::encode(&value).as_str())
+        })
+        .collect::<Vec<String>>();
+
+        let mut solana_uri = String::from(X402SolanaPay::SCHEME) + self.recipient.as_str();
+
+        if !params.is_empty() {
+            solana_uri.push('?');
+            solana_uri.push_str(&params.join("&"));
+        }
+
+        Ok(solana_uri)
+    }
+}
+
+impl X402UriBuf {
+    /// Converts a `x402://once/https://` URI into a Solana Pay transaction request
+    pub fn to_solana_pay(&self) -> X402UriResult<String> {
+        if self.action() != X402UriAction::Once {
+            return Err(X402UriError::SolanaPayRequiresOnceAction);
+        }
+
+        if self.uri_scheme() != X402UriScheme::Https {
+            return Err(X402UriError::UnsupportedUriScheme);
+        }
+
+        // Solana Pay requires percent-encoding links with query parameters only
+        let link = if self.uri().contains(['?', '#']) {
+            X402UriPercent::encode(self.uri())
+        } else {
+            self.uri().to_string()
+        };
+
+        Ok(String::from(X402SolanaPay::SCHEME) + link.as_str())
+    }
+}
+
+#[cfg(test)]
+mod x402_solana_pay_sanity {
+    use super::*;
+    use crate::{X402PayAmount, X402Uri};
+
+    const RECIPIENT: &str = "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const REFERENCE: &str = "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny";
+
+    #[test]
+    fn test_transfer_request() {
+        let solana_uri = String::from("solana:")
+            + RECIPIENT
+            + "?amount=0.01&spl-token="
+            + USDC
+            + "&reference="
+            + REFERENCE
+            + "&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345";
+
+        let parsed = X402SolanaPay::parse(&solana_uri).unwrap();
+        assert_eq!(parsed.action(), X402UriAction::Pay);
+
+        let X402SolanaPay::Transfer(transfer) = parsed else {
+            panic!("Expected a transfer request");
+        };
+        assert_eq!(transfer.references, vec![REFERENCE.to_string()]);
+        assert_eq!(transfer.pay_request.recipient, RECIPIENT);
+        assert_eq!(transfer.pay_request.amount, Some(X402PayAmount::new(1, 2)));
+        assert_eq!(transfer.pay_request.asset.as_deref(), Some(USDC));
+        assert_eq!(
+            transfer.pay_request.message.as_deref(),
+            Some("Thanks for all the fish")
+        );
+        assert_eq!(transfer.pay_request.memo.as_deref(), Some("OrderId12345"));
+
+        let x402_uri = transfer
+            .to_x402_uri("https://shop.example.com/x402/settle")
+            .unwrap();
+        assert_eq!(x402_uri.action(), X402UriAction::Pay);
+        assert_eq!(x402_uri.pay_request().unwrap(), transfer.pay_request);
+
+        let round_trip = transfer.pay_request.to_solana_pay().unwrap();
+        assert_eq!(
+            X402SolanaPay::parse(&round_trip).unwrap(),
+            X402SolanaPay::Transfer(X402SolanaTransfer {
+                pay_request: transfer.pay_request.clone(),
+                references: Vec::default(),
+            })
+        );
+
+        let minimal = X402SolanaPay::parse(&(String::from("SOLANA:") + RECIPIENT)).unwrap();
+        assert_eq!(
+            minimal,
+            X402SolanaPay::Transfer(X402SolanaTransfer {
+                pay_request: X402PayRequest::new(RECIPIENT),
+                references: Vec::default(),
+            })
+        );
+        assert_eq!(
+            X402PayRequest::new(RECIPIENT).to_solana_pay().unwrap(),
+            String::from("solana:") + RECIPIENT
+        );
+    }
+
+    #[test]
+    fn test_transaction_request() {
+        let encoded =
+            X402SolanaPay::parse("solana:https%3A%2F%2Fexample.com%2Fsolana-pay%3Forder%3D12345")
+                .unwrap();
+        let plain = X402SolanaPay::parse("solana:https://example.com/solana-pay").unwrap();
+        assert_eq!(encoded.action(), X402UriAction::Once);
+
+        let X402SolanaPay::Transaction(x402_uri) = encoded else {
+            panic!("Expected a transaction request");
+        };
+        assert_eq!(x402_uri.uri(), "https://example.com/solana-pay?order=12345");
+        assert_eq!(
+            x402_uri.to_solana_pay().unwrap(),
+            "solana:https%3A%2F%2Fexample.com%2Fsolana-pay%3Forder%3D12345"
+        );
+
+        let X402SolanaPay::Transaction(x402_uri) = plain else {
+            panic!("Expected a transaction request");
+        };
+        assert_eq!(
+            x402_uri.to_solana_pay().unwrap(),
+            "solana:https://example.com/solana-pay"
+        );
+
+        let once = X402Uri::new("x402://once/https%3A%2F%2Fexample.com%2Fbook")
+            .unwrap()
+            .to_buf();
+        assert_eq!(
+            once.to_solana_pay().unwrap(),
+            "solana:https://example.com/book"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            X402SolanaPay::parse("x402://once/https://example.com"),
+            Err(X402UriError::InvalidSolanaPayUri)
+        );
+        assert_eq!(
+            X402SolanaPay::parse("solana:"),
+            Err(X402UriError::MissingPayRecipient)
+        );
+        assert_eq!(
+            X402SolanaPay::parse("solana:http://example.com/solana-pay"),
+            Err(X402UriError::UnsupportedUriScheme)
+        );
+        assert_eq!(
+            X402SolanaPay::parse(&(String::from("solana:") + RECIPIENT + "?reference=0OIl")),
+            Err(X402UriError::InvalidPayAddress("reference".to_string()))
+        );
+        assert_eq!(
+            X402SolanaPay::parse(&(String::from("solana:") + RECIPIENT + "?amount=1&amount=2")),
+            Err(X402UriError::DuplicatePayParameter("amount".to_string()))
+        );
+        assert_eq!(
+            X402UriBuf::new("x402://discover/https://example.com")
+                .unwrap()
+                .to_solana_pay(),
+            Err(X402UriError::SolanaPayRequiresOnceAction)
+        );
+        assert_eq!(
+            X402UriBuf::new("x402://once/mcp://example.com")
+                .unwrap()
+                .to_solana_pay(),
+            Err(X402UriError::UnsupportedUriScheme)
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::BTreeSet;
+
+use base64ct::{Base64, Base64Url, Base64UrlUnpadded, Encoding};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    X402PayRequest, X402Uri, X402UriAction, X402UriBuf, X402UriBuilder, X402UriError, X402UriResult,
+};
+
+/// The Subscription Data appended as a base64 encoded JSON string to
+/// `x402://subscribe/<URI>/<optional query params>?<base64 encoded Subscription Data>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "RawSubscriptionData")]
+pub struct X402SubscriptionData {
+    pub unsubscribe: X402UriBuf,
+    pub x402_payload: X402PaymentRequirements,
+}
+
+impl X402SubscriptionData {
+    /// The x402 query parameters of the resource URI that must only appear once.
+    /// `pk` and `sig` are the parameters of an `X402UriSigner` signature.
+    pub const RESERVED_PARAMS: [&str; 8] = [
+        X402PayRequest::RECIPIENT,
+        X402PayRequest::AMOUNT,
+        X402PayRequest::ASSET,
+        X402PayRequest::LABEL,
+        X402PayRequest::MESSAGE,
+        X402PayRequest::MEMO,
+        "pk",
+        "sig",
+    ];
+
+    /// Decodes the base64 encoded Subscription Data JSON and validates it
+    pub fn decode(encoded: &str) -> X402UriResult<Self> {
+        let json = Base64::decode_vec(encoded)
+            .or_else(|_| Base64Url::decode_vec(encoded))
+            .or_else(|_| Base64UrlUnpadded::decode_vec(encoded))
+            .or(Err(X402UriError::InvalidSubscriptionDataBase64))?;
+
+        serde_json::from_slice::<RawSubscriptionData>(&json)
+            .map_err(|error| X402UriError::InvalidSubscriptionDataJson(error.to_string()))?
+            .try_into()
+    }
+
+    /// Encodes the Subscription Data as base64 encoded JSON
+    pub fn encode(&self) -> X402UriResult<String> {
+        serde_json::to_vec(self)
+            .map(|json| Base64::encode_string(&json))
+            .map_err(|error| X402UriError::InvalidSubscriptionDataJson(error.to_string()))
+    }
+
+    /// Splits the decoded resource URI of a subscribe x402-URI into
+    /// the URI of the resource and the encoded Subscription Data after the last `?` or `&`
+    pub fn split_uri(uri: &str) -> (&str, Option<&str>) {
+        uri.split_once('?')
+            .map(|(_, query)| {
+                let position =
+                    uri.len() - query.len() + query.rfind('&').map_or(0, |index| index + 1);
+
+                (&uri[..position - 1], Some(&uri[position..]))
+            })
+            .filter(|(_, encoded)| encoded.is_some_and(|value| !value.is_empty()))
+            .unwrap_or((uri, None))
+    }
+
+    /// Decodes the Subscription Data from the resource URI of a subscribe x402-URI
+    pub fn from_uri(action: X402UriAction, uri: &str) -> X402UriResult<Self> {
+        if action != X402UriAction::Subscribe {
+            return Err(X402UriError::SubscriptionDataRequiresSubscribeAction);
+        }
+
+        let (resource, encoded) = Self::split_uri(uri);
+        let encoded = encoded.ok_or(X402UriError::MissingSubscriptionData)?;
+        Self::check_unique_params(resource)?;
+
+        Self::decode(encoded)
+    }
+
+    /// Rejects a reserved x402 query parameter of the resource URI that appears more than once,
+    /// or another Subscription Data before the last one, since only one of the values would be used
+    fn check_unique_params(resource: &str) -> X402UriResult<()> {
+        fn name(param: &str) -> &str {
+            param.split_once('=').map_or(param, |(name, _)| name)
+        }
+
+        let mut names = BTreeSet::new();
+
+        resource
+            .split_once('?')
+            .map_or("", |(_, query)| query)
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .try_for_each(|param| {
+                let reserved = Self::RESERVED_PARAMS.contains(&name(param));
+
+                if (reserved && !names.insert(name(param))) || Self::decode(param).is_ok() {
+                    return Err(X402UriError::DuplicateQueryParameter(
+                        name(param).to_string(),
+                    ));
+                }
+
+                Ok(())
+            })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSubscriptionData {
+    unsubscribe: String,
+    x402_payload: X402PaymentRequirements,
+}
+
+impl TryFrom<RawSubscriptionData> for X402SubscriptionData {
+    type Error = X402UriError;
+
+    fn try_from(value: RawSubscriptionData) -> Result<Self, Self::Error> {
+        let unsubscribe = X402Uri::new(&value.unsubscribe)
+            .map_err(|error| X402UriError::InvalidUnsubscribeUri(Box::new(error)))?;
+
+        if unsubscribe.action() != X402UriAction::Unsubscribe {
+            return Err(X402UriError::UnsubscribeUriActionMismatch);
+        }
+
+        value
+            .x402_payload
+            .max_amount_required
+            .parse::<u64>()
+            .or(Err(X402UriError::InvalidSubscriptionAmount))?;
+
+        Ok(Self {
+            unsubscribe: unsubscribe.to_buf(),
+            x402_payload: value.x402_payload,
+        })
+    }
+}
+
+/// The `PaymentRequirements` from the x402 specification embedded in the Subscription Data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402PaymentRequirements {
+    pub scheme: String,
+    pub network: String,
+    pub max_amount_required: String,
+    pub asset: String,
+    pub pay_to: String,
+    pub resource: String,
+    pub description: String,
+    pub mime_type: String,
+    pub max_timeout_seconds: u64,
+    pub output_schema: Option<serde_json::Value>,
+    pub extra: Option<X402PaymentRequirementsExtra>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X402PaymentRequirementsExtra {
+    pub fee_payer: Option<String>,
+    /// Any other scheme specific fields
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl X402Uri<'_> {
+    /// Decodes the Subscription Data of a `x402://subscribe/` URI
+    pub fn subscription_data(&self) -> X402UriResult<X402SubscriptionData> {
+        X402SubscriptionData::from_uri(self.action(), self.uri())
+    }
+}
+
+impl X402UriBuf {
+    /// Decodes the Subscription Data of a `x402://subscribe/` URI
+    pub fn subscription_data(&self) -> X402UriResult<X402SubscriptionData> {
+        X402SubscriptionData::from_uri(self.action(), self.uri())
+    }
+}
+
+impl X402UriBuilder {
+    /// Builds a `x402://subscribe/` URI with the Subscription Data appended to the URI of the resource
+    pub fn build_subscription(
+        &self,
+        subscription_data: &X402SubscriptionData,
+    ) -> X402UriResult<X402UriBuf> {
+        let mut builder = self.clone();
+        let built = builder.set_action(X402UriAction::Subscribe).build()?;
+        let separator = if built.uri().contains('?') { '&' } else { '?' };
+
+        let mut uri = built.uri().to_string();
+        uri.push(separator);
+        uri.push_str(&subscription_data.encode()?);
+
+        builder.set_uri(&uri).build()
+    }
+}
+
+#[cfg(test)]
+mod x402_subscription_sanity {
+    use super::*;
+
+    const SUBSCRIPTION_JSON: &str = r#"{
+        "unsubscribe": "x402://unsubscribe/https://example.com/apiv1/unsubscribe/So11111111111111111111111111111111111111112",
+        "x402Payload": {
+          "scheme": "exact",
+          "network": "solana",
+          "maxAmountRequired": "1000",
+          "asset": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+          "payTo": "2wKupLR9q6wXYppw8Gr2NvWxKBUqm4PPJKkQfoxHDBg4",
+          "resource": "https://example.com/weather",
+          "description": "Access to protected content",
+          "mimeType": "application/json",
+          "maxTimeoutSeconds": 60,
+          "outputSchema": null,
+          "extra": {
+            "feePayer": "EwWqGE4ZFKLofuestmU4LDdK7XM1N4ALgdZccwYugwGd"
+          }
+        }
+    }"#;
+
+    #[test]
+    fn test_decode_spec_example() {
+        let encoded = Base64::encode_string(SUBSCRIPTION_JSON.as_bytes());
+        let decoded = X402SubscriptionData::decode(&encoded).unwrap();
+
+        assert_eq!(decoded.unsubscribe.action(), X402UriAction::Unsubscribe);
+        assert_eq!(decoded.x402_payload.max_amount_required, "1000");
+        assert_eq!(
+            decoded
+                .x402_payload
+                .extra
+                .as_ref()
+                .unwrap()
+                .fee_payer
+                .as_deref(),
+            Some("EwWqGE4ZFKLofuestmU4LDdK7XM1N4ALgdZccwYugwGd")
+        );
+        assert_eq!(
+            X402SubscriptionData::decode(&decoded.encode().unwrap()).unwrap(),
+            decoded
+        );
+    }
+
+    #[test]
+    fn test_subscribe_uri_round_trip() {
+        let encoded = Base64::encode_string(SUBSCRIPTION_JSON.as_bytes());
+        let subscription_data = X402SubscriptionData::decode(&encoded).unwrap();
+
+        let built = X402UriBuf::builder()
+            .set_uri("https://example.com/x402/topic?author=Toly-The-Great")
+            .build_subscription(&subscription_data)
+            .unwrap();
+        assert_eq!(built.action(), X402UriAction::Subscribe);
+
+        let displayed = built.to_string();
+        let parsed = X402Uri::new(&displayed).unwrap();
+        assert_eq!(parsed.subscription_data().unwrap(), subscription_data);
+        assert_eq!(
+            X402SubscriptionData::split_uri(parsed.uri()).0,
+            "https://example.com/x402/topic?author=Toly-The-Great"
+        );
+
+        let unencoded = String::from("x402://subscribe/https://example.com/x402/topic?") + &encoded;
+        assert_eq!(
+            X402Uri::new(&unencoded)
+                .unwrap()
+                .subscription_data()
+                .unwrap(),
+            subscription_data
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let discover = X402Uri::new("x402://discover/https://example.com/x402?e30=").unwrap();
+        assert_eq!(
+            discover.subscription_data(),
+            Err(X402UriError::SubscriptionDataRequiresSubscribeAction)
+        );
+
+        let missing = X402Uri::new("x402://subscribe/https://example.com/x402/topic").unwrap();
+        assert_eq!(
+            missing.subscription_data(),
+            Err(X402UriError::MissingSubscriptionData)
+        );
+
+        assert_eq!(
+            X402SubscriptionData::decode("not base64!"),
+            Err(X402UriError::InvalidSubscriptionDataBase64)
+        );
+        assert!(matches!(
+            X402SubscriptionData::decode(&Base64::encode_string(b"{}")),
+            Err(X402UriError::InvalidSubscriptionDataJson(_))
+        ));
+
+        let invalid_unsubscribe =
+            SUBSCRIPTION_JSON.replace("x402://unsubscribe/https://", "x402://unsubscribe/ftp://");
+        assert_eq!(
+            X402SubscriptionData::decode(&Base64::encode_string(invalid_unsubscribe.as_bytes())),
+            Err(X402UriError::InvalidUnsubscribeUri(Box::new(
+                X402UriError::UnsupportedUriScheme
+            )))
+        );
+
+        let wrong_action = SUBSCRIPTION_JSON.replace("x402://unsubscribe/", "x402://subscribe/");
+        assert_eq!(
+            X402SubscriptionData::decode(&Base64::encode_string(wrong_action.as_bytes())),
+            Err(X402UriError::UnsubscribeUriActionMismatch)
+        );
+
+        let encoded = Base64::encode_string(SUBSCRIPTION_JSON.as_bytes());
+        let repeated =
+            String::from("x402://subscribe/https://example.com/x402/topic?author=a&author=b&")
+                + &encoded;
+        assert!(X402Uri::new(&repeated).unwrap().subscription_data().is_ok());
+        let duplicate =
+            String::from("x402://subscribe/https://example.com/x402/topic?amount=1&amount=2&")
+                + &encoded;
+        assert_eq!(
+            X402Uri::new(&duplicate).unwrap().subscription_data(),
+            Err(X402UriError::DuplicateQueryParameter("amount".to_string()))
+        );
+        let twice = String::from("x402://subscribe/https://example.com/x402/topic?")
+            + &encoded
+            + "&"
+            + &encoded;
+        assert!(matches!(
+            X402Uri::new(&twice).unwrap().subscription_data(),
+            Err(X402UriError::DuplicateQueryParameter(_))
+        ));
+
+        let invalid_amount = SUBSCRIPTION_JSON.replace("\"1000\"", "\"1.5\"");
+        assert_eq!(
+            X402SubscriptionData::decode(&Base64::encode_string(invalid_amount.as_bytes())),
+            Err(X402UriError::InvalidSubscriptionAmount)
+        );
+    }
+}